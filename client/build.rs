@@ -0,0 +1,64 @@
+//! Generates the client from the metadata of the runtime, as `node-template export-metadata`
+//! writes it to `metadata.scale` next to this file, or to the path in `NODE_TEMPLATE_METADATA`.
+//!
+//! Without the metadata the crate builds empty, with a warning, so that the workspace builds from
+//! a fresh checkout. The generated code is behind `cfg(metadata)`.
+
+use codec::Decode;
+use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed};
+use std::path::PathBuf;
+use subxt_codegen::{GeneratedTypeDerives, RuntimeGenerator};
+
+/// The pallets the client has typed calls, storage queries and events for.
+const PALLETS: [&str; 3] = ["TemplateModule", "MyPallet", "EVM"];
+
+/// Overrides where the metadata is read from.
+const METADATA_VAR: &str = "NODE_TEMPLATE_METADATA";
+
+fn main() {
+	println!("cargo:rerun-if-env-changed={}", METADATA_VAR);
+	let path = std::env::var_os(METADATA_VAR).map(PathBuf::from).unwrap_or_else(|| {
+		let dir = std::env::var("CARGO_MANIFEST_DIR").expect("set by cargo; qed");
+		PathBuf::from(dir).join("metadata.scale")
+	});
+	println!("cargo:rerun-if-changed={}", path.display());
+
+	println!("cargo:rustc-check-cfg=cfg(metadata)");
+
+	let encoded = match std::fs::read(&path) {
+		Ok(encoded) => encoded,
+		Err(e) => {
+			println!(
+				"cargo:warning=Not generating the client, cannot read the runtime metadata at {}: \
+				{}. Write it with `node-template export-metadata --scale {}`",
+				path.display(),
+				e,
+				path.display(),
+			);
+			return
+		},
+	};
+	let RuntimeMetadataPrefixed(magic, metadata) =
+		RuntimeMetadataPrefixed::decode(&mut &encoded[..]).unwrap_or_else(|e| {
+			panic!("{} is not SCALE-encoded runtime metadata: {}", path.display(), e)
+		});
+	let mut metadata = match metadata {
+		RuntimeMetadata::V14(metadata) => metadata,
+		_ => panic!("The runtime's metadata is not V14"),
+	};
+	metadata.pallets.retain(|pallet| PALLETS.contains(&pallet.name.as_str()));
+
+	let metadata = RuntimeMetadataPrefixed(magic, RuntimeMetadata::V14(metadata));
+	let generator = RuntimeGenerator::new(metadata);
+	let mut derives = GeneratedTypeDerives::default();
+	derives.append([syn::parse_quote!(Clone)].into_iter());
+	let item_mod: syn::ItemMod = syn::parse_quote!(
+		pub mod api {}
+	);
+	let api = generator.generate_runtime(item_mod, derives);
+
+	let out_dir = std::env::var("OUT_DIR").expect("set by cargo; qed");
+	std::fs::write(std::path::Path::new(&out_dir).join("api.rs"), api.to_string())
+		.expect("OUT_DIR is writable");
+	println!("cargo:rustc-cfg=metadata");
+}
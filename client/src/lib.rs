@@ -0,0 +1,49 @@
+//! A typed client for the node template, generated at build time from the metadata of its runtime.
+//!
+//! [`api`] has call builders, storage queries and events for `TemplateModule`, `MyPallet` and
+//! `EVM`:
+//!
+//! ```ignore
+//! let api = node_template_client::connect("ws://127.0.0.1:9944").await?;
+//! let signer = PairSigner::new(AccountKeyring::Alice.pair());
+//! let events = api
+//! 	.tx()
+//! 	.template_module()
+//! 	.do_something(42)?
+//! 	.sign_and_submit_then_watch_default(&signer)
+//! 	.await?
+//! 	.wait_for_finalized_success()
+//! 	.await?;
+//! let stored = events.find_first::<api::template_module::events::SomethingStored>()?;
+//! let something = api.storage().template_module().something(None).await?;
+//! ```
+//!
+//! Calls and storage queries fail if the node runs a runtime whose pallets differ from those of
+//! the runtime the client was generated from.
+//!
+//! Only [`subxt`] is exported when the crate is built without the metadata, see the build script.
+
+pub use subxt::{self, BasicError, DefaultConfig, PairSigner, SubstrateExtrinsicParams};
+
+#[cfg(metadata)]
+#[allow(clippy::all)]
+mod generated {
+	include!(concat!(env!("OUT_DIR"), "/api.rs"));
+}
+
+#[cfg(metadata)]
+pub use generated::api;
+
+/// The generated API, for the node template's configuration and signed extensions.
+#[cfg(metadata)]
+pub type NodeTemplateApi = api::RuntimeApi<DefaultConfig, SubstrateExtrinsicParams<DefaultConfig>>;
+
+/// Connect to the node at `url`, a WebSocket RPC endpoint, and check that it runs the pallets the
+/// client was generated for.
+#[cfg(metadata)]
+pub async fn connect(url: &str) -> Result<NodeTemplateApi, BasicError> {
+	let api: NodeTemplateApi =
+		subxt::ClientBuilder::new().set_url(url).build().await?.to_runtime_api();
+	api.validate_metadata()?;
+	Ok(api)
+}
@@ -0,0 +1,131 @@
+//! The generated client, against a node running in process.
+
+#![cfg(metadata)]
+
+use node_template::{
+	chain_spec::{truncated_evm_address, DEV_ERC20},
+	service::Sealing,
+	test_node::TestNode,
+};
+use node_template_client::{
+	api, connect,
+	subxt::{
+		rpc::{rpc_params, ClientT},
+		sp_core::{crypto::AccountId32, keccak_256, sr25519, Pair, H160, H256, U256},
+	},
+	DefaultConfig, NodeTemplateApi, PairSigner,
+};
+use sp_keyring::Sr25519Keyring::{Alice, Bob};
+
+type Signer = PairSigner<DefaultConfig, sr25519::Pair>;
+
+/// Signs for a keyring account, with the key types `subxt` uses, which may be those of another
+/// Substrate release than the node's.
+fn signer(name: &str) -> Signer {
+	Signer::new(sr25519::Pair::from_string(&format!("//{}", name), None).unwrap())
+}
+
+async fn start() -> (TestNode, NodeTemplateApi) {
+	let node = TestNode::builder().sealing(Sealing::Instant).start().await.unwrap();
+	let api = connect(node.ws_url()).await.unwrap();
+	(node, api)
+}
+
+/// `holder`'s balance of the development ERC-20, from the slot Solidity would keep it at.
+async fn erc20_balance(api: &NodeTemplateApi, holder: H160) -> U256 {
+	let mut key = [0u8; 64];
+	key[12..32].copy_from_slice(holder.as_bytes());
+	let slot = H256(keccak_256(&key));
+	let balance = api.storage().evm().account_storages(&DEV_ERC20, &slot, None).await.unwrap();
+	U256::from_big_endian(balance.as_bytes())
+}
+
+/// The gas price the node asks EVM transactions to pay.
+async fn gas_price(api: &NodeTemplateApi) -> U256 {
+	api.client.rpc().client.request("eth_gasPrice", rpc_params![]).await.unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn template_module_calls_storage_and_events() {
+	let (_node, api) = start().await;
+	let signer = signer("Alice");
+
+	let events = api
+		.tx()
+		.template_module()
+		.do_something(42)
+		.unwrap()
+		.sign_and_submit_then_watch_default(&signer)
+		.await
+		.unwrap()
+		.wait_for_in_block()
+		.await
+		.unwrap()
+		.wait_for_success()
+		.await
+		.unwrap();
+
+	let stored = events.find_first::<api::template_module::events::SomethingStored>().unwrap();
+	let api::template_module::events::SomethingStored(something, who) = stored.unwrap();
+	assert_eq!((something, who), (42, AccountId32::new(Alice.to_raw_public())));
+	assert_eq!(api.storage().template_module().something(None).await.unwrap(), Some(42));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn my_pallet_calls_and_storage() {
+	let (_node, api) = start().await;
+	assert_eq!(api.storage().my_pallet().stored_value(None).await.unwrap(), None);
+
+	api.tx()
+		.my_pallet()
+		.simple(7)
+		.unwrap()
+		.sign_and_submit_then_watch_default(&signer("Bob"))
+		.await
+		.unwrap()
+		.wait_for_in_block()
+		.await
+		.unwrap()
+		.wait_for_success()
+		.await
+		.unwrap();
+
+	assert_eq!(api.storage().my_pallet().stored_value(None).await.unwrap(), Some(7));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn evm_calls_storage_and_events() {
+	let (_node, api) = start().await;
+	let alice = truncated_evm_address(&Alice.to_account_id());
+	let bob = truncated_evm_address(&Bob.to_account_id());
+
+	assert!(!api.storage().evm().account_codes(&DEV_ERC20, None).await.unwrap().is_empty());
+	let before = erc20_balance(&api, bob).await;
+	let gas_price = gas_price(&api).await;
+
+	// `transfer(bob, 5)`, from the address Alice controls.
+	let mut input = vec![0xa9, 0x05, 0x9c, 0xbb];
+	input.extend_from_slice(H256::from(bob).as_bytes());
+	input.extend_from_slice(&H256::from_low_u64_be(5).0);
+	let events = api
+		.tx()
+		.evm()
+		.call(alice, DEV_ERC20, input, U256::zero(), 100_000, gas_price, None, None, vec![])
+		.unwrap()
+		.sign_and_submit_then_watch_default(&signer("Alice"))
+		.await
+		.unwrap()
+		.wait_for_in_block()
+		.await
+		.unwrap()
+		.wait_for_success()
+		.await
+		.unwrap();
+
+	let executed = events.find_first::<api::evm::events::Executed>().unwrap();
+	assert_eq!(executed.map(|executed| executed.0), Some(DEV_ERC20));
+	let log = events.find_first::<api::evm::events::Log>().unwrap().unwrap().0;
+	assert_eq!(log.address, DEV_ERC20);
+	assert_eq!(log.topics[1..], [H256::from(alice), H256::from(bob)]);
+	assert_eq!(erc20_balance(&api, bob).await, before + 5);
+}
@@ -1,12 +1,23 @@
 use node_template_runtime::{
-	AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig, Signature, SudoConfig,
-	SystemConfig, WASM_BINARY,
+	evm_currency, AccountId, AuraConfig, Balance, BalancesConfig, CouncilConfig,
+	DeployerAllowlistConfig, EVMConfig, EvmHardforkConfig, GenesisConfig, GrandpaConfig,
+	MyPalletConfig, Signature, SudoConfig, SystemConfig, TechnicalCommitteeConfig, EVM_CHAIN_ID,
+	SS58_PREFIX, TOKEN_DECIMALS, TOKEN_SYMBOL, WASM_BINARY,
 };
-use sc_service::ChainType;
+use pallet_evm::GenesisAccount;
+use pallet_evm_hardfork::Hardfork;
+use sc_service::{config::MultiaddrWithPeerId, ChainType, Properties};
+use serde::Deserialize;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
-use sp_core::{sr25519, Pair, Public};
+use sp_core::{
+	crypto::{Ss58AddressFormat, Ss58AddressFormatRegistry, Ss58Codec, DEV_PHRASE},
+	ecdsa,
+	hashing::keccak_256,
+	sr25519, Pair, Public, H160, H256, U256,
+};
 use sp_finality_grandpa::AuthorityId as GrandpaId;
 use sp_runtime::traits::{IdentifyAccount, Verify};
+use std::{collections::BTreeMap, path::Path};
 
 // The URL for the telemetry server.
 // const STAGING_TELEMETRY_URL: &str = "wss://telemetry.polkadot.io/submit/";
@@ -36,7 +47,115 @@ pub fn authority_keys_from_seed(s: &str) -> (AuraId, GrandpaId) {
 	(get_from_seed::<AuraId>(s), get_from_seed::<GrandpaId>(s))
 }
 
-pub fn development_config() -> Result<ChainSpec, String> {
+/// Generate the keys of `count` development EVM accounts, derived as `//0`, `//1`, … from the
+/// development phrase.
+pub fn dev_evm_keys(count: u32) -> Vec<ecdsa::Pair> {
+	(0..count)
+		.map(|i| {
+			ecdsa::Pair::from_string(&format!("{}//{}", DEV_PHRASE, i), None)
+				.expect("static values are valid; qed")
+		})
+		.collect()
+}
+
+/// The EVM address of an ECDSA public key.
+pub fn evm_address(public: &ecdsa::Public) -> H160 {
+	let public = libsecp256k1::PublicKey::parse_compressed(&public.0)
+		.expect("public keys of pairs are valid; qed");
+	H160::from_slice(&keccak_256(&public.serialize()[1..])[12..])
+}
+
+/// Balance given to each pre-funded account of the built-in chain specs.
+const ENDOWMENT: Balance = 1 << 60;
+
+fn endowed(accounts: Vec<AccountId>) -> Vec<(AccountId, Balance)> {
+	accounts.into_iter().map(|k| (k, ENDOWMENT)).collect()
+}
+
+/// The first `count` development EVM accounts, pre-funded.
+fn endowed_dev_evm_accounts(count: u32) -> BTreeMap<H160, GenesisAccount> {
+	dev_evm_keys(count)
+		.iter()
+		.map(|pair| {
+			let account = GenesisAccount {
+				balance: evm_currency::balance_to_wei(ENDOWMENT),
+				nonce: U256::zero(),
+				code: vec![],
+				storage: BTreeMap::new(),
+			};
+			(evm_address(&pair.public()), account)
+		})
+		.collect()
+}
+
+/// A minimal ERC-20 token, pre-deployed on the development chain. Alice's, Bob's and the
+/// development EVM accounts hold its supply.
+pub const DEV_ERC20: H160 = H160::repeat_byte(0xe2);
+
+/// The runtime code of [`DEV_ERC20`]: `transfer(address,uint256)`, emitting `Transfer`, and
+/// `balanceOf(address)`, with the balances in a mapping at slot 0.
+const DEV_ERC20_CODE: &str = "\
+	60003560e01c8063a9059cbb146032576370a0823114601d57600080fd5b6004356000526040600020546000\
+	5260206000f35b5033600052604060002080546024358082106099579003905560043560005260406000208054\
+	602435019055602435600052600435337fddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4d\
+	f523b3ef60206000a3600160005260206000f35b600080fd";
+
+/// The EVM address a Substrate account controls without linking one, see
+/// `pallet_account_link::Pallet::controls`.
+pub fn truncated_evm_address(account: &AccountId) -> H160 {
+	pallet_account_link::truncated_address(AsRef::<[u8]>::as_ref(account))
+		.expect("accounts have 32 bytes; qed")
+}
+
+/// The pre-deployed EVM accounts of the development chain: the first `dev_evm_accounts`
+/// development EVM accounts and the addresses Alice and Bob control, all pre-funded, and
+/// [`DEV_ERC20`].
+fn dev_evm_genesis(dev_evm_accounts: u32) -> BTreeMap<H160, GenesisAccount> {
+	let mut accounts = endowed_dev_evm_accounts(dev_evm_accounts);
+	for seed in ["Alice", "Bob"] {
+		let address = truncated_evm_address(&get_account_id_from_seed::<sr25519::Public>(seed));
+		let account = GenesisAccount {
+			balance: evm_currency::balance_to_wei(ENDOWMENT),
+			nonce: U256::zero(),
+			code: vec![],
+			storage: BTreeMap::new(),
+		};
+		accounts.insert(address, account);
+	}
+
+	// balanceOf(holder) is at keccak256(holder ‖ 0), as Solidity lays out mappings.
+	let storage = accounts
+		.keys()
+		.map(|holder| {
+			let mut key = [0u8; 64];
+			key[12..32].copy_from_slice(holder.as_bytes());
+			let balance = U256::from(ENDOWMENT);
+			(H256(keccak_256(&key)), H256(balance.into()))
+		})
+		.collect();
+	let token = GenesisAccount {
+		balance: U256::zero(),
+		nonce: U256::one(),
+		code: sp_core::bytes::from_hex(DEV_ERC20_CODE).expect("static values are valid; qed"),
+		storage,
+	};
+	accounts.insert(DEV_ERC20, token);
+	accounts
+}
+
+/// Token and address metadata read by wallets, derived from the runtime constants.
+pub fn chain_properties() -> Properties {
+	let mut properties = Properties::new();
+	properties.insert("tokenSymbol".into(), TOKEN_SYMBOL.into());
+	properties.insert("tokenDecimals".into(), TOKEN_DECIMALS.into());
+	properties.insert("ss58Format".into(), SS58_PREFIX.into());
+	properties.insert("evmChainId".into(), EVM_CHAIN_ID.into());
+	properties
+}
+
+/// The development chain, with `dev_evm_accounts` pre-funded EVM accounts, see [`dev_evm_keys`],
+/// and the EVM fixtures of [`dev_evm_genesis`].
+pub fn development_config(dev_evm_accounts: u32) -> Result<ChainSpec, String> {
 	let wasm_binary = WASM_BINARY.ok_or_else(|| "Development wasm not available".to_string())?;
 
 	Ok(ChainSpec::from_genesis(
@@ -52,13 +171,28 @@ pub fn development_config() -> Result<ChainSpec, String> {
 				vec![authority_keys_from_seed("Alice")],
 				// Sudo account
 				get_account_id_from_seed::<sr25519::Public>("Alice"),
-				// Pre-funded accounts
+				// Council
 				vec![
 					get_account_id_from_seed::<sr25519::Public>("Alice"),
 					get_account_id_from_seed::<sr25519::Public>("Bob"),
+				],
+				// Technical committee
+				vec![get_account_id_from_seed::<sr25519::Public>("Alice")],
+				// Pre-funded accounts
+				endowed(vec![
+					get_account_id_from_seed::<sr25519::Public>("Alice"),
+					get_account_id_from_seed::<sr25519::Public>("Bob"),
 					get_account_id_from_seed::<sr25519::Public>("Alice//stash"),
 					get_account_id_from_seed::<sr25519::Public>("Bob//stash"),
-				],
+				]),
+				// Pre-deployed EVM accounts
+				dev_evm_genesis(dev_evm_accounts),
+				// EVM hardfork rules
+				Hardfork::London,
+				// Contract deployment is open to everyone
+				None,
+				// mypallet initial value
+				0,
 				true,
 			)
 		},
@@ -68,9 +202,10 @@ pub fn development_config() -> Result<ChainSpec, String> {
 		None,
 		// Protocol ID
 		None,
+		// Fork ID
 		None,
 		// Properties
-		None,
+		Some(chain_properties()),
 		// Extensions
 		None,
 	))
@@ -92,10 +227,21 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 				vec![authority_keys_from_seed("Alice"), authority_keys_from_seed("Bob")],
 				// Sudo account
 				get_account_id_from_seed::<sr25519::Public>("Alice"),
-				// Pre-funded accounts
+				// Council
+				vec![
+					get_account_id_from_seed::<sr25519::Public>("Alice"),
+					get_account_id_from_seed::<sr25519::Public>("Bob"),
+					get_account_id_from_seed::<sr25519::Public>("Charlie"),
+				],
+				// Technical committee
 				vec![
 					get_account_id_from_seed::<sr25519::Public>("Alice"),
 					get_account_id_from_seed::<sr25519::Public>("Bob"),
+				],
+				// Pre-funded accounts
+				endowed(vec![
+					get_account_id_from_seed::<sr25519::Public>("Alice"),
+					get_account_id_from_seed::<sr25519::Public>("Bob"),
 					get_account_id_from_seed::<sr25519::Public>("Charlie"),
 					get_account_id_from_seed::<sr25519::Public>("Dave"),
 					get_account_id_from_seed::<sr25519::Public>("Eve"),
@@ -106,7 +252,15 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 					get_account_id_from_seed::<sr25519::Public>("Dave//stash"),
 					get_account_id_from_seed::<sr25519::Public>("Eve//stash"),
 					get_account_id_from_seed::<sr25519::Public>("Ferdie//stash"),
-				],
+				]),
+				// Pre-deployed EVM accounts
+				BTreeMap::new(),
+				// EVM hardfork rules
+				Hardfork::London,
+				// Contract deployment is open to everyone
+				None,
+				// mypallet initial value
+				0,
 				true,
 			)
 		},
@@ -116,9 +270,10 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 		None,
 		// Protocol ID
 		None,
-		// Properties
-		None,
+		// Fork ID
 		None,
+		// Properties
+		Some(chain_properties()),
 		// Extensions
 		None,
 	))
@@ -129,7 +284,13 @@ fn testnet_genesis(
 	wasm_binary: &[u8],
 	initial_authorities: Vec<(AuraId, GrandpaId)>,
 	root_key: AccountId,
-	endowed_accounts: Vec<AccountId>,
+	council: Vec<AccountId>,
+	technical_committee: Vec<AccountId>,
+	endowed_accounts: Vec<(AccountId, Balance)>,
+	evm_accounts: BTreeMap<H160, GenesisAccount>,
+	evm_hardfork: Hardfork,
+	deployers: Option<Vec<H160>>,
+	init_val: u32,
 	_enable_println: bool,
 ) -> GenesisConfig {
 	GenesisConfig {
@@ -137,10 +298,7 @@ fn testnet_genesis(
 			// Add Wasm runtime to storage.
 			code: wasm_binary.to_vec(),
 		},
-		balances: BalancesConfig {
-			// Configure endowed accounts with initial balance of 1 << 60.
-			balances: endowed_accounts.iter().cloned().map(|k| (k, 1 << 60)).collect(),
-		},
+		balances: BalancesConfig { balances: endowed_accounts },
 		aura: AuraConfig {
 			authorities: initial_authorities.iter().map(|x| (x.0.clone())).collect(),
 		},
@@ -152,5 +310,499 @@ fn testnet_genesis(
 			key: Some(root_key),
 		},
 		transaction_payment: Default::default(),
+		democracy: Default::default(),
+		council: CouncilConfig { members: council, phantom: Default::default() },
+		technical_committee: TechnicalCommitteeConfig {
+			members: technical_committee,
+			phantom: Default::default(),
+		},
+		evm: EVMConfig { accounts: evm_accounts },
+		ethereum: Default::default(),
+		evm_hardfork: EvmHardforkConfig { hardfork: evm_hardfork },
+		deployer_allowlist: DeployerAllowlistConfig {
+			enforced: deployers.is_some(),
+			deployers: deployers.unwrap_or_default(),
+		},
+		maintenance_mode: Default::default(),
+		my_pallet: MyPalletConfig { init_val },
+	}
+}
+
+/// A human-written description of a chain, see `node/res/testnet.toml` for an example.
+///
+/// Accounts and keys are either SS58 addresses or secret URIs starting with `//` (e.g. `//Alice`),
+/// which derive from the public development phrase and are therefore only accepted when
+/// `chain_type` is `Development` or `Local`. Balances are given in the smallest unit, as integers or
+/// decimal strings since TOML integers cannot hold a `u128`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChainConfig {
+	/// Human-readable name of the chain.
+	pub name: String,
+	/// Chain id, also used as the database directory name.
+	pub id: String,
+	#[serde(default = "ChainConfig::default_chain_type")]
+	pub chain_type: ChainType,
+	#[serde(default)]
+	pub boot_nodes: Vec<MultiaddrWithPeerId>,
+	#[serde(default)]
+	pub protocol_id: Option<String>,
+	/// Extra chain properties. The token symbol, decimals, address format and EVM chain id are
+	/// filled in from the runtime and may only be repeated here with the same values.
+	#[serde(default)]
+	pub properties: Option<Properties>,
+	pub authorities: Vec<AuthorityConfig>,
+	pub sudo: String,
+	#[serde(default)]
+	pub council: Vec<String>,
+	#[serde(default)]
+	pub technical_committee: Vec<String>,
+	#[serde(default)]
+	pub endowed_accounts: Vec<EndowedAccountConfig>,
+	/// Pre-funded EVM accounts and pre-deployed contracts.
+	#[serde(default)]
+	pub evm_accounts: Vec<EvmAccountConfig>,
+	/// The hardfork rules the EVM starts with, `London` by default.
+	#[serde(default)]
+	pub evm_hardfork: Hardfork,
+	/// The EVM accounts allowed to deploy contracts. Deployment is open to everyone if omitted.
+	#[serde(default)]
+	pub deployers: Option<Vec<H160>>,
+	#[serde(default)]
+	pub my_pallet: MyPalletGenesisConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthorityConfig {
+	pub aura: String,
+	pub grandpa: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EndowedAccountConfig {
+	pub account: String,
+	pub balance: Amount,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EvmAccountConfig {
+	pub address: H160,
+	#[serde(default)]
+	pub balance: Amount,
+	#[serde(default)]
+	pub nonce: Amount,
+	/// Hex-encoded runtime bytecode of a pre-deployed contract.
+	#[serde(default)]
+	pub code: Option<String>,
+	#[serde(default)]
+	pub storage: BTreeMap<H256, H256>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MyPalletGenesisConfig {
+	#[serde(default)]
+	pub init_val: u32,
+}
+
+/// An integer amount written either as a number or as a decimal string.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Amount {
+	Number(u64),
+	String(String),
+}
+
+impl Default for Amount {
+	fn default() -> Self {
+		Amount::Number(0)
+	}
+}
+
+impl Amount {
+	fn to_u256(&self) -> Result<U256, String> {
+		match self {
+			Amount::Number(n) => Ok((*n).into()),
+			Amount::String(s) => U256::from_dec_str(&s.replace('_', ""))
+				.map_err(|e| format!("Invalid amount {:?}: {:?}", s, e)),
+		}
+	}
+
+	fn to_balance(&self) -> Result<Balance, String> {
+		let amount = self.to_u256()?;
+		amount
+			.try_into()
+			.map_err(|_| format!("Amount {} does not fit a balance", amount))
+	}
+}
+
+impl ChainConfig {
+	fn default_chain_type() -> ChainType {
+		ChainType::Live
+	}
+
+	/// Read a chain config from a `.json` file, or from TOML otherwise.
+	pub fn load(path: &Path) -> Result<Self, String> {
+		let content = std::fs::read_to_string(path)
+			.map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+		let config = if path.extension().map_or(false, |ext| ext == "json") {
+			serde_json::from_str(&content).map_err(|e| e.to_string())
+		} else {
+			toml::from_str(&content).map_err(|e| e.to_string())
+		};
+		config.map_err(|e| format!("Error parsing {}: {}", path.display(), e))
+	}
+
+	/// The configured properties on top of [`chain_properties`].
+	fn properties(&self) -> Result<Properties, String> {
+		let mut properties = chain_properties();
+		for (key, value) in self.properties.iter().flatten() {
+			match properties.get(key) {
+				Some(expected) if expected != value =>
+					return Err(format!(
+						"Property {} is {} but the runtime uses {}",
+						key, value, expected
+					)),
+				_ => {
+					properties.insert(key.clone(), value.clone());
+				},
+			}
+		}
+		Ok(properties)
+	}
+
+	/// Check the config and turn it into the arguments of `testnet_genesis`.
+	fn resolve(&self) -> Result<ResolvedGenesis, String> {
+		if self.authorities.is_empty() {
+			return Err("At least one authority is required".into())
+		}
+		let dev_keys = matches!(self.chain_type, ChainType::Development | ChainType::Local);
+		let allowed = |s: &str| -> Result<(), String> {
+			if s.starts_with("//") && !dev_keys {
+				return Err(format!(
+					"Secret URI {:?} derives from the development phrase and is only allowed on \
+					 Development and Local chains",
+					s
+				))
+			}
+			Ok(())
+		};
+		let authorities = self
+			.authorities
+			.iter()
+			.map(|a| {
+				allowed(&a.aura)?;
+				allowed(&a.grandpa)?;
+				Ok((parse_public::<AuraId>(&a.aura)?, parse_public::<GrandpaId>(&a.grandpa)?))
+			})
+			.collect::<Result<_, String>>()?;
+		let account = |s: &str| -> Result<AccountId, String> {
+			allowed(s)?;
+			parse_account(s)
+		};
+		let accounts = |list: &[String]| -> Result<Vec<AccountId>, String> {
+			list.iter().map(|a| account(a)).collect()
+		};
+
+		let mut endowed_accounts = Vec::new();
+		for endowed in &self.endowed_accounts {
+			let who = account(&endowed.account)?;
+			if endowed_accounts.iter().any(|(a, _)| *a == who) {
+				return Err(format!("Account {} is endowed twice", endowed.account))
+			}
+			endowed_accounts.push((who, endowed.balance.to_balance()?));
+		}
+
+		let mut evm_accounts = BTreeMap::new();
+		for evm in &self.evm_accounts {
+			let code = match &evm.code {
+				Some(code) => sp_core::bytes::from_hex(code)
+					.map_err(|e| format!("Invalid code for {:?}: {}", evm.address, e))?,
+				None => Vec::new(),
+			};
+			let balance = evm.balance.to_u256()?;
+			if evm_currency::wei_to_balance_exact(balance).is_none() {
+				return Err(format!(
+					"EVM balance of {:?} must be a multiple of {} wei",
+					evm.address,
+					evm_currency::WEI_PER_BALANCE
+				))
+			}
+			let account = GenesisAccount {
+				balance,
+				nonce: evm.nonce.to_u256()?,
+				code,
+				storage: evm.storage.clone(),
+			};
+			if evm_accounts.insert(evm.address, account).is_some() {
+				return Err(format!("EVM account {:?} is listed twice", evm.address))
+			}
+		}
+
+		Ok(ResolvedGenesis {
+			authorities,
+			root_key: account(&self.sudo)?,
+			council: accounts(&self.council)?,
+			technical_committee: accounts(&self.technical_committee)?,
+			endowed_accounts,
+			evm_accounts,
+			evm_hardfork: self.evm_hardfork,
+			deployers: self.deployers.clone(),
+			init_val: self.my_pallet.init_val,
+		})
+	}
+}
+
+/// The validated contents of a [`ChainConfig`].
+#[derive(Clone)]
+struct ResolvedGenesis {
+	authorities: Vec<(AuraId, GrandpaId)>,
+	root_key: AccountId,
+	council: Vec<AccountId>,
+	technical_committee: Vec<AccountId>,
+	endowed_accounts: Vec<(AccountId, Balance)>,
+	evm_accounts: BTreeMap<H160, GenesisAccount>,
+	evm_hardfork: Hardfork,
+	deployers: Option<Vec<H160>>,
+	init_val: u32,
+}
+
+/// Parse a public key from an SS58 address or a `//` secret URI.
+fn parse_public<TPublic: Public + Ss58Codec>(s: &str) -> Result<TPublic, String> {
+	if s.starts_with("//") {
+		TPublic::Pair::from_string(s, None)
+			.map(|pair| pair.public())
+			.map_err(|e| format!("Invalid secret URI {:?}: {:?}", s, e))
+	} else {
+		parse_ss58(s)
+	}
+}
+
+/// Parse an address in this chain's format or in the generic Substrate format.
+fn parse_ss58<T: Ss58Codec>(s: &str) -> Result<T, String> {
+	let (public, format) = T::from_ss58check_with_version(s)
+		.map_err(|e| format!("Invalid address {:?}: {:?}", s, e))?;
+	if format != Ss58AddressFormat::custom(SS58_PREFIX) &&
+		format != Ss58AddressFormat::from(Ss58AddressFormatRegistry::SubstrateAccount)
+	{
+		return Err(format!("Address {:?} has the wrong SS58 format {}", s, format.prefix()))
+	}
+	Ok(public)
+}
+
+/// Parse an account from an SS58 address or a `//` secret URI of an sr25519 key.
+fn parse_account(s: &str) -> Result<AccountId, String> {
+	if s.starts_with("//") {
+		Ok(AccountPublic::from(parse_public::<sr25519::Public>(s)?).into_account())
+	} else {
+		parse_ss58(s)
+	}
+}
+
+/// Build a chain spec from the [`ChainConfig`] at `path`.
+pub fn from_config(path: &Path) -> Result<ChainSpec, String> {
+	let wasm_binary = WASM_BINARY.ok_or_else(|| "Wasm binary not available".to_string())?;
+	let config = ChainConfig::load(path)?;
+	let genesis = config.resolve()?;
+	let properties = config.properties()?;
+	let protocol_id = config.protocol_id.clone();
+
+	Ok(ChainSpec::from_genesis(
+		&config.name,
+		&config.id,
+		config.chain_type.clone(),
+		move || {
+			let genesis = genesis.clone();
+			testnet_genesis(
+				wasm_binary,
+				genesis.authorities,
+				genesis.root_key,
+				genesis.council,
+				genesis.technical_committee,
+				genesis.endowed_accounts,
+				genesis.evm_accounts,
+				genesis.evm_hardfork,
+				genesis.deployers,
+				genesis.init_val,
+				true,
+			)
+		},
+		config.boot_nodes,
+		None,
+		protocol_id.as_deref(),
+		None,
+		Some(properties),
+		None,
+	))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::Get;
+
+	fn example() -> ChainConfig {
+		ChainConfig::load(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/res/testnet.toml")))
+			.unwrap()
+	}
+
+	#[test]
+	fn example_config_resolves() {
+		let genesis = example().resolve().unwrap();
+		assert_eq!(genesis.authorities, vec![authority_keys_from_seed("Alice")]);
+		assert_eq!(genesis.root_key, get_account_id_from_seed::<sr25519::Public>("Alice"));
+		assert_eq!(
+			genesis.endowed_accounts[0],
+			(get_account_id_from_seed::<sr25519::Public>("Alice"), 1_000_000_000_000_000_000)
+		);
+		assert_eq!(genesis.evm_accounts.len(), 2);
+		assert_eq!(genesis.evm_hardfork, Hardfork::Berlin);
+		assert_eq!(
+			genesis.deployers,
+			Some(vec!["0xd43593c715fdd31c61141abd04a99fd6822c8558".parse().unwrap()])
+		);
+		assert_eq!(genesis.init_val, 8888);
+	}
+
+	#[test]
+	fn json_and_toml_configs_are_equivalent() {
+		let json = r#"{
+			"name": "Test",
+			"id": "test",
+			"chain_type": "Local",
+			"authorities": [{ "aura": "//Alice", "grandpa": "//Alice" }],
+			"sudo": "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY",
+			"endowed_accounts": [{ "account": "//Bob", "balance": "1_000" }]
+		}"#;
+		let toml = r#"
+			name = "Test"
+			id = "test"
+			chain_type = "Local"
+			sudo = "//Alice"
+			authorities = [{ aura = "//Alice", grandpa = "//Alice" }]
+			endowed_accounts = [{ account = "//Bob", balance = 1000 }]
+		"#;
+		let json = serde_json::from_str::<ChainConfig>(json).unwrap().resolve().unwrap();
+		let toml = toml::from_str::<ChainConfig>(toml).unwrap().resolve().unwrap();
+		assert_eq!(json.root_key, toml.root_key);
+		assert_eq!(json.endowed_accounts, toml.endowed_accounts);
+		assert_eq!(json.deployers, None);
+	}
+
+	#[test]
+	fn invalid_configs_are_rejected() {
+		let mut config = example();
+		config.sudo = "not an address".into();
+		assert!(config.resolve().unwrap_err().contains("Invalid address"));
+
+		let mut config = example();
+		config.endowed_accounts.push(config.endowed_accounts[0].clone());
+		assert!(config.resolve().unwrap_err().contains("endowed twice"));
+
+		let mut config = example();
+		config.authorities.clear();
+		assert!(config.resolve().is_err());
+
+		let mut config = example();
+		config.endowed_accounts[0].balance = Amount::String(U256::MAX.to_string());
+		assert!(config.resolve().unwrap_err().contains("does not fit"));
+
+		let mut config = example();
+		config.sudo = AccountId::from(sr25519::Public([1; 32]))
+			.to_ss58check_with_version(Ss58AddressFormatRegistry::PolkadotAccount.into());
+		assert!(config.resolve().unwrap_err().contains("wrong SS58 format"));
+
+		let mut config = example();
+		config.evm_accounts[0].balance = Amount::Number(evm_currency::WEI_PER_BALANCE as u64 + 1);
+		assert!(config.resolve().unwrap_err().contains("must be a multiple"));
+	}
+
+	#[test]
+	fn secret_uris_are_only_accepted_on_test_chains() {
+		let mut config = example();
+		config.chain_type = ChainType::Development;
+		assert!(config.resolve().is_ok());
+
+		config.chain_type = ChainType::Live;
+		assert!(config.resolve().unwrap_err().contains("development phrase"));
+
+		// Accounts are checked as well as authority keys.
+		let (aura, grandpa) = authority_keys_from_seed("Alice");
+		config.authorities =
+			vec![AuthorityConfig { aura: aura.to_ss58check(), grandpa: grandpa.to_ss58check() }];
+		config.sudo = "//Bob".into();
+		assert!(config.resolve().unwrap_err().contains("\"//Bob\""));
+	}
+
+	#[test]
+	fn addresses_in_the_chain_format_are_accepted() {
+		let alice = get_account_id_from_seed::<sr25519::Public>("Alice");
+		let mut config = example();
+		config.sudo = alice.to_ss58check_with_version(Ss58AddressFormat::custom(SS58_PREFIX));
+		assert_eq!(config.resolve().unwrap().root_key, alice);
+	}
+
+	fn assert_matches_runtime(properties: &Properties) {
+		assert_eq!(properties["tokenSymbol"], TOKEN_SYMBOL);
+		assert_eq!(properties["tokenDecimals"], TOKEN_DECIMALS);
+		assert_eq!(properties["ss58Format"], SS58_PREFIX);
+		assert_eq!(properties["evmChainId"], EVM_CHAIN_ID);
+		assert_eq!(
+			SS58_PREFIX,
+			<node_template_runtime::Runtime as frame_system::Config>::SS58Prefix::get()
+		);
+		assert_eq!(
+			EVM_CHAIN_ID,
+			<node_template_runtime::Runtime as pallet_evm::Config>::ChainId::get()
+		);
+	}
+
+	#[test]
+	fn chain_specs_agree_with_the_runtime() {
+		use sc_service::ChainSpec as _;
+
+		assert_matches_runtime(&development_config(0).unwrap().properties());
+		assert_matches_runtime(&local_testnet_config().unwrap().properties());
+		assert_matches_runtime(&example().properties().unwrap());
+	}
+
+	#[test]
+	fn dev_evm_accounts_are_endowed() {
+		let accounts = endowed_dev_evm_accounts(3);
+		assert_eq!(accounts.len(), 3);
+		for (pair, (address, account)) in dev_evm_keys(3).iter().zip(accounts) {
+			assert_eq!(evm_address(&pair.public()), address);
+			assert_eq!(account.balance, evm_currency::balance_to_wei(ENDOWMENT));
+		}
+		assert!(endowed_dev_evm_accounts(0).is_empty());
+	}
+
+	#[test]
+	fn dev_erc20_holders_are_funded_in_the_evm() {
+		let accounts = dev_evm_genesis(1);
+		let token = &accounts[&DEV_ERC20];
+		assert_eq!(token.code[..5], [0x60, 0x00, 0x35, 0x60, 0xe0]);
+
+		let holders: Vec<_> = accounts.keys().filter(|address| **address != DEV_ERC20).collect();
+		assert_eq!(holders.len(), 3);
+		assert_eq!(token.storage.len(), holders.len());
+		let bob = truncated_evm_address(&get_account_id_from_seed::<sr25519::Public>("Bob"));
+		assert!(holders.contains(&&bob));
+	}
+
+	#[test]
+	fn conflicting_properties_are_rejected() {
+		let mut config = example();
+		let properties = config.properties.get_or_insert_with(Default::default);
+		properties.insert("tokenDecimals".into(), 18.into());
+		assert!(config.properties().unwrap_err().contains("tokenDecimals"));
+
+		let mut config = example();
+		let properties = config.properties.get_or_insert_with(Default::default);
+		properties.insert("website".into(), "example.com".into());
+		assert_eq!(config.properties().unwrap()["website"], "example.com");
 	}
 }
@@ -1,4 +1,7 @@
+use crate::{command_helper::Workload, load_test::Mix, service::Sealing, tx::Mortality};
 use sc_cli::RunCmd;
+use sp_core::H256;
+use std::path::PathBuf;
 
 #[derive(Debug, clap::Parser)]
 pub struct Cli {
@@ -7,6 +10,17 @@ pub struct Cli {
 
 	#[clap(flatten)]
 	pub run: RunCmd,
+
+	/// Seal blocks on demand instead of running Aura and GRANDPA, and enable the Hardhat-style
+	/// `evm_*` and `hardhat_*` RPCs. Development chains only.
+	#[clap(long, arg_enum)]
+	pub sealing: Option<Sealing>,
+
+	/// Endow this many EVM accounts, derived from the dev phrase as `//0`, `//1`, … ECDSA keys, in
+	/// the `dev` chain, and sign for them through `eth_sendTransaction` and `eth_sign`.
+	/// The count is part of the genesis, so changing it needs a fresh database.
+	#[clap(long, value_name = "N", default_value = "0")]
+	pub dev_accounts: u32,
 }
 
 #[derive(Debug, clap::Subcommand)]
@@ -16,7 +30,7 @@ pub enum Subcommand {
 	Key(sc_cli::KeySubcommand),
 
 	/// Build a chain specification.
-	BuildSpec(sc_cli::BuildSpecCmd),
+	BuildSpec(BuildSpecCmd),
 
 	/// Validate blocks.
 	CheckBlock(sc_cli::CheckBlockCmd),
@@ -37,8 +51,16 @@ pub enum Subcommand {
 	Revert(sc_cli::RevertCmd),
 
 	/// Sub-commands concerned with benchmarking.
-	#[clap(subcommand)]
-	Benchmark(frame_benchmarking_cli::BenchmarkCmd),
+	Benchmark(BenchmarkCmd),
+
+	/// Flood a node with transactions and report how many are included, and how fast.
+	LoadTest(LoadTestCmd),
+
+	/// Build a runtime call from JSON arguments, sign it, and print or submit it.
+	Tx(TxCmd),
+
+	/// Write the metadata of the runtime this binary was built with, as SCALE and JSON.
+	ExportMetadata(ExportMetadataCmd),
 
 	/// Try some command against runtime state.
 	#[cfg(feature = "try-runtime")]
@@ -51,3 +73,136 @@ pub enum Subcommand {
 	/// Db meta columns information.
 	ChainInfo(sc_cli::ChainInfoCmd),
 }
+
+#[derive(Debug, clap::Parser)]
+pub struct BuildSpecCmd {
+	#[clap(flatten)]
+	pub inner: sc_cli::BuildSpecCmd,
+
+	/// Build the spec from a TOML or JSON chain config instead of `--chain`.
+	///
+	/// See `node/res/testnet.toml` for the format.
+	#[clap(long, value_name = "PATH", parse(from_os_str))]
+	pub from_config: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Parser)]
+pub struct BenchmarkCmd {
+	#[clap(subcommand)]
+	pub inner: frame_benchmarking_cli::BenchmarkCmd,
+
+	/// The extrinsics `benchmark overhead` measures.
+	#[clap(long, arg_enum, default_value = "remark", global = true)]
+	pub workload: Workload,
+}
+
+#[derive(Debug, Clone, clap::Parser)]
+pub struct LoadTestCmd {
+	/// The WebSocket RPC endpoint of the node to load, e.g. `ws://127.0.0.1:9944`. Without it, a
+	/// development node is started in process, with the options below.
+	#[clap(long, value_name = "URL")]
+	pub url: Option<String>,
+
+	/// Transactions to submit per second, at most.
+	#[clap(long, default_value = "100")]
+	pub rate: u32,
+
+	/// How long to submit transactions for, in seconds.
+	#[clap(long, value_name = "SECONDS", default_value = "30")]
+	pub duration: u64,
+
+	/// How long to wait for submitted transactions to be included, in seconds.
+	#[clap(long, value_name = "SECONDS", default_value = "30")]
+	pub drain: u64,
+
+	/// The transactions to submit, with their relative frequency: `transfer` for balance
+	/// transfers, `simple` for `mypallet::simple` calls and `evm` for ERC-20 transfers in Ethereum
+	/// transactions.
+	#[clap(long, default_value = "transfer=1,simple=1,evm=1")]
+	pub mix: Mix,
+
+	/// Sign the Ethereum transactions with this many of the ECDSA development keys.
+	#[clap(long, value_name = "N", default_value = "4")]
+	pub evm_senders: u32,
+
+	#[clap(flatten)]
+	pub node: RunCmd,
+}
+
+#[derive(Debug, Clone, clap::Parser)]
+pub struct TxCmd {
+	/// The pallet, as named in the runtime, e.g. `TemplateModule`.
+	pub pallet: String,
+
+	/// The call, e.g. `do_something`.
+	pub call: String,
+
+	/// The arguments of the call, in order, as JSON. Arguments that are not JSON are strings, so
+	/// SS58 addresses need no quotes. Enum variants, including nested calls such as the one
+	/// `Sudo::sudo` takes, are written `{"Variant": fields}`, e.g.
+	/// `{"TemplateModule": {"do_something": {"something": 42}}}`.
+	pub args: Vec<String>,
+
+	/// The secret URI of the sr25519 key to sign with, e.g. `//Alice`.
+	#[clap(long, value_name = "SURI")]
+	pub suri: String,
+
+	/// The nonce to sign with, by default the signer's next one according to the node.
+	#[clap(long)]
+	pub nonce: Option<u32>,
+
+	/// `immortal`, or about how many blocks the transaction is valid for, from 4 to 65536.
+	#[clap(long, value_name = "BLOCKS", default_value = "64")]
+	pub era: Mortality,
+
+	/// Paid to the block author on top of the fee, for priority.
+	#[clap(long, default_value = "0")]
+	pub tip: u128,
+
+	/// The WebSocket RPC endpoint of the node.
+	#[clap(long, value_name = "URL", default_value = "ws://127.0.0.1:9944")]
+	pub url: String,
+
+	/// Submit the signed extrinsic instead of printing it as hex.
+	#[clap(long, conflicts_with = "offline")]
+	pub submit: bool,
+
+	/// Sign without connecting to the node. Needs `--nonce`, `--genesis-hash`, and for a mortal
+	/// transaction `--block-hash` and `--block-number`.
+	#[clap(long)]
+	pub offline: bool,
+
+	/// The genesis hash of the chain, by default the node's.
+	#[clap(long, value_name = "HASH")]
+	pub genesis_hash: Option<H256>,
+
+	/// The block the transaction's era starts at, by default the node's finalized head.
+	#[clap(long, value_name = "HASH", requires = "block_number")]
+	pub block_hash: Option<H256>,
+
+	/// The number of `--block-hash`.
+	#[clap(long, value_name = "NUMBER", requires = "block_hash")]
+	pub block_number: Option<u32>,
+
+	/// The spec version of the runtime to sign for, by default the one this binary was built
+	/// with, e.g. to sign offline for a chain that upgraded since.
+	#[clap(long, value_name = "VERSION")]
+	pub spec_version: Option<u32>,
+
+	/// The transaction version of the runtime to sign for, by default the one this binary was
+	/// built with. Calls are still encoded as in the built runtime, so only change it when the
+	/// calls being signed are encoded the same way in both.
+	#[clap(long, value_name = "VERSION")]
+	pub transaction_version: Option<u32>,
+}
+
+#[derive(Debug, clap::Parser)]
+pub struct ExportMetadataCmd {
+	/// Where to write the SCALE-encoded metadata, as `state_getMetadata` returns it.
+	#[clap(long, value_name = "PATH", parse(from_os_str), default_value = "metadata.scale")]
+	pub scale: PathBuf,
+
+	/// Where to write the metadata as JSON.
+	#[clap(long, value_name = "PATH", parse(from_os_str), default_value = "metadata.json")]
+	pub json: PathBuf,
+}
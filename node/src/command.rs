@@ -1,8 +1,8 @@
 use crate::{
 	chain_spec,
-	cli::{Cli, Subcommand},
-	command_helper::{inherent_benchmark_data, BenchmarkExtrinsicBuilder},
-	service,
+	cli::{self, Cli, Subcommand},
+	command_helper::{inherent_benchmark_data, BenchmarkExtrinsicBuilder, Workload},
+	load_test, metadata, service, tx,
 };
 use frame_benchmarking_cli::{BenchmarkCmd, SUBSTRATE_REFERENCE_HARDWARE};
 use node_template_runtime::Block;
@@ -37,8 +37,10 @@ impl SubstrateCli for Cli {
 
 	fn load_spec(&self, id: &str) -> Result<Box<dyn sc_service::ChainSpec>, String> {
 		Ok(match id {
-			"dev" => Box::new(chain_spec::development_config()?),
+			"dev" => Box::new(chain_spec::development_config(self.dev_accounts)?),
 			"" | "local" => Box::new(chain_spec::local_testnet_config()?),
+			path if path.ends_with(".toml") =>
+				Box::new(chain_spec::from_config(std::path::Path::new(path))?),
 			path =>
 				Box::new(chain_spec::ChainSpec::from_json_file(std::path::PathBuf::from(path))?),
 		})
@@ -56,28 +58,36 @@ pub fn run() -> sc_cli::Result<()> {
 	match &cli.subcommand {
 		Some(Subcommand::Key(cmd)) => cmd.run(&cli),
 		Some(Subcommand::BuildSpec(cmd)) => {
-			let runner = cli.create_runner(cmd)?;
-			runner.sync_run(|config| cmd.run(config.chain_spec, config.network))
+			let runner = cli.create_runner(&cmd.inner)?;
+			runner.sync_run(|config| {
+				let spec: Box<dyn ChainSpec> = match &cmd.from_config {
+					Some(path) => Box::new(chain_spec::from_config(path)?),
+					None => config.chain_spec,
+				};
+				cmd.inner.run(spec, config.network)
+			})
 		},
 		Some(Subcommand::CheckBlock(cmd)) => {
 			let runner = cli.create_runner(cmd)?;
 			runner.async_run(|config| {
 				let PartialComponents { client, task_manager, import_queue, .. } =
-					service::new_partial(&config)?;
+					service::new_partial(&config, None)?;
 				Ok((cmd.run(client, import_queue), task_manager))
 			})
 		},
 		Some(Subcommand::ExportBlocks(cmd)) => {
 			let runner = cli.create_runner(cmd)?;
 			runner.async_run(|config| {
-				let PartialComponents { client, task_manager, .. } = service::new_partial(&config)?;
+				let PartialComponents { client, task_manager, .. } =
+					service::new_partial(&config, None)?;
 				Ok((cmd.run(client, config.database), task_manager))
 			})
 		},
 		Some(Subcommand::ExportState(cmd)) => {
 			let runner = cli.create_runner(cmd)?;
 			runner.async_run(|config| {
-				let PartialComponents { client, task_manager, .. } = service::new_partial(&config)?;
+				let PartialComponents { client, task_manager, .. } =
+					service::new_partial(&config, None)?;
 				Ok((cmd.run(client, config.chain_spec), task_manager))
 			})
 		},
@@ -85,7 +95,7 @@ pub fn run() -> sc_cli::Result<()> {
 			let runner = cli.create_runner(cmd)?;
 			runner.async_run(|config| {
 				let PartialComponents { client, task_manager, import_queue, .. } =
-					service::new_partial(&config)?;
+					service::new_partial(&config, None)?;
 				Ok((cmd.run(client, import_queue), task_manager))
 			})
 		},
@@ -97,7 +107,7 @@ pub fn run() -> sc_cli::Result<()> {
 			let runner = cli.create_runner(cmd)?;
 			runner.async_run(|config| {
 				let PartialComponents { client, task_manager, backend, .. } =
-					service::new_partial(&config)?;
+					service::new_partial(&config, None)?;
 				let aux_revert = Box::new(|client, _, blocks| {
 					sc_finality_grandpa::revert(client, blocks)?;
 					Ok(())
@@ -105,7 +115,7 @@ pub fn run() -> sc_cli::Result<()> {
 				Ok((cmd.run(client, backend, Some(aux_revert)), task_manager))
 			})
 		},
-		Some(Subcommand::Benchmark(cmd)) => {
+		Some(Subcommand::Benchmark(cli::BenchmarkCmd { inner: cmd, workload })) => {
 			let runner = cli.create_runner(cmd)?;
 
 			runner.sync_run(|config| {
@@ -124,20 +134,26 @@ pub fn run() -> sc_cli::Result<()> {
 						cmd.run::<Block, service::ExecutorDispatch>(config)
 					},
 					BenchmarkCmd::Block(cmd) => {
-						let PartialComponents { client, .. } = service::new_partial(&config)?;
+						let PartialComponents { client, .. } = service::new_partial(&config, None)?;
 						cmd.run(client)
 					},
 					BenchmarkCmd::Storage(cmd) => {
 						let PartialComponents { client, backend, .. } =
-							service::new_partial(&config)?;
+							service::new_partial(&config, None)?;
 						let db = backend.expose_db();
 						let storage = backend.expose_storage();
 
 						cmd.run(config, client, db, storage)
 					},
 					BenchmarkCmd::Overhead(cmd) => {
-						let PartialComponents { client, .. } = service::new_partial(&config)?;
-						let ext_builder = BenchmarkExtrinsicBuilder::new(client.clone());
+						if *workload == Workload::EthereumTransfer && cli.dev_accounts == 0 {
+							return Err("The `ethereum-transfer` workload signs with the first \
+								development EVM account, which needs `--dev-accounts 1`."
+								.into())
+						}
+
+						let PartialComponents { client, .. } = service::new_partial(&config, None)?;
+						let ext_builder = BenchmarkExtrinsicBuilder::new(client.clone(), *workload)?;
 
 						cmd.run(config, client, inherent_benchmark_data()?, Arc::new(ext_builder))
 					},
@@ -146,6 +162,23 @@ pub fn run() -> sc_cli::Result<()> {
 				}
 			})
 		},
+		Some(Subcommand::LoadTest(cmd)) => match &cmd.url {
+			Some(url) => sc_cli::build_runtime()?.block_on(load_test::run(url.clone(), cmd.clone())),
+			None => {
+				let runner = cli.create_runner(&cmd.node)?;
+				runner.async_run(|config| {
+					let port = config
+						.rpc_ws
+						.ok_or("The in-process node needs its WebSocket RPC server")?
+						.port();
+					let url = format!("ws://127.0.0.1:{}", port);
+					let task_manager = service::new_full(config, cli.sealing, cli.dev_accounts)?;
+					Ok((load_test::run(url, cmd.clone()), task_manager))
+				})
+			},
+		},
+		Some(Subcommand::Tx(cmd)) => sc_cli::build_runtime()?.block_on(tx::run(cmd.clone())),
+		Some(Subcommand::ExportMetadata(cmd)) => metadata::export(cmd),
 		#[cfg(feature = "try-runtime")]
 		Some(Subcommand::TryRuntime(cmd)) => {
 			let runner = cli.create_runner(cmd)?;
@@ -170,7 +203,8 @@ pub fn run() -> sc_cli::Result<()> {
 		None => {
 			let runner = cli.create_runner(&cli.run)?;
 			runner.run_node_until_exit(|config| async move {
-				service::new_full(config).map_err(sc_cli::Error::Service)
+				service::new_full(config, cli.sealing, cli.dev_accounts)
+					.map_err(sc_cli::Error::Service)
 			})
 		},
 	}
@@ -19,77 +19,248 @@
 //! Contains code to setup the command invocations in [`super::command`] which would
 //! otherwise bloat that module.
 
-use crate::service::FullClient;
+use crate::{
+	chain_spec::{dev_evm_keys, truncated_evm_address, DEV_ERC20},
+	rpc::DevSigner,
+	service::FullClient,
+};
 
+use ethereum::{EIP1559TransactionMessage, TransactionAction};
+use fc_rpc::EthSigner;
+use fc_rpc_core::types::TransactionMessage;
+use fp_rpc::EthereumRuntimeRPCApi;
 use node_template_runtime as runtime;
-use runtime::SystemCall;
+use pallet_evm::ExitReason;
+use runtime::{evm_currency, EVMCall, EthereumCall, SystemCall};
 use sc_cli::Result;
 use sc_client_api::BlockBackend;
-use sp_core::{Encode, Pair};
+use sp_api::ProvideRuntimeApi;
+use sp_core::{Encode, Pair, H160, H256, U256};
 use sp_inherents::{InherentData, InherentDataProvider};
 use sp_keyring::Sr25519Keyring;
-use sp_runtime::{OpaqueExtrinsic, SaturatedConversion};
+use sp_runtime::{
+	generic::{BlockId, Era},
+	OpaqueExtrinsic, SaturatedConversion,
+};
 
 use std::{sync::Arc, time::Duration};
 
+/// The extrinsics `benchmark overhead` fills blocks with.
+///
+/// The EVM workloads rely on the fixtures of the `dev` chain spec.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ArgEnum)]
+pub enum Workload {
+	/// `System::remark` from Bob.
+	Remark,
+	/// `EVM::call` from the EVM address Bob controls, sending the existential deposit to an
+	/// empty account.
+	EvmTransfer,
+	/// `EVM::call` from the EVM address Bob controls, calling `transfer` on the dev ERC-20.
+	Erc20Transfer,
+	/// Ethereum transactions from the first development EVM account, sending the existential
+	/// deposit to an empty account. Needs `--dev-accounts`.
+	EthereumTransfer,
+}
+
+/// Receives the value and tokens of the EVM workloads.
+const RECIPIENT: H160 = H160::repeat_byte(0xbe);
+/// Enough gas for an ERC-20 transfer to a new holder.
+const GAS_LIMIT: u64 = 100_000;
+
 /// Generates extrinsics for the `benchmark overhead` command.
 ///
 /// Note: Should only be used for benchmarking.
 pub struct BenchmarkExtrinsicBuilder {
 	client: Arc<FullClient>,
+	workload: Workload,
+	/// Signs for the first development EVM account.
+	evm_signer: DevSigner,
+	/// The gas price of the best block, paid by the EVM workloads.
+	gas_price: U256,
 }
 
 impl BenchmarkExtrinsicBuilder {
-	/// Creates a new [`Self`] from the given client.
-	pub fn new(client: Arc<FullClient>) -> Self {
-		Self { client }
+	/// Creates a new [`Self`] from the given client, generating `workload`.
+	///
+	/// Fails if a call of the workload does not succeed on the best block, as the benchmark would
+	/// then measure failing calls.
+	pub fn new(client: Arc<FullClient>, workload: Workload) -> Result<Self> {
+		let best = BlockId::Hash(client.chain_info().best_hash);
+		let gas_price = client
+			.runtime_api()
+			.gas_price(&best)
+			.map_err(|e| format!("Reading the gas price: {}", e))?;
+		let builder =
+			Self { client, workload, evm_signer: DevSigner::new(dev_evm_keys(1)), gas_price };
+		builder.check_succeeds()?;
+		Ok(builder)
+	}
+
+	/// The source, target, input and value of the EVM call the workload makes, if any.
+	fn evm_transaction(&self) -> Option<(H160, H160, Vec<u8>, U256)> {
+		let bob = truncated_evm_address(&Sr25519Keyring::Bob.to_account_id());
+		let value = evm_currency::balance_to_wei(runtime::EXISTENTIAL_DEPOSIT);
+		match self.workload {
+			Workload::Remark => None,
+			Workload::EvmTransfer => Some((bob, RECIPIENT, vec![], value)),
+			Workload::Erc20Transfer => {
+				// transfer(RECIPIENT, 1)
+				let mut input = vec![0xa9, 0x05, 0x9c, 0xbb];
+				input.extend_from_slice(H256::from(RECIPIENT).as_bytes());
+				input.extend_from_slice(&<[u8; 32]>::from(U256::one()));
+				Some((bob, DEV_ERC20, input, U256::zero()))
+			},
+			Workload::EthereumTransfer =>
+				Some((self.evm_signer.accounts()[0], RECIPIENT, vec![], value)),
+		}
+	}
+
+	/// Runs the EVM call of the workload on the best block and fails unless it succeeds.
+	fn check_succeeds(&self) -> Result<()> {
+		let (source, target, input, value) = match self.evm_transaction() {
+			Some(transaction) => transaction,
+			None => return Ok(()),
+		};
+		let best = BlockId::Hash(self.client.chain_info().best_hash);
+		let info = self
+			.client
+			.runtime_api()
+			.call(
+				&best,
+				source,
+				target,
+				input,
+				value,
+				GAS_LIMIT.into(),
+				Some(self.gas_price),
+				None,
+				None,
+				false,
+				None,
+			)
+			.map_err(|e| format!("Running the {:?} workload: {}", self.workload, e))?
+			.map_err(|e| format!("The {:?} workload is rejected: {:?}", self.workload, e))?;
+		match info.exit_reason {
+			ExitReason::Succeed(_) => Ok(()),
+			reason =>
+				Err(format!("The {:?} workload fails in the EVM: {:?}", self.workload, reason).into()),
+		}
+	}
+
+	/// `EVM::call` of `target` from the EVM address Bob controls.
+	fn evm_call(&self, target: H160, input: Vec<u8>, value: U256, nonce: u32) -> OpaqueExtrinsic {
+		let bob = Sr25519Keyring::Bob;
+		let call = EVMCall::call {
+			source: truncated_evm_address(&bob.to_account_id()),
+			target,
+			input,
+			value,
+			gas_limit: GAS_LIMIT,
+			max_fee_per_gas: self.gas_price,
+			max_priority_fee_per_gas: None,
+			nonce: None,
+			access_list: vec![],
+		};
+		create_benchmark_extrinsic(self.client.as_ref(), bob.pair(), call.into(), nonce).into()
+	}
+
+	/// An EIP-1559 transaction sending `value` to `RECIPIENT`, signed with the first development
+	/// EVM key.
+	fn ethereum_transfer(
+		&self,
+		value: U256,
+		nonce: u32,
+	) -> std::result::Result<OpaqueExtrinsic, &'static str> {
+		let from = self.evm_signer.accounts()[0];
+		let message = EIP1559TransactionMessage {
+			chain_id: runtime::EVM_CHAIN_ID,
+			nonce: nonce.into(),
+			max_priority_fee_per_gas: U256::zero(),
+			max_fee_per_gas: self.gas_price,
+			gas_limit: GAS_LIMIT.into(),
+			action: TransactionAction::Call(RECIPIENT),
+			value,
+			input: vec![],
+			access_list: vec![],
+		};
+		let transaction = self
+			.evm_signer
+			.sign(TransactionMessage::EIP1559(message), &from)
+			.map_err(|_| "Could not sign the Ethereum transaction")?;
+		let call = EthereumCall::transact { transaction };
+		Ok(runtime::UncheckedExtrinsic::new_unsigned(call.into()).into())
 	}
 }
 
 impl frame_benchmarking_cli::ExtrinsicBuilder for BenchmarkExtrinsicBuilder {
+	/// Despite its name, builds an extrinsic of the selected [`Workload`].
 	fn remark(&self, nonce: u32) -> std::result::Result<OpaqueExtrinsic, &'static str> {
-		let acc = Sr25519Keyring::Bob.pair();
-		let extrinsic: OpaqueExtrinsic = create_benchmark_extrinsic(
-			self.client.as_ref(),
-			acc,
-			SystemCall::remark { remark: vec![] }.into(),
-			nonce,
-		)
-		.into();
+		let extrinsic = match (self.workload, self.evm_transaction()) {
+			(Workload::EthereumTransfer, Some((_, _, _, value))) =>
+				self.ethereum_transfer(value, nonce)?,
+			(_, Some((_, target, input, value))) => self.evm_call(target, input, value, nonce),
+			(_, None) => create_benchmark_extrinsic(
+				self.client.as_ref(),
+				Sr25519Keyring::Bob.pair(),
+				SystemCall::remark { remark: vec![] }.into(),
+				nonce,
+			)
+			.into(),
+		};
 
 		Ok(extrinsic)
 	}
 }
 
-/// Create a transaction using the given `call`.
-///
-/// Note: Should only be used for benchmarking.
-pub fn create_benchmark_extrinsic(
-	client: &FullClient,
+/// What a signature commits to besides the call, see [`create_extrinsic`].
+#[derive(Debug, Clone)]
+pub struct ExtrinsicParams {
+	/// The sender's nonce.
+	pub nonce: u32,
+	/// How long the extrinsic is valid for.
+	pub era: Era,
+	/// Paid on top of the fee, for priority.
+	pub tip: runtime::Balance,
+	/// The genesis hash of the chain to sign for.
+	pub genesis_hash: H256,
+	/// The hash of the block a mortal `era` starts at, or the genesis hash for an immortal one.
+	pub era_block_hash: H256,
+	/// The spec version of the runtime to sign for.
+	pub spec_version: u32,
+	/// The transaction version of the runtime to sign for.
+	pub transaction_version: u32,
+}
+
+impl ExtrinsicParams {
+	/// An immortal extrinsic without a tip, for the runtime the node was built with.
+	pub fn immortal(nonce: u32, genesis_hash: H256) -> Self {
+		Self {
+			nonce,
+			era: Era::Immortal,
+			tip: 0,
+			genesis_hash,
+			era_block_hash: genesis_hash,
+			spec_version: runtime::VERSION.spec_version,
+			transaction_version: runtime::VERSION.transaction_version,
+		}
+	}
+}
+
+/// Sign `call` with `sender`, for the chain and runtime given in `params`.
+pub fn create_extrinsic(
 	sender: sp_core::sr25519::Pair,
 	call: runtime::Call,
-	nonce: u32,
+	params: ExtrinsicParams,
 ) -> runtime::UncheckedExtrinsic {
-	let genesis_hash = client.block_hash(0).ok().flatten().expect("Genesis block exists; qed");
-	let best_hash = client.chain_info().best_hash;
-	let best_block = client.chain_info().best_number;
-
-	let period = runtime::BlockHashCount::get()
-		.checked_next_power_of_two()
-		.map(|c| c / 2)
-		.unwrap_or(2) as u64;
 	let extra: runtime::SignedExtra = (
 		frame_system::CheckNonZeroSender::<runtime::Runtime>::new(),
 		frame_system::CheckSpecVersion::<runtime::Runtime>::new(),
 		frame_system::CheckTxVersion::<runtime::Runtime>::new(),
 		frame_system::CheckGenesis::<runtime::Runtime>::new(),
-		frame_system::CheckEra::<runtime::Runtime>::from(sp_runtime::generic::Era::mortal(
-			period,
-			best_block.saturated_into(),
-		)),
-		frame_system::CheckNonce::<runtime::Runtime>::from(nonce),
+		frame_system::CheckEra::<runtime::Runtime>::from(params.era),
+		frame_system::CheckNonce::<runtime::Runtime>::from(params.nonce),
 		frame_system::CheckWeight::<runtime::Runtime>::new(),
-		pallet_transaction_payment::ChargeTransactionPayment::<runtime::Runtime>::from(0),
+		pallet_transaction_payment::ChargeTransactionPayment::<runtime::Runtime>::from(params.tip),
 	);
 
 	let raw_payload = runtime::SignedPayload::from_raw(
@@ -97,10 +268,10 @@ pub fn create_benchmark_extrinsic(
 		extra.clone(),
 		(
 			(),
-			runtime::VERSION.spec_version,
-			runtime::VERSION.transaction_version,
-			genesis_hash,
-			best_hash,
+			params.spec_version,
+			params.transaction_version,
+			params.genesis_hash,
+			params.era_block_hash,
 			(),
 			(),
 			(),
@@ -109,13 +280,42 @@ pub fn create_benchmark_extrinsic(
 	let signature = raw_payload.using_encoded(|e| sender.sign(e));
 
 	runtime::UncheckedExtrinsic::new_signed(
-		call.clone(),
+		call,
 		sp_runtime::AccountId32::from(sender.public()).into(),
-		runtime::Signature::Sr25519(signature.clone()),
-		extra.clone(),
+		runtime::Signature::Sr25519(signature),
+		extra,
 	)
 }
 
+/// Create a transaction using the given `call`.
+///
+/// Note: Should only be used for benchmarking.
+pub fn create_benchmark_extrinsic(
+	client: &FullClient,
+	sender: sp_core::sr25519::Pair,
+	call: runtime::Call,
+	nonce: u32,
+) -> runtime::UncheckedExtrinsic {
+	let genesis_hash = client.block_hash(0).ok().flatten().expect("Genesis block exists; qed");
+	let best_hash = client.chain_info().best_hash;
+	let best_block = client.chain_info().best_number;
+
+	let period = runtime::BlockHashCount::get()
+		.checked_next_power_of_two()
+		.map(|c| c / 2)
+		.unwrap_or(2) as u64;
+	let params = ExtrinsicParams {
+		nonce,
+		era: Era::mortal(period, best_block.saturated_into()),
+		tip: 0,
+		genesis_hash,
+		era_block_hash: best_hash,
+		spec_version: runtime::VERSION.spec_version,
+		transaction_version: runtime::VERSION.transaction_version,
+	};
+	create_extrinsic(sender, call, params)
+}
+
 /// Generates inherent data for the `benchmark overhead` command.
 ///
 /// Note: Should only be used for benchmarking.
@@ -1,3 +1,6 @@
 pub mod chain_spec;
+pub mod command_helper;
 pub mod rpc;
 pub mod service;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_node;
@@ -0,0 +1,449 @@
+//! The `load-test` subcommand: floods a node with transactions and reports how many made it into
+//! blocks, how long they took, and how full the blocks were.
+
+use crate::{
+	chain_spec::{dev_evm_keys, DEV_ERC20},
+	cli::LoadTestCmd,
+	command_helper::{create_extrinsic, ExtrinsicParams},
+	rpc::DevSigner,
+};
+use ethereum::{EIP1559TransactionMessage, TransactionAction};
+use fc_rpc::EthSigner;
+use fc_rpc_core::types::TransactionMessage;
+use futures::StreamExt;
+use jsonrpsee::{
+	core::client::{ClientT, SubscriptionClientT},
+	rpc_params,
+	ws_client::{WsClient, WsClientBuilder},
+};
+use node_template_runtime::{
+	self as runtime, evm_currency::balance_to_wei, opaque, BalancesCall, EthereumCall,
+	MyPalletCall, Weight, UNIT,
+};
+use pallet_evm::{AddressMapping, HashedAddressMapping};
+use sc_cli::Result;
+use serde::Deserialize;
+use sp_core::{blake2_256, crypto::Ss58Codec, twox_128, Bytes, Decode, Encode, H160, H256, U256};
+use sp_keyring::Sr25519Keyring;
+use sp_runtime::traits::{BlakeTwo256, Header as _};
+use std::{
+	collections::HashMap,
+	str::FromStr,
+	sync::Mutex,
+	time::{Duration, Instant},
+};
+
+/// Balance Alice gives each of the other keyring accounts, and the development EVM accounts that
+/// hold less, before the test.
+const FUNDING: runtime::Balance = 1_000 * UNIT;
+
+/// A kind of transaction the load test submits.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TxKind {
+	/// `Balances::transfer_keep_alive` between keyring accounts.
+	Transfer,
+	/// `MyPallet::simple` from a keyring account.
+	Simple,
+	/// An Ethereum transaction from a development EVM account, calling `transfer` on the
+	/// development ERC-20.
+	Evm,
+}
+
+/// The kinds of transactions to submit, with their relative frequency, e.g.
+/// `transfer=5,simple=1,evm=2`.
+#[derive(Debug, Clone)]
+pub struct Mix(Vec<(TxKind, u32)>);
+
+impl FromStr for Mix {
+	type Err = String;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		let mix = s
+			.split(',')
+			.map(|part| {
+				let (kind, weight) = part.split_once('=').unwrap_or((part, "1"));
+				let kind = match kind.trim() {
+					"transfer" => TxKind::Transfer,
+					"simple" => TxKind::Simple,
+					"evm" => TxKind::Evm,
+					other => return Err(format!("Unknown transaction kind `{}`", other)),
+				};
+				let weight =
+					weight.trim().parse().map_err(|_| format!("Invalid weight in `{}`", part))?;
+				Ok((kind, weight))
+			})
+			.collect::<std::result::Result<Vec<_>, _>>()?;
+		if mix.iter().all(|(_, weight)| *weight == 0) {
+			return Err("The mix needs a kind with a non-zero weight".into())
+		}
+		Ok(Mix(mix))
+	}
+}
+
+impl Mix {
+	/// The kinds in a repeating order that follows the weights.
+	fn cycle(&self) -> impl Iterator<Item = TxKind> + '_ {
+		self.0
+			.iter()
+			.flat_map(|(kind, weight)| std::iter::repeat(*kind).take(*weight as usize))
+			.collect::<Vec<_>>()
+			.into_iter()
+			.cycle()
+	}
+}
+
+/// What the chain reports about itself, to sign for it.
+struct Chain {
+	genesis_hash: H256,
+	spec_version: u32,
+	transaction_version: u32,
+	/// What EVM gas costs, from `eth_gasPrice`.
+	gas_price: U256,
+}
+
+/// A keyring account and the next nonce to sign with.
+struct Sender {
+	keyring: Sr25519Keyring,
+	nonce: runtime::Index,
+}
+
+/// A development EVM account and the next nonce to sign with.
+struct EvmSender {
+	address: H160,
+	nonce: U256,
+}
+
+#[derive(Deserialize)]
+struct SignedBlock {
+	block: Block,
+}
+
+#[derive(Deserialize)]
+struct Block {
+	extrinsics: Vec<Bytes>,
+}
+
+/// The submitted transactions not seen in a block yet, and what became of the others.
+#[derive(Default)]
+struct Tracker {
+	/// When each pending transaction was submitted, by hash.
+	pending: HashMap<H256, Instant>,
+	submitted: usize,
+	rejected: usize,
+	/// Submission to inclusion, of every included transaction.
+	latencies: Vec<Duration>,
+	first_submission: Option<Instant>,
+	last_inclusion: Option<Instant>,
+}
+
+fn rpc_error(e: impl ToString) -> sc_cli::Error {
+	sc_cli::Error::Application(e.to_string().into())
+}
+
+/// Signs `call` for `sender`, immortal so that it does not depend on the best block.
+fn sign(chain: &Chain, sender: &mut Sender, call: runtime::Call) -> runtime::UncheckedExtrinsic {
+	let params = ExtrinsicParams {
+		spec_version: chain.spec_version,
+		transaction_version: chain.transaction_version,
+		..ExtrinsicParams::immortal(sender.nonce, chain.genesis_hash)
+	};
+	sender.nonce += 1;
+	create_extrinsic(sender.keyring.pair(), call, params)
+}
+
+/// An Ethereum transaction calling `transfer(to, 0)` on the development ERC-20, which succeeds
+/// whatever the sender holds.
+fn erc20_transfer(
+	chain: &Chain,
+	signer: &DevSigner,
+	sender: &mut EvmSender,
+	to: H160,
+) -> Result<runtime::UncheckedExtrinsic> {
+	let mut input = vec![0xa9, 0x05, 0x9c, 0xbb];
+	input.extend_from_slice(H256::from(to).as_bytes());
+	input.extend_from_slice(&[0; 32]);
+	let message = EIP1559TransactionMessage {
+		chain_id: runtime::EVM_CHAIN_ID,
+		nonce: sender.nonce,
+		max_priority_fee_per_gas: U256::zero(),
+		max_fee_per_gas: chain.gas_price,
+		gas_limit: 100_000.into(),
+		action: TransactionAction::Call(DEV_ERC20),
+		value: U256::zero(),
+		input,
+		access_list: vec![],
+	};
+	let transaction = signer
+		.sign(TransactionMessage::EIP1559(message), &sender.address)
+		.map_err(rpc_error)?;
+	sender.nonce += U256::one();
+	Ok(runtime::UncheckedExtrinsic::new_unsigned(EthereumCall::transact { transaction }.into()))
+}
+
+/// Submits `extrinsic`, recording when. Rejections are counted rather than fatal, since a loaded
+/// pool is expected to turn some transactions away.
+async fn submit(
+	client: &WsClient,
+	tracker: &Mutex<Tracker>,
+	extrinsic: runtime::UncheckedExtrinsic,
+) {
+	let encoded = extrinsic.encode();
+	let hash = H256(blake2_256(&encoded));
+	{
+		let mut tracker = tracker.lock().expect("not poisoned");
+		let now = Instant::now();
+		tracker.first_submission.get_or_insert(now);
+		tracker.submitted += 1;
+		tracker.pending.insert(hash, now);
+	}
+
+	if client
+		.request::<H256>("author_submitExtrinsic", rpc_params![Bytes(encoded)])
+		.await
+		.is_err()
+	{
+		let mut tracker = tracker.lock().expect("not poisoned");
+		tracker.rejected += 1;
+		tracker.pending.remove(&hash);
+	}
+}
+
+/// Watches new blocks, recording the inclusion of pending transactions and printing how full each
+/// block is. Stops once nothing is pending after `drain_from`, or at `deadline`.
+async fn watch_blocks(
+	client: &WsClient,
+	tracker: &Mutex<Tracker>,
+	drain_from: Instant,
+	deadline: Instant,
+) -> Result<()> {
+	let max_block = runtime::BlockWeights::get().max_block;
+	let block_weight_key: Vec<u8> = [twox_128(b"System"), twox_128(b"BlockWeight")].concat();
+	let mut heads = client
+		.subscribe::<opaque::Header>(
+			"chain_subscribeNewHeads",
+			rpc_params![],
+			"chain_unsubscribeNewHeads",
+		)
+		.await
+		.map_err(rpc_error)?;
+
+	loop {
+		let drained = {
+			let tracker = tracker.lock().expect("not poisoned");
+			Instant::now() >= drain_from && tracker.pending.is_empty()
+		};
+		if drained {
+			return Ok(())
+		}
+		let timeout = deadline.saturating_duration_since(Instant::now());
+		let header = match tokio::time::timeout(timeout, heads.next()).await {
+			Ok(Some(header)) => header.map_err(rpc_error)?,
+			Ok(None) | Err(_) => return Ok(()),
+		};
+		let seen = Instant::now();
+		let hash = header.hash();
+
+		let block: SignedBlock =
+			client.request("chain_getBlock", rpc_params![hash]).await.map_err(rpc_error)?;
+		let weight: Option<Bytes> = client
+			.request("state_getStorage", rpc_params![Bytes(block_weight_key.clone()), hash])
+			.await
+			.map_err(rpc_error)?;
+		// A `PerDispatchClass<Weight>`: normal, operational and mandatory.
+		let weight = weight
+			.and_then(|data| <(Weight, Weight, Weight)>::decode(&mut &data[..]).ok())
+			.map_or(0, |(normal, operational, mandatory)| normal + operational + mandatory);
+
+		let mut included = 0;
+		{
+			let mut tracker = tracker.lock().expect("not poisoned");
+			for extrinsic in &block.block.extrinsics {
+				if let Some(submitted) = tracker.pending.remove(&H256(blake2_256(extrinsic))) {
+					tracker.latencies.push(seen - submitted);
+					tracker.last_inclusion = Some(seen);
+					included += 1;
+				}
+			}
+		}
+		println!(
+			"#{}: {} extrinsics, {} from the load test, {:.1}% of the maximum weight",
+			header.number,
+			block.block.extrinsics.len(),
+			included,
+			weight as f64 * 100.0 / max_block as f64,
+		);
+	}
+}
+
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+	if sorted.is_empty() {
+		return Duration::ZERO
+	}
+	sorted[((sorted.len() - 1) * percent) / 100]
+}
+
+/// Runs the load test described by `cmd` against the node at `url`.
+pub async fn run(url: String, cmd: LoadTestCmd) -> Result<()> {
+	let client = WsClientBuilder::default().build(&url).await.map_err(rpc_error)?;
+
+	let genesis_hash: H256 = client
+		.request("chain_getBlockHash", rpc_params![0u32])
+		.await
+		.map_err(rpc_error)?;
+	let version: serde_json::Value = client
+		.request("state_getRuntimeVersion", rpc_params![])
+		.await
+		.map_err(rpc_error)?;
+	let version_field = |name: &str| {
+		version[name]
+			.as_u64()
+			.map(|n| n as u32)
+			.ok_or_else(|| rpc_error("Invalid runtime version"))
+	};
+	let chain = Chain {
+		genesis_hash,
+		spec_version: version_field("specVersion")?,
+		transaction_version: version_field("transactionVersion")?,
+		gas_price: client.request("eth_gasPrice", rpc_params![]).await.map_err(rpc_error)?,
+	};
+
+	let mut senders = vec![];
+	for keyring in Sr25519Keyring::iter() {
+		let nonce = client
+			.request("system_accountNextIndex", rpc_params![keyring.to_account_id().to_ss58check()])
+			.await
+			.map_err(rpc_error)?;
+		senders.push(Sender { keyring, nonce });
+	}
+	let evm_signer = DevSigner::new(dev_evm_keys(cmd.evm_senders));
+	let mut evm_senders = vec![];
+	let mut unfunded = vec![];
+	for address in evm_signer.accounts() {
+		let nonce = client
+			.request("eth_getTransactionCount", rpc_params![address, "latest"])
+			.await
+			.map_err(rpc_error)?;
+		let balance: U256 = client
+			.request("eth_getBalance", rpc_params![address, "latest"])
+			.await
+			.map_err(rpc_error)?;
+		if balance < balance_to_wei(FUNDING) {
+			unfunded.push(address);
+		}
+		evm_senders.push(EvmSender { address, nonce });
+	}
+	if evm_senders.is_empty() && cmd.mix.0.iter().any(|(kind, w)| *kind == TxKind::Evm && *w > 0) {
+		return Err("The `evm` transactions need `--evm-senders` of at least 1".into())
+	}
+
+	// Alice funds the other keyring accounts, so that all of them can send transfers, and the
+	// EVM accounts the chain spec did not endow, so that they can pay for gas. Those are not
+	// linked to a Substrate account, so their account is the hash of their address.
+	println!(
+		"Funding {} keyring and {} EVM accounts from Alice",
+		senders.len() - 1,
+		unfunded.len()
+	);
+	let funding = Mutex::new(Tracker::default());
+	let (alice, others) = senders.split_first_mut().expect("the keyring is not empty");
+	let evm_account = HashedAddressMapping::<BlakeTwo256>::into_account_id;
+	let recipients = others
+		.iter()
+		.map(|other| other.keyring.to_account_id())
+		.chain(unfunded.iter().copied().map(evm_account))
+		.collect::<Vec<_>>();
+	for dest in recipients {
+		let call = BalancesCall::transfer_keep_alive { dest: dest.into(), value: FUNDING };
+		submit(&client, &funding, sign(&chain, alice, call.into())).await;
+	}
+	let now = Instant::now();
+	watch_blocks(&client, &funding, now, now + Duration::from_secs(cmd.drain)).await?;
+	if !funding.into_inner().expect("not poisoned").pending.is_empty() {
+		return Err("The accounts were not funded in time".into())
+	}
+
+	println!(
+		"Submitting up to {} transactions per second for {}s: {:?}",
+		cmd.rate, cmd.duration, cmd.mix.0
+	);
+	let tracker = Mutex::new(Tracker::default());
+	let end = Instant::now() + Duration::from_secs(cmd.duration);
+	let submissions = async {
+		let mut interval = tokio::time::interval(Duration::from_secs(1) / cmd.rate.max(1));
+		let mut kinds = cmd.mix.cycle();
+		let accounts = senders.len();
+		let mut i = 0;
+		while Instant::now() < end {
+			interval.tick().await;
+			let extrinsic = match kinds.next().expect("the cycle is infinite") {
+				TxKind::Transfer => {
+					let dest = senders[(i + 1) % accounts].keyring.to_account_id();
+					let call = BalancesCall::transfer_keep_alive { dest: dest.into(), value: 1 };
+					sign(&chain, &mut senders[i % accounts], call.into())
+				},
+				TxKind::Simple => {
+					let call = MyPalletCall::simple { val: i as u32 };
+					sign(&chain, &mut senders[i % accounts], call.into())
+				},
+				TxKind::Evm => {
+					let count = evm_senders.len();
+					let to = evm_senders[(i + 1) % count].address;
+					erc20_transfer(&chain, &evm_signer, &mut evm_senders[i % count], to)?
+				},
+			};
+			submit(&client, &tracker, extrinsic).await;
+			i += 1;
+		}
+		Ok::<_, sc_cli::Error>(())
+	};
+	let blocks = watch_blocks(&client, &tracker, end, end + Duration::from_secs(cmd.drain));
+	futures::try_join!(submissions, blocks)?;
+
+	let mut tracker = tracker.into_inner().expect("not poisoned");
+	tracker.latencies.sort();
+	let included = tracker.latencies.len();
+	println!();
+	println!("Submitted:  {} ({} rejected by the pool)", tracker.submitted, tracker.rejected);
+	println!(
+		"Included:   {} ({:.1}%)",
+		included,
+		included as f64 * 100.0 / tracker.submitted.max(1) as f64
+	);
+	if let (Some(first), Some(last)) = (tracker.first_submission, tracker.last_inclusion) {
+		let throughput = included as f64 / (last - first).as_secs_f64();
+		println!("Throughput: {:.1} transactions per second", throughput);
+	}
+	println!(
+		"Latency:    p50 {:?}, p90 {:?}, p99 {:?}, max {:?}",
+		percentile(&tracker.latencies, 50),
+		percentile(&tracker.latencies, 90),
+		percentile(&tracker.latencies, 99),
+		tracker.latencies.last().copied().unwrap_or_default(),
+	);
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn mixes_are_parsed_and_cycled_by_weight() {
+		let mix: Mix = "transfer=2, evm".parse().unwrap();
+		let kinds: Vec<_> = mix.cycle().take(6).collect();
+		use TxKind::*;
+		assert_eq!(kinds, [Transfer, Transfer, Evm, Transfer, Transfer, Evm]);
+
+		assert!("transfer=0".parse::<Mix>().is_err());
+		assert!("swap=1".parse::<Mix>().is_err());
+		assert!("simple=x".parse::<Mix>().is_err());
+	}
+
+	#[test]
+	fn percentiles_pick_from_sorted_latencies() {
+		let latencies: Vec<_> = (1..=100).map(Duration::from_millis).collect();
+		assert_eq!(percentile(&latencies, 50), Duration::from_millis(50));
+		assert_eq!(percentile(&latencies, 99), Duration::from_millis(99));
+		assert_eq!(percentile(&[], 90), Duration::ZERO);
+	}
+}
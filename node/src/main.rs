@@ -7,7 +7,10 @@ mod service;
 mod cli;
 mod command;
 mod command_helper;
+mod load_test;
+mod metadata;
 mod rpc;
+mod tx;
 
 fn main() -> sc_cli::Result<()> {
 	command::run()
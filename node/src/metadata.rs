@@ -0,0 +1,52 @@
+//! The metadata of the runtime this binary was built with, and the `export-metadata` subcommand.
+
+use crate::cli::ExportMetadataCmd;
+use codec::Encode;
+use frame_metadata::{RuntimeMetadata, RuntimeMetadataV14};
+use node_template_runtime as runtime;
+
+/// The metadata, as the `Metadata_metadata` runtime API returns it.
+pub fn v14() -> Result<RuntimeMetadataV14, String> {
+	match runtime::Runtime::metadata().1 {
+		RuntimeMetadata::V14(metadata) => Ok(metadata),
+		_ => Err("The runtime's metadata is not V14".into()),
+	}
+}
+
+/// Write the metadata SCALE-encoded, as in the `OpaqueMetadata` the runtime API returns and
+/// `state_getMetadata` serves, and as JSON.
+pub fn export(cmd: &ExportMetadataCmd) -> sc_cli::Result<()> {
+	v14()?;
+	let metadata = runtime::Runtime::metadata();
+	std::fs::write(&cmd.scale, metadata.encode())?;
+	let json = serde_json::to_vec_pretty(&metadata).map_err(|e| e.to_string())?;
+	std::fs::write(&cmd.json, json)?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::Value;
+
+	#[test]
+	fn metadata_is_exported_as_scale_and_json() {
+		let dir = tempfile::tempdir().unwrap();
+		let cmd = ExportMetadataCmd {
+			scale: dir.path().join("metadata.scale"),
+			json: dir.path().join("metadata.json"),
+		};
+		export(&cmd).unwrap();
+
+		let scale = std::fs::read(&cmd.scale).unwrap();
+		assert_eq!(scale, runtime::Runtime::metadata().encode());
+		// The magic number `meta`, then the version.
+		assert_eq!(&scale[..5], b"meta\x0e");
+
+		let json: Value = serde_json::from_slice(&std::fs::read(&cmd.json).unwrap()).unwrap();
+		let pallets = json[1]["V14"]["pallets"].as_array().unwrap();
+		for name in ["TemplateModule", "MyPallet", "EVM"] {
+			assert!(pallets.iter().any(|pallet| pallet["name"] == name), "{} is missing", name);
+		}
+	}
+}
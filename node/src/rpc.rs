@@ -5,48 +5,186 @@
 
 #![warn(missing_docs)]
 
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
-use jsonrpsee::RpcModule;
-use node_template_runtime::{opaque::Block, AccountId, Balance, Index};
+use fc_rpc::{
+	EthBlockDataCacheTask, OverrideHandle, RuntimeApiStorageOverride, SchemaV1Override,
+	SchemaV2Override, SchemaV3Override, StorageOverride,
+};
+use fc_rpc_core::types::{FeeHistoryCache, FeeHistoryCacheLimit, FilterPool};
+use fp_storage::EthereumStorageSchema;
+use jsonrpsee::{
+	core::Error as JsonRpseeError,
+	types::error::{CallError, ErrorObject},
+	RpcModule,
+};
+use node_template_runtime::{opaque::Block, AccountId, Balance, Hash, Index};
+use sc_client_api::{
+	backend::{AuxStore, StateBackend},
+	Backend, BlockchainEvents, StorageProvider,
+};
+use sc_network::NetworkService;
+use sc_transaction_pool::{ChainApi, Pool};
 use sc_transaction_pool_api::TransactionPool;
 use sp_api::ProvideRuntimeApi;
 use sp_block_builder::BlockBuilder;
 use sp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata};
+use sp_runtime::traits::BlakeTwo256;
 
+mod dev;
+mod eip712;
+mod signer;
+
+pub use dev::{Dev, DevApiServer, DevClock, DevDeps};
 pub use sc_rpc_api::DenyUnsafe;
+pub use signer::{DevSigner, EthSignApiServer};
 
 /// Full client dependencies.
-pub struct FullDeps<C, P> {
+pub struct FullDeps<C, P, A: ChainApi, BE> {
 	/// The client instance to use.
 	pub client: Arc<C>,
 	/// Transaction pool instance.
 	pub pool: Arc<P>,
+	/// Graph pool instance.
+	pub graph: Arc<Pool<A>>,
 	/// Whether to deny unsafe calls
 	pub deny_unsafe: DenyUnsafe,
+	/// The node's network service.
+	pub network: Arc<NetworkService<Block, Hash>>,
+	/// Whether the node is an authority.
+	pub is_authority: bool,
+	/// The Frontier database, mapping Ethereum to Substrate blocks and transactions.
+	pub frontier_backend: Arc<fc_db::Backend<Block>>,
+	/// Readers of the Ethereum data in storage, see [`overrides_handle`].
+	pub overrides: Arc<OverrideHandle<Block>>,
+	/// Ethereum blocks and statuses recently read.
+	pub block_data_cache: Arc<EthBlockDataCacheTask<Block>>,
+	/// Filters created through `eth_newFilter` and friends.
+	pub filter_pool: FilterPool,
+	/// Fee history of recent blocks.
+	pub fee_history_cache: FeeHistoryCache,
+	/// How many blocks `fee_history_cache` keeps.
+	pub fee_history_cache_limit: FeeHistoryCacheLimit,
+	/// Signs for the development EVM accounts, on development chains.
+	pub dev_signer: Option<DevSigner>,
+	/// Dependencies of the dev cheatcodes, on development chains with manual sealing.
+	pub dev: Option<DevDeps<BE>>,
+}
+
+/// Readers of the Ethereum blocks, receipts and statuses that `pallet_ethereum` keeps in
+/// storage, one for each storage schema it ever used.
+pub fn overrides_handle<C, BE>(client: Arc<C>) -> Arc<OverrideHandle<Block>>
+where
+	BE: Backend<Block> + 'static,
+	BE::State: StateBackend<BlakeTwo256>,
+	C: ProvideRuntimeApi<Block> + StorageProvider<Block, BE> + AuxStore,
+	C: HeaderBackend<Block> + HeaderMetadata<Block, Error = BlockChainError>,
+	C: Send + Sync + 'static,
+	C::Api: fp_rpc::EthereumRuntimeRPCApi<Block>,
+{
+	let mut schemas = BTreeMap::new();
+	schemas.insert(
+		EthereumStorageSchema::V1,
+		Box::new(SchemaV1Override::new(client.clone())) as Box<dyn StorageOverride<_>>,
+	);
+	schemas.insert(EthereumStorageSchema::V2, Box::new(SchemaV2Override::new(client.clone())));
+	schemas.insert(EthereumStorageSchema::V3, Box::new(SchemaV3Override::new(client.clone())));
+
+	Arc::new(OverrideHandle { schemas, fallback: Box::new(RuntimeApiStorageOverride::new(client)) })
 }
 
 /// Instantiate all full RPC extensions.
-pub fn create_full<C, P>(
-	deps: FullDeps<C, P>,
+pub fn create_full<C, P, A, BE>(
+	deps: FullDeps<C, P, A, BE>,
 ) -> Result<RpcModule<()>, Box<dyn std::error::Error + Send + Sync>>
 where
-	C: ProvideRuntimeApi<Block>,
+	BE: Backend<Block> + 'static,
+	BE::State: StateBackend<BlakeTwo256>,
+	C: ProvideRuntimeApi<Block> + StorageProvider<Block, BE> + AuxStore,
+	C: BlockchainEvents<Block>,
 	C: HeaderBackend<Block> + HeaderMetadata<Block, Error = BlockChainError> + 'static,
 	C: Send + Sync + 'static,
 	C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Index>,
 	C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
 	C::Api: BlockBuilder<Block>,
-	P: TransactionPool + 'static,
+	C::Api: fp_rpc::ConvertTransactionRuntimeApi<Block>,
+	C::Api: fp_rpc::EthereumRuntimeRPCApi<Block>,
+	P: TransactionPool<Block = Block> + 'static,
+	A: ChainApi<Block = Block> + 'static,
 {
+	use fc_rpc::{
+		Eth, EthApiServer, EthFilter, EthFilterApiServer, EthSigner, Net, NetApiServer, Web3,
+		Web3ApiServer,
+	};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
 	use substrate_frame_rpc_system::{System, SystemApiServer};
 
+	/// How many filters `eth_newFilter` and friends keep at most.
+	const MAX_STORED_FILTERS: usize = 500;
+	/// How many logs `eth_getLogs` returns at most.
+	const MAX_PAST_LOGS: u32 = 10_000;
+
 	let mut module = RpcModule::new(());
-	let FullDeps { client, pool, deny_unsafe } = deps;
+	let FullDeps {
+		client,
+		pool,
+		graph,
+		deny_unsafe,
+		network,
+		is_authority,
+		frontier_backend,
+		overrides,
+		block_data_cache,
+		filter_pool,
+		fee_history_cache,
+		fee_history_cache_limit,
+		dev_signer,
+		dev,
+	} = deps;
 
 	module.merge(System::new(client.clone(), pool.clone(), deny_unsafe).into_rpc())?;
-	module.merge(TransactionPayment::new(client).into_rpc())?;
+	module.merge(TransactionPayment::new(client.clone()).into_rpc())?;
+
+	let mut signers = Vec::<Box<dyn EthSigner>>::new();
+	if let Some(signer) = dev_signer {
+		let signer = signer.with_deny_unsafe(deny_unsafe);
+		signers.push(Box::new(signer.clone()));
+		module.merge(signer.into_rpc())?;
+	}
+	module.merge(
+		Eth::new(
+			client.clone(),
+			pool.clone(),
+			graph,
+			Some(node_template_runtime::TransactionConverter),
+			network.clone(),
+			signers,
+			overrides,
+			frontier_backend.clone(),
+			is_authority,
+			block_data_cache.clone(),
+			fee_history_cache,
+			fee_history_cache_limit,
+		)
+		.into_rpc(),
+	)?;
+	module.merge(
+		EthFilter::new(
+			client.clone(),
+			frontier_backend,
+			filter_pool,
+			MAX_STORED_FILTERS,
+			MAX_PAST_LOGS,
+			block_data_cache,
+		)
+		.into_rpc(),
+	)?;
+	module.merge(Net::new(client.clone(), network, true).into_rpc())?;
+	module.merge(Web3::new(client.clone()).into_rpc())?;
+
+	if let Some(dev) = dev {
+		module.merge(Dev::new(client, pool, dev, deny_unsafe).into_rpc())?;
+	}
 
 	// Extend this RPC with a custom API by using the following syntax.
 	// `YourRpcStruct` should have a reference to a client, which is needed
@@ -55,3 +193,7 @@ where
 
 	Ok(module)
 }
+
+fn error(e: impl ToString) -> JsonRpseeError {
+	CallError::Custom(ErrorObject::owned(1, e.to_string(), None::<()>)).into()
+}
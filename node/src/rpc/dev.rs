@@ -0,0 +1,368 @@
+//! Hardhat-compatible cheatcodes for development chains with manual sealing.
+//!
+//! They let existing Hardhat and Foundry test suites set up state, move time and blocks, and
+//! roll back between tests. State is changed through `sudo` calls signed by Alice, the
+//! development chain's sudo key, and takes effect in a newly sealed block.
+//!
+//! Unlike in Hardhat, where they change state in place, the `hardhat_*` methods therefore advance
+//! the block number, and the block they seal also includes the transactions pending in the pool.
+//!
+//! `evm_revert` reverts the blocks sealed since the snapshot and empties the pool, whose
+//! transactions were validated against the reverted state. Like every transaction the pool drops
+//! as invalid, they cannot be resubmitted unchanged for a while. Frontier's mapping database
+//! keeps the Ethereum hashes of the reverted blocks and transactions, as it has no way to delete
+//! them; the Ethereum RPC reports them as not found, since their blocks are no longer part of the
+//! chain.
+
+use std::{
+	sync::{Arc, Mutex},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use futures::{
+	channel::{mpsc, oneshot},
+	SinkExt,
+};
+use jsonrpsee::{
+	core::{async_trait, RpcResult},
+	proc_macros::rpc,
+};
+use node_template_runtime::{
+	self as runtime, evm_currency, opaque::Block, AccountId, BalancesCall, Hash, Index, SudoCall,
+	SystemCall,
+};
+use pallet_evm::{AddressMapping, HashedAddressMapping};
+use sc_client_api::{Backend, StorageProvider};
+use sc_consensus_manual_seal::{CreatedBlock, EngineCommand, Error as SealError};
+use sc_rpc_api::DenyUnsafe;
+use sc_transaction_pool_api::{InPoolTransaction, TransactionPool, TransactionSource};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{
+	hashing::{blake2_128, twox_128},
+	storage::StorageKey,
+	Bytes, Decode, Encode, H160, H256, U256,
+};
+use sp_keyring::Sr25519Keyring;
+use sp_runtime::{generic::BlockId, traits::BlakeTwo256, SaturatedConversion};
+use substrate_frame_rpc_system::AccountNonceApi;
+
+use super::error;
+use crate::command_helper::{create_extrinsic, ExtrinsicParams};
+
+/// Hardhat and Ganache cheatcodes.
+#[rpc(server)]
+pub trait DevApi {
+	/// Remember the current state and return an id to revert to it.
+	#[method(name = "evm_snapshot")]
+	fn snapshot(&self) -> RpcResult<U256>;
+
+	/// Go back to the state of snapshot `id`. It and all later snapshots are dropped, and so are
+	/// the transactions in the pool.
+	#[method(name = "evm_revert")]
+	fn revert(&self, id: U256) -> RpcResult<bool>;
+
+	/// Seal a new block.
+	#[method(name = "evm_mine")]
+	async fn mine(&self) -> RpcResult<String>;
+
+	/// Move the timestamp of the next blocks forward, and return the total offset in seconds.
+	#[method(name = "evm_increaseTime")]
+	fn increase_time(&self, seconds: u64) -> RpcResult<u64>;
+
+	/// Set the balance of an EVM address, in wei. Dust below a native unit is dropped.
+	///
+	/// This and the other `hardhat_*` methods seal a block, see the module documentation.
+	#[method(name = "hardhat_setBalance")]
+	async fn set_balance(&self, address: H160, balance: U256) -> RpcResult<bool>;
+
+	/// Replace the code of an EVM address.
+	#[method(name = "hardhat_setCode")]
+	async fn set_code(&self, address: H160, code: Bytes) -> RpcResult<bool>;
+
+	/// Write one storage slot of an EVM address.
+	#[method(name = "hardhat_setStorageAt")]
+	async fn set_storage_at(&self, address: H160, slot: U256, value: H256) -> RpcResult<bool>;
+}
+
+/// The clock of a development chain with manual sealing.
+///
+/// Block timestamps follow the system time plus the offset added by `evm_increaseTime`, and
+/// advance by at least a slot per block so that Aura sees a new slot even when blocks are sealed
+/// in quick succession.
+pub struct DevClock {
+	slot_duration: u64,
+	state: Mutex<ClockState>,
+}
+
+#[derive(Default)]
+struct ClockState {
+	offset: u64,
+	last: u64,
+}
+
+impl DevClock {
+	/// Create a clock for blocks of `slot_duration`.
+	pub fn new(slot_duration: Duration) -> Self {
+		Self {
+			slot_duration: slot_duration.as_millis().saturated_into(),
+			state: Default::default(),
+		}
+	}
+
+	/// The timestamp of the next block.
+	pub fn next(&self) -> sp_timestamp::Timestamp {
+		let now: u64 = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.expect("Current time is after the unix epoch; qed")
+			.as_millis()
+			.saturated_into();
+		let mut state = self.state.lock().expect("Clock lock is not poisoned; qed");
+		state.last = (now + state.offset).max(state.last + self.slot_duration);
+		state.last.into()
+	}
+
+	fn offset(&self) -> Duration {
+		Duration::from_millis(self.state.lock().expect("Clock lock is not poisoned; qed").offset)
+	}
+
+	fn set_offset(&self, offset: Duration) {
+		self.state.lock().expect("Clock lock is not poisoned; qed").offset =
+			offset.as_millis().saturated_into();
+	}
+}
+
+/// What the dev RPCs need besides the client and pool.
+pub struct DevDeps<BE> {
+	/// The backend, to revert blocks.
+	pub backend: Arc<BE>,
+	/// Sends commands to the manual seal task.
+	pub command_sink: mpsc::Sender<EngineCommand<Hash>>,
+	/// The clock the sealed blocks take their timestamp from.
+	pub clock: Arc<DevClock>,
+}
+
+/// A snapshot taken by `evm_snapshot`.
+struct Snapshot {
+	id: U256,
+	number: runtime::BlockNumber,
+	time_offset: Duration,
+}
+
+/// Implements [`DevApiServer`].
+pub struct Dev<C, P, BE> {
+	client: Arc<C>,
+	pool: Arc<P>,
+	deps: DevDeps<BE>,
+	deny_unsafe: DenyUnsafe,
+	snapshots: Mutex<(U256, Vec<Snapshot>)>,
+}
+
+impl<C, P, BE> Dev<C, P, BE> {
+	/// Create the dev RPCs.
+	pub fn new(client: Arc<C>, pool: Arc<P>, deps: DevDeps<BE>, deny_unsafe: DenyUnsafe) -> Self {
+		Self { client, pool, deps, deny_unsafe, snapshots: Default::default() }
+	}
+}
+
+impl<C, P, BE> Dev<C, P, BE>
+where
+	BE: Backend<Block> + 'static,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + StorageProvider<Block, BE>,
+	C: Send + Sync + 'static,
+	C::Api: AccountNonceApi<Block, AccountId, Index>,
+	P: TransactionPool<Block = Block> + 'static,
+{
+	/// Seal a block, with or without transactions.
+	async fn seal(&self, create_empty: bool) -> RpcResult<Result<CreatedBlock<Hash>, SealError>> {
+		let (sender, receiver) = oneshot::channel();
+		self.deps
+			.command_sink
+			.clone()
+			.send(EngineCommand::SealNewBlock {
+				create_empty,
+				finalize: false,
+				parent_hash: None,
+				sender: Some(sender),
+			})
+			.await
+			.map_err(error)?;
+		receiver.await.map_err(error)
+	}
+
+	/// The next nonce of `who`, after its transactions that are ready in the pool.
+	fn next_nonce(&self, who: AccountId) -> RpcResult<Index> {
+		let best = BlockId::hash(self.client.info().best_hash);
+		let mut nonce =
+			self.client.runtime_api().account_nonce(&best, who.clone()).map_err(error)?;
+		// Ready transactions are ordered by nonce, and provide `(who, nonce)` first.
+		let mut tag = (who.clone(), nonce).encode();
+		for transaction in self.pool.ready() {
+			if transaction.provides().first() == Some(&tag) {
+				nonce += 1;
+				tag = (who.clone(), nonce).encode();
+			}
+		}
+		Ok(nonce)
+	}
+
+	/// Dispatch `call` as root in a new block.
+	async fn sudo(&self, call: runtime::Call) -> RpcResult<bool> {
+		let info = self.client.info();
+		let signer = Sr25519Keyring::Alice;
+		let nonce = self.next_nonce(signer.to_account_id())?;
+
+		let call: runtime::Call = SudoCall::sudo { call: Box::new(call) }.into();
+		let params = ExtrinsicParams::immortal(nonce, info.genesis_hash);
+		let extrinsic = create_extrinsic(signer.pair(), call, params);
+
+		self.pool
+			.submit_one(&BlockId::hash(info.best_hash), TransactionSource::Local, extrinsic.into())
+			.await
+			.map_err(error)?;
+		match self.seal(false).await? {
+			// With instant sealing the transaction may already be in a block.
+			Ok(_) | Err(SealError::EmptyTransactionPool) => Ok(true),
+			Err(e) => Err(error(e)),
+		}
+	}
+
+	/// The account `pallet_evm` uses for `address`, see `pallet_account_link`.
+	fn account_of(&self, address: H160) -> RpcResult<AccountId> {
+		let key = storage_key(b"AccountLink", b"Accounts", &address);
+		let linked = self
+			.client
+			.storage(&BlockId::hash(self.client.info().best_hash), &key)
+			.map_err(error)?
+			.map(|data| AccountId::decode(&mut &data.0[..]))
+			.transpose()
+			.map_err(error)?;
+		Ok(linked.unwrap_or_else(|| HashedAddressMapping::<BlakeTwo256>::into_account_id(address)))
+	}
+}
+
+#[async_trait]
+impl<C, P, BE> DevApiServer for Dev<C, P, BE>
+where
+	BE: Backend<Block> + 'static,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + StorageProvider<Block, BE>,
+	C: Send + Sync + 'static,
+	C::Api: AccountNonceApi<Block, AccountId, Index>,
+	P: TransactionPool<Block = Block> + 'static,
+{
+	fn snapshot(&self) -> RpcResult<U256> {
+		self.deny_unsafe.check_if_safe()?;
+		let mut snapshots = self.snapshots.lock().expect("Snapshot lock is not poisoned; qed");
+		snapshots.0 += U256::one();
+		let snapshot = Snapshot {
+			id: snapshots.0,
+			number: self.client.info().best_number,
+			time_offset: self.deps.clock.offset(),
+		};
+		snapshots.1.push(snapshot);
+		Ok(snapshots.0)
+	}
+
+	fn revert(&self, id: U256) -> RpcResult<bool> {
+		self.deny_unsafe.check_if_safe()?;
+		let mut snapshots = self.snapshots.lock().expect("Snapshot lock is not poisoned; qed");
+		let index = match snapshots.1.iter().position(|snapshot| snapshot.id == id) {
+			Some(index) => index,
+			None => return Ok(false),
+		};
+		let snapshot = snapshots.1.drain(index..).next().expect("Found at `index`; qed");
+
+		let blocks = self.client.info().best_number.saturating_sub(snapshot.number);
+		if blocks > 0 {
+			self.deps.backend.revert(blocks, true).map_err(error)?;
+		}
+		let pending: Vec<_> = self
+			.pool
+			.ready()
+			.map(|transaction| transaction.hash().clone())
+			.chain(self.pool.futures().iter().map(|transaction| transaction.hash().clone()))
+			.collect();
+		self.pool.remove_invalid(&pending);
+		self.deps.clock.set_offset(snapshot.time_offset);
+		Ok(true)
+	}
+
+	async fn mine(&self) -> RpcResult<String> {
+		self.deny_unsafe.check_if_safe()?;
+		self.seal(true).await?.map_err(error)?;
+		Ok("0x0".into())
+	}
+
+	fn increase_time(&self, seconds: u64) -> RpcResult<u64> {
+		self.deny_unsafe.check_if_safe()?;
+		let offset = self.deps.clock.offset() + Duration::from_secs(seconds);
+		self.deps.clock.set_offset(offset);
+		Ok(offset.as_secs())
+	}
+
+	async fn set_balance(&self, address: H160, balance: U256) -> RpcResult<bool> {
+		self.deny_unsafe.check_if_safe()?;
+		let who = self.account_of(address)?;
+		self.sudo(
+			BalancesCall::set_balance {
+				who: who.into(),
+				new_free: evm_currency::wei_to_balance_floor(balance),
+				new_reserved: 0,
+			}
+			.into(),
+		)
+		.await
+	}
+
+	async fn set_code(&self, address: H160, code: Bytes) -> RpcResult<bool> {
+		self.deny_unsafe.check_if_safe()?;
+		let key = storage_key(b"EVM", b"AccountCodes", &address).0;
+		let call = if code.is_empty() {
+			SystemCall::kill_storage { keys: vec![key] }
+		} else {
+			SystemCall::set_storage { items: vec![(key, code.0.encode())] }
+		};
+		self.sudo(call.into()).await
+	}
+
+	async fn set_storage_at(&self, address: H160, slot: U256, value: H256) -> RpcResult<bool> {
+		self.deny_unsafe.check_if_safe()?;
+		let mut index = [0u8; 32];
+		slot.to_big_endian(&mut index);
+		let mut key = storage_key(b"EVM", b"AccountStorages", &address).0;
+		key.extend(blake2_128(&index));
+		key.extend(index);
+		// `pallet_evm` does not store zero values.
+		let call = if value.is_zero() {
+			SystemCall::kill_storage { keys: vec![key] }
+		} else {
+			SystemCall::set_storage { items: vec![(key, value.encode())] }
+		};
+		self.sudo(call.into()).await
+	}
+}
+
+/// The key of `address` in a `Blake2_128Concat` map.
+fn storage_key(pallet: &[u8], storage: &[u8], address: &H160) -> StorageKey {
+	let mut key = twox_128(pallet).to_vec();
+	key.extend(twox_128(storage));
+	key.extend(blake2_128(address.as_bytes()));
+	key.extend(address.as_bytes());
+	StorageKey(key)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn clock_moves_a_slot_per_block_and_jumps_by_the_offset() {
+		let clock = DevClock::new(Duration::from_secs(6));
+		let first = *clock.next();
+		assert_eq!(*clock.next(), first + 6_000);
+
+		clock.set_offset(clock.offset() + Duration::from_secs(3_600));
+		assert!(*clock.next() >= first + 3_600_000);
+		assert_eq!(clock.offset(), Duration::from_secs(3_600));
+	}
+}
@@ -0,0 +1,233 @@
+//! Hashing of EIP-712 typed data, as signed by `eth_signTypedData_v4`.
+
+use serde::Deserialize;
+use serde_json::Value;
+use sp_core::{hashing::keccak_256, H160, U256};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A member of a struct type.
+#[derive(Debug, Deserialize)]
+pub struct Member {
+	pub name: String,
+	#[serde(rename = "type")]
+	pub ty: String,
+}
+
+/// The typed data a dapp asks to sign.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypedData {
+	pub types: BTreeMap<String, Vec<Member>>,
+	pub primary_type: String,
+	pub domain: Value,
+	pub message: Value,
+}
+
+impl TypedData {
+	/// The hash to sign: `keccak256("\x19\x01" ‖ domainSeparator ‖ hashStruct(message))`.
+	pub fn signing_hash(&self) -> Result<[u8; 32], String> {
+		let mut encoded = vec![0x19, 0x01];
+		encoded.extend(self.hash_struct("EIP712Domain", &self.domain)?);
+		if self.primary_type != "EIP712Domain" {
+			encoded.extend(self.hash_struct(&self.primary_type, &self.message)?);
+		}
+		Ok(keccak_256(&encoded))
+	}
+
+	fn hash_struct(&self, ty: &str, value: &Value) -> Result<[u8; 32], String> {
+		let members = self.members(ty)?;
+		let mut encoded = keccak_256(self.encode_type(ty)?.as_bytes()).to_vec();
+		for member in members {
+			let field = value
+				.get(&member.name)
+				.ok_or_else(|| format!("`{}` is missing `{}`", ty, member.name))?;
+			encoded.extend(self.encode_value(&member.ty, field)?);
+		}
+		Ok(keccak_256(&encoded))
+	}
+
+	fn members(&self, ty: &str) -> Result<&Vec<Member>, String> {
+		self.types.get(ty).ok_or_else(|| format!("Unknown type `{}`", ty))
+	}
+
+	/// `Name(type name,…)` followed by the struct types it references, sorted by name.
+	fn encode_type(&self, ty: &str) -> Result<String, String> {
+		let mut referenced = BTreeSet::new();
+		self.collect_references(ty, &mut referenced)?;
+		referenced.remove(ty);
+
+		let mut encoded = String::new();
+		for ty in std::iter::once(ty).chain(referenced.iter().map(String::as_str)) {
+			let members = self.members(ty)?;
+			let members: Vec<_> =
+				members.iter().map(|member| format!("{} {}", member.ty, member.name)).collect();
+			encoded.push_str(&format!("{}({})", ty, members.join(",")));
+		}
+		Ok(encoded)
+	}
+
+	fn collect_references(&self, ty: &str, found: &mut BTreeSet<String>) -> Result<(), String> {
+		if !found.insert(ty.to_string()) {
+			return Ok(())
+		}
+		for member in self.members(ty)? {
+			let base = member.ty.split('[').next().unwrap_or_default();
+			if self.types.contains_key(base) {
+				self.collect_references(base, found)?;
+			}
+		}
+		Ok(())
+	}
+
+	fn encode_value(&self, ty: &str, value: &Value) -> Result<[u8; 32], String> {
+		if let Some(item_ty) = ty.strip_suffix(']').and_then(|ty| ty.rsplit_once('[')).map(|t| t.0)
+		{
+			let items =
+				value.as_array().ok_or_else(|| format!("Expected an array for `{}`", ty))?;
+			let mut encoded = Vec::with_capacity(items.len() * 32);
+			for item in items {
+				encoded.extend(self.encode_value(item_ty, item)?);
+			}
+			return Ok(keccak_256(&encoded))
+		}
+		if self.types.contains_key(ty) {
+			return self.hash_struct(ty, value)
+		}
+
+		let mut word = [0u8; 32];
+		match ty {
+			"string" => word = keccak_256(as_str(value)?.as_bytes()),
+			"bytes" => word = keccak_256(&as_bytes(value)?),
+			"bool" => word[31] = value.as_bool().ok_or("Expected a bool")? as u8,
+			"address" => {
+				let address: H160 = as_str(value)?.parse().map_err(|_| "Invalid address")?;
+				word[12..].copy_from_slice(address.as_bytes());
+			},
+			_ if ty.starts_with("bytes") => {
+				let bytes = as_bytes(value)?;
+				if bytes.len() > 32 {
+					return Err(format!("Too many bytes for `{}`", ty))
+				}
+				word[..bytes.len()].copy_from_slice(&bytes);
+			},
+			_ if ty.starts_with("uint") => as_number(value)?.to_big_endian(&mut word),
+			_ if ty.starts_with("int") => {
+				let number = match value.as_i64() {
+					Some(n) if n < 0 => U256::from(n.unsigned_abs()),
+					_ => match as_str(value).ok().and_then(|s| s.strip_prefix('-')) {
+						Some(magnitude) => as_number(&Value::String(magnitude.into()))?,
+						None => return self.encode_value("uint256", value),
+					},
+				};
+				// Two's complement of the magnitude.
+				(!number).overflowing_add(U256::one()).0.to_big_endian(&mut word);
+			},
+			_ => return Err(format!("Unknown type `{}`", ty)),
+		}
+		Ok(word)
+	}
+}
+
+fn as_str(value: &Value) -> Result<&str, String> {
+	value.as_str().ok_or_else(|| format!("Expected a string, got {}", value))
+}
+
+fn as_bytes(value: &Value) -> Result<Vec<u8>, String> {
+	let hex = as_str(value)?;
+	let hex = hex.strip_prefix("0x").unwrap_or(hex);
+	if hex.len() % 2 != 0 {
+		return Err(format!("Odd number of hex digits in {}", value))
+	}
+	(0..hex.len())
+		.step_by(2)
+		.map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+		.collect::<Result<_, _>>()
+		.map_err(|_| format!("Invalid hex in {}", value))
+}
+
+fn as_number(value: &Value) -> Result<U256, String> {
+	if let Some(n) = value.as_u64() {
+		return Ok(n.into())
+	}
+	let s = as_str(value)?;
+	let number = match s.strip_prefix("0x") {
+		Some(hex) => U256::from_str_radix(hex, 16).ok(),
+		None => U256::from_dec_str(s).ok(),
+	};
+	number.ok_or_else(|| format!("Invalid number {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	/// The example of EIP-712.
+	fn mail() -> TypedData {
+		serde_json::from_value(json!({
+			"types": {
+				"EIP712Domain": [
+					{ "name": "name", "type": "string" },
+					{ "name": "version", "type": "string" },
+					{ "name": "chainId", "type": "uint256" },
+					{ "name": "verifyingContract", "type": "address" }
+				],
+				"Person": [
+					{ "name": "name", "type": "string" },
+					{ "name": "wallet", "type": "address" }
+				],
+				"Mail": [
+					{ "name": "from", "type": "Person" },
+					{ "name": "to", "type": "Person" },
+					{ "name": "contents", "type": "string" }
+				]
+			},
+			"primaryType": "Mail",
+			"domain": {
+				"name": "Ether Mail",
+				"version": "1",
+				"chainId": 1,
+				"verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+			},
+			"message": {
+				"from": { "name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826" },
+				"to": { "name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB" },
+				"contents": "Hello, Bob!"
+			}
+		}))
+		.unwrap()
+	}
+
+	#[test]
+	fn hashes_the_eip712_example() {
+		let data = mail();
+		assert_eq!(
+			data.encode_type("Mail").unwrap(),
+			"Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+		);
+		assert_eq!(
+			hex(&data.hash_struct("EIP712Domain", &data.domain).unwrap()),
+			"f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+		);
+		assert_eq!(
+			hex(&data.hash_struct("Mail", &data.message).unwrap()),
+			"c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e"
+		);
+		assert_eq!(
+			hex(&data.signing_hash().unwrap()),
+			"be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+		);
+	}
+
+	#[test]
+	fn encodes_negative_integers_in_twos_complement() {
+		let data = mail();
+		assert_eq!(data.encode_value("int8", &json!(-1)).unwrap(), [0xff; 32]);
+		assert_eq!(data.encode_value("int256", &json!("-1")).unwrap(), [0xff; 32]);
+		assert_eq!(data.encode_value("int256", &json!("0x01")).unwrap()[31], 1);
+	}
+
+	fn hex(bytes: &[u8]) -> String {
+		bytes.iter().map(|b| format!("{:02x}", b)).collect()
+	}
+}
@@ -0,0 +1,207 @@
+//! Unlocked EVM accounts of development chains.
+//!
+//! They sign transactions sent with `eth_sendTransaction`, and messages for `eth_sign` and
+//! `eth_signTypedData_v4`, so Ethereum tooling works without a wallet. Over RPC, they are only
+//! available where unsafe methods are, as anyone who reaches them can spend the accounts' funds.
+
+use ethereum::{
+	EIP1559Transaction, EIP2930Transaction, LegacyTransaction, TransactionSignature,
+	TransactionV2 as EthereumTransaction,
+};
+use fc_rpc::EthSigner;
+use fc_rpc_core::types::TransactionMessage;
+use jsonrpsee::{
+	core::{Error as JsonRpseeError, RpcResult},
+	proc_macros::rpc,
+};
+use sc_rpc_api::DenyUnsafe;
+use serde_json::Value;
+use sp_core::{ecdsa, hashing::keccak_256, Bytes, Pair, H160, H256};
+
+use super::{eip712::TypedData, error};
+use crate::chain_spec::evm_address;
+
+/// Message signing with the development accounts.
+#[rpc(server)]
+pub trait EthSignApi {
+	/// Sign `data` the way `personal_sign` does, as defined by EIP-191.
+	#[method(name = "eth_sign")]
+	fn sign(&self, address: H160, data: Bytes) -> RpcResult<Bytes>;
+
+	/// Sign EIP-712 typed data, given as an object or as a JSON string.
+	#[method(name = "eth_signTypedData_v4")]
+	fn sign_typed_data(&self, address: H160, typed_data: Value) -> RpcResult<Bytes>;
+}
+
+/// Signs for the development accounts, see [`crate::chain_spec::dev_evm_keys`].
+#[derive(Clone)]
+pub struct DevSigner {
+	keys: Vec<(H160, ecdsa::Pair)>,
+	deny_unsafe: DenyUnsafe,
+}
+
+impl DevSigner {
+	/// Sign for the addresses of `keys`.
+	pub fn new(keys: Vec<ecdsa::Pair>) -> Self {
+		Self {
+			keys: keys.into_iter().map(|pair| (evm_address(&pair.public()), pair)).collect(),
+			deny_unsafe: DenyUnsafe::No,
+		}
+	}
+
+	/// Refuse to sign, and list no accounts, where `deny_unsafe` denies unsafe calls.
+	pub fn with_deny_unsafe(self, deny_unsafe: DenyUnsafe) -> Self {
+		Self { deny_unsafe, ..self }
+	}
+
+	fn pair(&self, address: &H160) -> Result<&ecdsa::Pair, JsonRpseeError> {
+		self.deny_unsafe.check_if_safe()?;
+		self.keys
+			.iter()
+			.find(|(a, _)| a == address)
+			.map(|(_, pair)| pair)
+			.ok_or_else(|| error(format!("No signer for {:?}", address)))
+	}
+
+	/// `r`, `s` and the recovery id of a signature of `hash`.
+	fn sign_hash(&self, address: &H160, hash: H256) -> Result<(H256, H256, u8), JsonRpseeError> {
+		let signature = self.pair(address)?.sign_prehashed(hash.as_fixed_bytes());
+		let bytes: &[u8; 65] = signature.as_ref();
+		Ok((H256::from_slice(&bytes[..32]), H256::from_slice(&bytes[32..64]), bytes[64]))
+	}
+
+	/// A signature in the `r ‖ s ‖ v` form wallets return, with `v` of 27 or 28.
+	fn sign_message(&self, address: &H160, hash: H256) -> RpcResult<Bytes> {
+		let (r, s, recovery_id) = self.sign_hash(address, hash)?;
+		let mut signature = [r.as_bytes(), s.as_bytes()].concat();
+		signature.push(27 + recovery_id);
+		Ok(signature.into())
+	}
+}
+
+impl EthSigner for DevSigner {
+	fn accounts(&self) -> Vec<H160> {
+		if self.deny_unsafe.check_if_safe().is_err() {
+			return Vec::new()
+		}
+		self.keys.iter().map(|(address, _)| *address).collect()
+	}
+
+	fn sign(
+		&self,
+		message: TransactionMessage,
+		address: &H160,
+	) -> Result<EthereumTransaction, JsonRpseeError> {
+		Ok(match message {
+			TransactionMessage::Legacy(m) => {
+				let (r, s, recovery_id) = self.sign_hash(address, m.hash())?;
+				let v = match m.chain_id {
+					Some(chain_id) => chain_id * 2 + 35 + recovery_id as u64,
+					None => 27 + recovery_id as u64,
+				};
+				EthereumTransaction::Legacy(LegacyTransaction {
+					nonce: m.nonce,
+					gas_price: m.gas_price,
+					gas_limit: m.gas_limit,
+					action: m.action,
+					value: m.value,
+					input: m.input,
+					signature: TransactionSignature::new(v, r, s)
+						.ok_or_else(|| error("Invalid transaction signature"))?,
+				})
+			},
+			TransactionMessage::EIP2930(m) => {
+				let (r, s, recovery_id) = self.sign_hash(address, m.hash())?;
+				EthereumTransaction::EIP2930(EIP2930Transaction {
+					chain_id: m.chain_id,
+					nonce: m.nonce,
+					gas_price: m.gas_price,
+					gas_limit: m.gas_limit,
+					action: m.action,
+					value: m.value,
+					input: m.input,
+					access_list: m.access_list,
+					odd_y_parity: recovery_id != 0,
+					r,
+					s,
+				})
+			},
+			TransactionMessage::EIP1559(m) => {
+				let (r, s, recovery_id) = self.sign_hash(address, m.hash())?;
+				EthereumTransaction::EIP1559(EIP1559Transaction {
+					chain_id: m.chain_id,
+					nonce: m.nonce,
+					max_priority_fee_per_gas: m.max_priority_fee_per_gas,
+					max_fee_per_gas: m.max_fee_per_gas,
+					gas_limit: m.gas_limit,
+					action: m.action,
+					value: m.value,
+					input: m.input,
+					access_list: m.access_list,
+					odd_y_parity: recovery_id != 0,
+					r,
+					s,
+				})
+			},
+		})
+	}
+}
+
+impl EthSignApiServer for DevSigner {
+	fn sign(&self, address: H160, data: Bytes) -> RpcResult<Bytes> {
+		let mut message = format!("\x19Ethereum Signed Message:\n{}", data.len()).into_bytes();
+		message.extend_from_slice(&data);
+		self.sign_message(&address, keccak_256(&message).into())
+	}
+
+	fn sign_typed_data(&self, address: H160, typed_data: Value) -> RpcResult<Bytes> {
+		let typed_data: TypedData = match typed_data {
+			Value::String(json) => serde_json::from_str(&json),
+			object => serde_json::from_value(object),
+		}
+		.map_err(error)?;
+		let hash = typed_data.signing_hash().map_err(error)?;
+		self.sign_message(&address, hash.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::chain_spec::dev_evm_keys;
+
+	fn recover(signature: &Bytes, hash: [u8; 32]) -> H160 {
+		let public = libsecp256k1::recover(
+			&libsecp256k1::Message::parse(&hash),
+			&libsecp256k1::Signature::parse_standard_slice(&signature[..64]).unwrap(),
+			&libsecp256k1::RecoveryId::parse(signature[64] - 27).unwrap(),
+		)
+		.unwrap();
+		H160::from_slice(&keccak_256(&public.serialize()[1..])[12..])
+	}
+
+	#[test]
+	fn signs_messages_for_its_accounts_only() {
+		let signer = DevSigner::new(dev_evm_keys(2));
+		let accounts = EthSigner::accounts(&signer);
+		assert_eq!(accounts.len(), 2);
+		assert_ne!(accounts[0], accounts[1]);
+
+		let signature =
+			EthSignApiServer::sign(&signer, accounts[1], b"hello world".to_vec().into()).unwrap();
+		let hash = keccak_256(b"\x19Ethereum Signed Message:\n11hello world");
+		assert_eq!(recover(&signature, hash), accounts[1]);
+
+		assert!(EthSignApiServer::sign(&signer, H160::repeat_byte(1), Bytes(vec![])).is_err());
+	}
+
+	#[test]
+	fn refuses_to_sign_where_unsafe_calls_are_denied() {
+		let signer = DevSigner::new(dev_evm_keys(1));
+		let address = EthSigner::accounts(&signer)[0];
+		let signer = signer.with_deny_unsafe(DenyUnsafe::Yes);
+
+		assert!(EthSigner::accounts(&signer).is_empty());
+		assert!(EthSignApiServer::sign(&signer, address, Bytes(vec![])).is_err());
+	}
+}
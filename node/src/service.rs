@@ -1,15 +1,27 @@
 //! Service and ServiceFactory implementation. Specialized wrapper over substrate service.
 
-use node_template_runtime::{self, opaque::Block, RuntimeApi};
+use fc_mapping_sync::{MappingSyncWorker, SyncStrategy};
+use fc_rpc::{EthBlockDataCacheTask, EthTask};
+use futures::{channel::mpsc, prelude::*};
+use node_template_runtime::{self, opaque::Block, Hash, RuntimeApi};
 use sc_client_api::{BlockBackend, ExecutorProvider};
 use sc_consensus_aura::{ImportQueueParams, SlotProportion, StartAuraParams};
+use sc_consensus_manual_seal::{consensus::aura::AuraConsensusDataProvider, EngineCommand};
 pub use sc_executor::NativeElseWasmExecutor;
 use sc_finality_grandpa::SharedVoterState;
 use sc_keystore::LocalKeystore;
-use sc_service::{error::Error as ServiceError, Configuration, TaskManager};
+use sc_service::{
+	error::Error as ServiceError, BasePath, ChainType, Configuration, DatabaseSource, TaskManager,
+};
 use sc_telemetry::{Telemetry, TelemetryWorker};
+use sc_transaction_pool_api::TransactionPool;
 use sp_consensus_aura::sr25519::AuthorityPair as AuraPair;
-use std::{sync::Arc, time::Duration};
+use std::{
+	collections::BTreeMap,
+	path::PathBuf,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
 
 // Our native executor instance.
 pub struct ExecutorDispatch;
@@ -31,20 +43,31 @@ impl sc_executor::NativeExecutionDispatch for ExecutorDispatch {
 	}
 }
 
-pub(crate) type FullClient =
+/// How a development chain authors blocks instead of Aura and GRANDPA.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ArgEnum)]
+pub enum Sealing {
+	/// Seal a block for every transaction, and when asked to through `evm_mine`.
+	Instant,
+	/// Seal blocks only when asked to through `evm_mine`.
+	Manual,
+}
+
+pub type FullClient =
 	sc_service::TFullClient<Block, RuntimeApi, NativeElseWasmExecutor<ExecutorDispatch>>;
+pub type FullPool = sc_transaction_pool::FullPool<Block, FullClient>;
 type FullBackend = sc_service::TFullBackend<Block>;
 type FullSelectChain = sc_consensus::LongestChain<FullBackend, Block>;
 
 pub fn new_partial(
 	config: &Configuration,
+	sealing: Option<Sealing>,
 ) -> Result<
 	sc_service::PartialComponents<
 		FullClient,
 		FullBackend,
 		FullSelectChain,
 		sc_consensus::DefaultImportQueue<Block, FullClient>,
-		sc_transaction_pool::FullPool<Block, FullClient>,
+		FullPool,
 		(
 			sc_finality_grandpa::GrandpaBlockImport<
 				FullBackend,
@@ -110,6 +133,25 @@ pub fn new_partial(
 		telemetry.as_ref().map(|x| x.handle()),
 	)?;
 
+	if sealing.is_some() {
+		let import_queue = sc_consensus_manual_seal::import_queue(
+			Box::new(client.clone()),
+			&task_manager.spawn_essential_handle(),
+			config.prometheus_registry(),
+		);
+
+		return Ok(sc_service::PartialComponents {
+			client,
+			backend,
+			task_manager,
+			import_queue,
+			keystore_container,
+			select_chain,
+			transaction_pool,
+			other: (grandpa_block_import, grandpa_link, telemetry),
+		})
+	}
+
 	let slot_duration = sc_consensus_aura::slot_duration(&*client)?;
 
 	let import_queue =
@@ -149,6 +191,24 @@ pub fn new_partial(
 	})
 }
 
+/// Where the Frontier database, mapping Ethereum to Substrate blocks, lives.
+fn frontier_database_dir(config: &Configuration) -> PathBuf {
+	let config_dir = config
+		.base_path
+		.as_ref()
+		.map(|base_path| base_path.config_dir(config.chain_spec.id()))
+		.unwrap_or_else(|| {
+			BasePath::from_project("", "", "node-template").config_dir(config.chain_spec.id())
+		});
+	config_dir.join("frontier").join("db")
+}
+
+fn open_frontier_backend(config: &Configuration) -> Result<Arc<fc_db::Backend<Block>>, String> {
+	Ok(Arc::new(fc_db::Backend::<Block>::new(&fc_db::DatabaseSettings {
+		source: DatabaseSource::RocksDb { path: frontier_database_dir(config), cache_size: 0 },
+	})?))
+}
+
 fn remote_keystore(_url: &String) -> Result<Arc<LocalKeystore>, &'static str> {
 	// FIXME: here would the concrete keystore be built,
 	//        must return a concrete type (NOT `LocalKeystore`) that
@@ -156,8 +216,42 @@ fn remote_keystore(_url: &String) -> Result<Arc<LocalKeystore>, &'static str> {
 	Err("Remote Keystore not supported.")
 }
 
+/// The parts of a running full node that in-process users, such as tests, drive directly.
+pub struct NewFullBase {
+	/// Runs the node until dropped.
+	pub task_manager: TaskManager,
+	/// The client.
+	pub client: Arc<FullClient>,
+	/// The transaction pool.
+	pub transaction_pool: Arc<FullPool>,
+	/// Sends commands to the manual seal task, if blocks are sealed on demand.
+	pub command_sink: Option<mpsc::Sender<EngineCommand<Hash>>>,
+}
+
 /// Builds a new service for a full client.
-pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError> {
+///
+/// With `sealing`, which development chains only accept, blocks are sealed on demand instead of
+/// by Aura and GRANDPA, and the dev cheatcode RPCs are enabled. On development chains, the first
+/// `dev_accounts` keys of [`crate::chain_spec::dev_evm_keys`] sign for the Ethereum RPCs.
+pub fn new_full(
+	config: Configuration,
+	sealing: Option<Sealing>,
+	dev_accounts: u32,
+) -> Result<TaskManager, ServiceError> {
+	new_full_base(config, sealing, dev_accounts)
+		.map(|NewFullBase { task_manager, .. }| task_manager)
+}
+
+/// Like [`new_full`], but also returns the parts of the node to drive it in process.
+pub fn new_full_base(
+	mut config: Configuration,
+	sealing: Option<Sealing>,
+	dev_accounts: u32,
+) -> Result<NewFullBase, ServiceError> {
+	if sealing.is_some() && config.chain_spec.chain_type() != ChainType::Development {
+		return Err(ServiceError::Other("Sealing is only supported on development chains.".into()))
+	}
+
 	let sc_service::PartialComponents {
 		client,
 		backend,
@@ -167,7 +261,7 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 		select_chain,
 		transaction_pool,
 		other: (block_import, grandpa_link, mut telemetry),
-	} = new_partial(&config)?;
+	} = new_partial(&config, sealing)?;
 
 	if let Some(url) = &config.keystore_remote {
 		match remote_keystore(url) {
@@ -214,6 +308,22 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 		);
 	}
 
+	let frontier_backend = open_frontier_backend(&config)?;
+	let overrides = crate::rpc::overrides_handle(client.clone());
+	let filter_pool = Arc::new(Mutex::new(BTreeMap::new()));
+	let fee_history_cache = Arc::new(Mutex::new(BTreeMap::new()));
+	let fee_history_cache_limit = 2048;
+	let block_data_cache = Arc::new(EthBlockDataCacheTask::new(
+		task_manager.spawn_handle(),
+		overrides.clone(),
+		50,
+		50,
+		config.prometheus_registry().cloned(),
+	));
+	let dev_signer = (config.chain_spec.chain_type() == ChainType::Development &&
+		dev_accounts > 0)
+		.then(|| crate::rpc::DevSigner::new(crate::chain_spec::dev_evm_keys(dev_accounts)));
+
 	let role = config.role.clone();
 	let force_authoring = config.force_authoring;
 	let backoff_authoring_blocks: Option<()> = None;
@@ -221,13 +331,46 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 	let enable_grandpa = !config.disable_grandpa;
 	let prometheus_registry = config.prometheus_registry().cloned();
 
+	let (command_sink, commands) = mpsc::channel(1000);
+	let clock = Arc::new(crate::rpc::DevClock::new(
+		sc_consensus_aura::slot_duration(&*client)?.as_duration(),
+	));
+
 	let rpc_extensions_builder = {
 		let client = client.clone();
 		let pool = transaction_pool.clone();
+		let backend = backend.clone();
+		let network = network.clone();
+		let is_authority = role.is_authority();
+		let frontier_backend = frontier_backend.clone();
+		let overrides = overrides.clone();
+		let filter_pool = filter_pool.clone();
+		let fee_history_cache = fee_history_cache.clone();
+		let command_sink = command_sink.clone();
+		let clock = clock.clone();
 
 		Box::new(move |deny_unsafe, _| {
-			let deps =
-				crate::rpc::FullDeps { client: client.clone(), pool: pool.clone(), deny_unsafe };
+			let dev = sealing.map(|_| crate::rpc::DevDeps {
+				backend: backend.clone(),
+				command_sink: command_sink.clone(),
+				clock: clock.clone(),
+			});
+			let deps = crate::rpc::FullDeps {
+				client: client.clone(),
+				pool: pool.clone(),
+				graph: pool.pool().clone(),
+				deny_unsafe,
+				network: network.clone(),
+				is_authority,
+				frontier_backend: frontier_backend.clone(),
+				overrides: overrides.clone(),
+				block_data_cache: block_data_cache.clone(),
+				filter_pool: filter_pool.clone(),
+				fee_history_cache: fee_history_cache.clone(),
+				fee_history_cache_limit,
+				dev_signer: dev_signer.clone(),
+				dev,
+			};
 			crate::rpc::create_full(deps).map_err(Into::into)
 		})
 	};
@@ -239,17 +382,112 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 		task_manager: &mut task_manager,
 		transaction_pool: transaction_pool.clone(),
 		rpc_builder: rpc_extensions_builder,
-		backend,
+		backend: backend.clone(),
 		system_rpc_tx,
 		config,
 		telemetry: telemetry.as_mut(),
 	})?;
 
-	if role.is_authority() {
+	task_manager.spawn_essential_handle().spawn(
+		"frontier-mapping-sync-worker",
+		None,
+		MappingSyncWorker::new(
+			client.import_notification_stream(),
+			Duration::new(6, 0),
+			client.clone(),
+			backend,
+			frontier_backend.clone(),
+			3,
+			0,
+			SyncStrategy::Normal,
+		)
+		.for_each(|()| future::ready(())),
+	);
+	// Filters untouched for this many blocks are dropped.
+	const FILTER_RETAIN_THRESHOLD: u64 = 100;
+	task_manager.spawn_essential_handle().spawn(
+		"frontier-filter-pool",
+		None,
+		EthTask::filter_pool_task(client.clone(), filter_pool, FILTER_RETAIN_THRESHOLD),
+	);
+	task_manager.spawn_essential_handle().spawn(
+		"frontier-schema-cache-task",
+		None,
+		EthTask::ethereum_schema_cache_task(client.clone(), frontier_backend),
+	);
+	task_manager.spawn_essential_handle().spawn(
+		"frontier-fee-history",
+		None,
+		EthTask::fee_history_task(
+			client.clone(),
+			overrides,
+			fee_history_cache,
+			fee_history_cache_limit,
+		),
+	);
+
+	if let Some(sealing) = sealing {
 		let proposer_factory = sc_basic_authorship::ProposerFactory::new(
 			task_manager.spawn_handle(),
 			client.clone(),
+			transaction_pool.clone(),
+			prometheus_registry.as_ref(),
+			telemetry.as_ref().map(|x| x.handle()),
+		);
+
+		let commands_stream = match sealing {
+			Sealing::Instant => Box::new(stream::select(
+				commands,
+				transaction_pool.import_notification_stream().map(|_| {
+					EngineCommand::SealNewBlock {
+						create_empty: false,
+						finalize: false,
+						parent_hash: None,
+						sender: None,
+					}
+				}),
+			)) as Box<dyn Stream<Item = _> + Send + Unpin>,
+			Sealing::Manual => Box::new(commands),
+		};
+
+		let manual_seal =
+			sc_consensus_manual_seal::run_manual_seal(sc_consensus_manual_seal::ManualSealParams {
+				block_import: client.clone(),
+				env: proposer_factory,
+				client: client.clone(),
+				pool: transaction_pool.clone(),
+				commands_stream,
+				select_chain,
+				consensus_data_provider: Some(Box::new(AuraConsensusDataProvider::new(
+					client.clone(),
+				))),
+				create_inherent_data_providers: move |_, ()| {
+					let timestamp = clock.next();
+					async move { Ok(sp_timestamp::InherentDataProvider::new(timestamp)) }
+				},
+			});
+
+		// Blocks are not finalized, so that `evm_revert` can revert them.
+		task_manager.spawn_essential_handle().spawn_blocking(
+			"manual-seal",
+			Some("block-authoring"),
+			manual_seal,
+		);
+
+		network_starter.start_network();
+		return Ok(NewFullBase {
+			task_manager,
+			client,
 			transaction_pool,
+			command_sink: Some(command_sink),
+		})
+	}
+
+	if role.is_authority() {
+		let proposer_factory = sc_basic_authorship::ProposerFactory::new(
+			task_manager.spawn_handle(),
+			client.clone(),
+			transaction_pool.clone(),
 			prometheus_registry.as_ref(),
 			telemetry.as_ref().map(|x| x.handle()),
 		);
@@ -262,7 +500,7 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 		let aura = sc_consensus_aura::start_aura::<AuraPair, _, _, _, _, _, _, _, _, _, _, _>(
 			StartAuraParams {
 				slot_duration,
-				client,
+				client: client.clone(),
 				select_chain,
 				block_import,
 				proposer_factory,
@@ -340,5 +578,5 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 	}
 
 	network_starter.start_network();
-	Ok(task_manager)
+	Ok(NewFullBase { task_manager, client, transaction_pool, command_sink: None })
 }
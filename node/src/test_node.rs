@@ -0,0 +1,301 @@
+//! A development node running in process, for end-to-end tests.
+//!
+//! Each [`TestNode`] has its own temporary base path and ports, so tests can run in parallel:
+//!
+//! ```ignore
+//! let node = TestNode::builder().sealing(Sealing::Instant).start().await?;
+//! let call = BalancesCall::transfer { dest: Bob.to_account_id().into(), value: UNIT };
+//! let block = node.submit_signed(Alice, call).await?.in_block().await?;
+//! ```
+//!
+//! The node is stopped when the [`TestNode`] is dropped. It needs to be started from a
+//! multi-threaded Tokio runtime.
+
+use crate::{
+	chain_spec,
+	command_helper::{create_extrinsic, ExtrinsicParams},
+	service::{self, FullClient, FullPool, NewFullBase, Sealing},
+};
+use clap::Parser;
+use futures::{
+	channel::{mpsc, oneshot},
+	SinkExt, StreamExt,
+};
+use jsonrpsee::{
+	core::client::ClientT,
+	rpc_params,
+	ws_client::{WsClient, WsClientBuilder},
+};
+use node_template_runtime::{self as runtime, Hash};
+use sc_cli::{ChainSpec, CliConfiguration, Result, RuntimeVersion, SubstrateCli};
+use sc_consensus_manual_seal::EngineCommand;
+use sc_service::TaskManager;
+use sc_transaction_pool_api::{
+	TransactionPool, TransactionSource, TransactionStatus, TransactionStatusStreamFor,
+};
+use sp_core::{crypto::Ss58Codec, Encode};
+use sp_keyring::Sr25519Keyring;
+use sp_runtime::generic::BlockId;
+use std::{net::TcpListener, pin::Pin, sync::Arc};
+use tempfile::TempDir;
+
+fn error(e: impl ToString) -> sc_cli::Error {
+	sc_cli::Error::Application(e.to_string().into())
+}
+
+/// What `create_configuration` needs to know about the node, and the chain it runs.
+struct TestCli {
+	dev_accounts: u32,
+}
+
+impl SubstrateCli for TestCli {
+	fn impl_name() -> String {
+		"Substrate Test Node".into()
+	}
+
+	fn impl_version() -> String {
+		env!("SUBSTRATE_CLI_IMPL_VERSION").into()
+	}
+
+	fn description() -> String {
+		env!("CARGO_PKG_DESCRIPTION").into()
+	}
+
+	fn author() -> String {
+		env!("CARGO_PKG_AUTHORS").into()
+	}
+
+	fn support_url() -> String {
+		"support.anonymous.an".into()
+	}
+
+	fn copyright_start_year() -> i32 {
+		2017
+	}
+
+	fn load_spec(&self, _: &str) -> std::result::Result<Box<dyn ChainSpec>, String> {
+		Ok(Box::new(chain_spec::development_config(self.dev_accounts)?))
+	}
+
+	fn native_runtime_version(_: &Box<dyn ChainSpec>) -> &'static RuntimeVersion {
+		&runtime::VERSION
+	}
+}
+
+/// A port nothing listens on right now.
+fn free_port() -> Result<u16> {
+	Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
+
+/// Configures a [`TestNode`].
+#[derive(Debug, Default)]
+pub struct TestNodeBuilder {
+	sealing: Option<Sealing>,
+	dev_accounts: u32,
+}
+
+impl TestNodeBuilder {
+	/// Seal blocks on demand instead of running Aura and GRANDPA.
+	pub fn sealing(mut self, sealing: Sealing) -> Self {
+		self.sealing = Some(sealing);
+		self
+	}
+
+	/// Endow this many development EVM accounts and sign for them through the Ethereum RPCs.
+	pub fn dev_accounts(mut self, dev_accounts: u32) -> Self {
+		self.dev_accounts = dev_accounts;
+		self
+	}
+
+	/// Start the node, and connect to its RPC server.
+	pub async fn start(self) -> Result<TestNode> {
+		let base_path = tempfile::tempdir()?;
+		let ws_port = free_port()?;
+		let args = [
+			"node-template".to_string(),
+			"--dev".into(),
+			"--base-path".into(),
+			base_path.path().display().to_string(),
+			"--port".into(),
+			free_port()?.to_string(),
+			"--rpc-port".into(),
+			free_port()?.to_string(),
+			"--ws-port".into(),
+			ws_port.to_string(),
+			"--no-mdns".into(),
+			"--no-prometheus".into(),
+			"--no-telemetry".into(),
+		];
+		let config = sc_cli::RunCmd::try_parse_from(args).map_err(error)?.create_configuration(
+			&TestCli { dev_accounts: self.dev_accounts },
+			tokio::runtime::Handle::current(),
+		)?;
+		let NewFullBase { task_manager, client, transaction_pool, command_sink } =
+			service::new_full_base(config, self.sealing, self.dev_accounts)?;
+
+		let ws_url = format!("ws://127.0.0.1:{}", ws_port);
+		let rpc = WsClientBuilder::default().build(&ws_url).await.map_err(error)?;
+		Ok(TestNode {
+			rpc,
+			ws_url,
+			client,
+			pool: transaction_pool,
+			command_sink,
+			task_manager,
+			_base_path: base_path,
+		})
+	}
+}
+
+/// A development node running in process. See the [module docs](self).
+pub struct TestNode {
+	rpc: WsClient,
+	ws_url: String,
+	client: Arc<FullClient>,
+	pool: Arc<FullPool>,
+	command_sink: Option<mpsc::Sender<EngineCommand<Hash>>>,
+	task_manager: TaskManager,
+	// Removed after the node, which is dropped first, stopped using it.
+	_base_path: TempDir,
+}
+
+impl TestNode {
+	/// Configure a node, which authors blocks with Aura and finalizes them with GRANDPA unless
+	/// told otherwise.
+	pub fn builder() -> TestNodeBuilder {
+		TestNodeBuilder::default()
+	}
+
+	/// The client.
+	pub fn client(&self) -> &Arc<FullClient> {
+		&self.client
+	}
+
+	/// The transaction pool.
+	pub fn pool(&self) -> &Arc<FullPool> {
+		&self.pool
+	}
+
+	/// A client connected to the node's WebSocket RPC server.
+	pub fn rpc(&self) -> &WsClient {
+		&self.rpc
+	}
+
+	/// The URL of the node's WebSocket RPC server, to connect other clients.
+	pub fn ws_url(&self) -> &str {
+		&self.ws_url
+	}
+
+	/// The task manager, to spawn tasks that stop with the node.
+	pub fn task_manager(&self) -> &TaskManager {
+		&self.task_manager
+	}
+
+	/// Sign `call` for `signer`, with the nonce after those of its transactions in the pool.
+	pub async fn sign(
+		&self,
+		signer: Sr25519Keyring,
+		call: impl Into<runtime::Call>,
+	) -> Result<runtime::UncheckedExtrinsic> {
+		let call = call.into();
+		let nonce: runtime::Index = self
+			.rpc
+			.request("system_accountNextIndex", rpc_params![signer.to_account_id().to_ss58check()])
+			.await
+			.map_err(error)?;
+		let params = ExtrinsicParams::immortal(nonce, self.client.chain_info().genesis_hash);
+		Ok(create_extrinsic(signer.pair(), call, params))
+	}
+
+	/// Submit `extrinsic` to the pool, to watch it until it is included.
+	pub async fn submit(&self, extrinsic: runtime::UncheckedExtrinsic) -> Result<Submitted> {
+		let hash = Hash(sp_core::blake2_256(&extrinsic.encode()));
+		let at = BlockId::hash(self.client.chain_info().best_hash);
+		let status = self
+			.pool
+			.submit_and_watch(&at, TransactionSource::External, extrinsic.into())
+			.await
+			.map_err(error)?;
+		Ok(Submitted { hash, status })
+	}
+
+	/// Sign `call` for `signer` and submit it.
+	pub async fn submit_signed(
+		&self,
+		signer: Sr25519Keyring,
+		call: impl Into<runtime::Call>,
+	) -> Result<Submitted> {
+		self.submit(self.sign(signer, call).await?).await
+	}
+
+	/// Seal a block, finalizing it if `finalize`, and return its hash. Only for nodes built with
+	/// [`TestNodeBuilder::sealing`].
+	pub async fn seal(&self, finalize: bool) -> Result<Hash> {
+		let (sender, receiver) = oneshot::channel();
+		self.command_sink
+			.clone()
+			.ok_or("The node does not seal blocks on demand")?
+			.send(EngineCommand::SealNewBlock {
+				create_empty: true,
+				finalize,
+				parent_hash: None,
+				sender: Some(sender),
+			})
+			.await
+			.map_err(error)?;
+		Ok(receiver.await.map_err(error)?.map_err(error)?.hash)
+	}
+}
+
+/// A transaction in the pool, and what becomes of it.
+pub struct Submitted {
+	hash: Hash,
+	status: Pin<Box<TransactionStatusStreamFor<FullPool>>>,
+}
+
+impl Submitted {
+	/// The hash of the transaction.
+	pub fn hash(&self) -> Hash {
+		self.hash
+	}
+
+	/// Wait for the transaction to be included in a block, and return its hash.
+	pub async fn in_block(&mut self) -> Result<Hash> {
+		self.wait_for(|status| match status {
+			TransactionStatus::InBlock(block) | TransactionStatus::Finalized(block) => Some(*block),
+			_ => None,
+		})
+		.await
+	}
+
+	/// Wait for the block including the transaction to be finalized, and return its hash.
+	///
+	/// Blocks sealed on demand are only finalized by [`TestNode::seal`] with `finalize`.
+	pub async fn finalized(&mut self) -> Result<Hash> {
+		self.wait_for(|status| match status {
+			TransactionStatus::Finalized(block) => Some(*block),
+			_ => None,
+		})
+		.await
+	}
+
+	async fn wait_for(
+		&mut self,
+		done: impl Fn(&TransactionStatus<Hash, Hash>) -> Option<Hash>,
+	) -> Result<Hash> {
+		while let Some(status) = self.status.next().await {
+			if let Some(block) = done(&status) {
+				return Ok(block)
+			}
+			match status {
+				TransactionStatus::Usurped(_) |
+				TransactionStatus::Dropped |
+				TransactionStatus::Invalid |
+				TransactionStatus::FinalityTimeout(_) =>
+					return Err(format!("Transaction {:?} is {:?}", self.hash, status).into()),
+				_ => (),
+			}
+		}
+		Err(format!("The pool stopped watching transaction {:?}", self.hash).into())
+	}
+}
@@ -0,0 +1,452 @@
+//! The `tx` subcommand: builds a runtime call from JSON arguments, following the runtime's
+//! metadata, then signs it and prints or submits it.
+
+use crate::{
+	cli::TxCmd,
+	command_helper::{create_extrinsic, ExtrinsicParams},
+	metadata,
+};
+use codec::{Compact, DecodeAll, Encode};
+use jsonrpsee::{
+	core::client::ClientT,
+	rpc_params,
+	ws_client::{WsClient, WsClientBuilder},
+};
+use node_template_runtime::{self as runtime, opaque};
+use scale_info::{form::PortableForm, Field, PortableRegistry, TypeDef, TypeDefPrimitive};
+use serde_json::{json, Value};
+use sp_core::{
+	bytes,
+	crypto::{AccountId32, Ss58Codec},
+	sr25519, Bytes, Pair, H256, U256,
+};
+use sp_runtime::generic::Era;
+use std::str::FromStr;
+
+type EncodeResult<T = ()> = std::result::Result<T, String>;
+
+/// How long a transaction is valid for: `immortal`, or about this many blocks.
+#[derive(Debug, Copy, Clone)]
+pub struct Mortality(Option<u64>);
+
+impl FromStr for Mortality {
+	type Err = String;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		match s {
+			"immortal" => Ok(Mortality(None)),
+			period => period.parse().map(|period| Mortality(Some(period))).map_err(|_| {
+				format!("Expected `immortal` or a number of blocks, not `{}`", period)
+			}),
+		}
+	}
+}
+
+fn rpc_error(e: impl ToString) -> sc_cli::Error {
+	sc_cli::Error::Application(e.to_string().into())
+}
+
+/// The call `pallet::call(args)`, with the arguments in the order the call declares them.
+pub fn encode_call(pallet: &str, call: &str, args: &[Value]) -> EncodeResult<runtime::Call> {
+	let metadata = metadata::v14()?;
+	let registry = &metadata.types;
+	let mut with_calls = metadata.pallets.iter().filter(|pallet| pallet.calls.is_some());
+	let pallet_metadata = with_calls.clone().find(|p| p.name == pallet).ok_or_else(|| {
+		let names: Vec<_> = with_calls.map(|pallet| pallet.name.as_str()).collect();
+		format!("No pallet `{}` with calls, try one of {}", pallet, names.join(", "))
+	})?;
+	let calls = pallet_metadata.calls.as_ref().expect("filtered on calls above; qed").ty.id();
+	let calls = match registry.resolve(calls).map(|ty| ty.type_def()) {
+		Some(TypeDef::Variant(calls)) => calls.variants(),
+		_ => return Err(format!("The calls of `{}` are not an enum", pallet)),
+	};
+	let variant = calls.iter().find(|variant| variant.name() == call).ok_or_else(|| {
+		let names: Vec<_> = calls.iter().map(|variant| variant.name().as_str()).collect();
+		format!("`{}` has no call `{}`, try one of {}", pallet, call, names.join(", "))
+	})?;
+
+	let fields = variant.fields();
+	if args.len() != fields.len() {
+		let params: Vec<_> = fields
+			.iter()
+			.map(|field| {
+				let name = field.name().map_or("_", String::as_str);
+				format!("{}: {}", name, field.type_name().map_or("?", String::as_str))
+			})
+			.collect();
+		return Err(format!(
+			"`{}::{}` takes {} arguments, ({}), not {}",
+			pallet,
+			call,
+			fields.len(),
+			params.join(", "),
+			args.len()
+		))
+	}
+
+	let mut encoded = vec![pallet_metadata.index, variant.index()];
+	for (field, arg) in fields.iter().zip(args) {
+		encode(registry, field.ty().id(), arg, &mut encoded).map_err(|e| {
+			format!("Argument `{}`: {}", field.name().map_or("_", String::as_str), e)
+		})?;
+	}
+	runtime::Call::decode_all(&mut &encoded[..])
+		.map_err(|e| format!("The arguments do not make a valid call: {}", e))
+}
+
+fn is_u8(registry: &PortableRegistry, id: u32) -> bool {
+	matches!(
+		registry.resolve(id).map(|ty| ty.type_def()),
+		Some(TypeDef::Primitive(TypeDefPrimitive::U8))
+	)
+}
+
+/// Encode `value` as the type `id` of `registry`.
+///
+/// Composites are JSON objects, or arrays when their fields are unnamed, and a composite of a
+/// single field may be given as that field. Enum variants are `"Variant"` or
+/// `{"Variant": fields}`, options may be given as `null` or their value, and multi-addresses as
+/// their account. Accounts are SS58 addresses, byte sequences and arrays `0x`-prefixed hex, and
+/// byte sequences may also be UTF-8 strings. Integers may be strings, to go beyond JSON numbers.
+fn encode(registry: &PortableRegistry, id: u32, value: &Value, out: &mut Vec<u8>) -> EncodeResult {
+	let ty = registry.resolve(id).ok_or_else(|| format!("Unknown type {}", id))?;
+	let name = ty.path().segments().last().map(String::as_str);
+	match ty.type_def() {
+		TypeDef::Composite(composite) => match (name, value) {
+			(Some("AccountId32"), Value::String(address)) if !address.starts_with("0x") => {
+				let account = AccountId32::from_ss58check(address)
+					.map_err(|e| format!("Invalid address `{}`: {:?}", address, e))?;
+				account.encode_to(out);
+				Ok(())
+			},
+			_ => encode_fields(registry, composite.fields(), value, out),
+		},
+		TypeDef::Variant(variant) => {
+			let variants = variant.variants();
+			let is_variant = |name: &str| variants.iter().any(|variant| variant.name() == name);
+			let value = match (name, value) {
+				(Some("Option"), Value::Null) => json!("None"),
+				(Some("Option"), Value::Object(map))
+					if map.len() == 1 && map.keys().all(|key| is_variant(key)) =>
+					value.clone(),
+				(Some("Option"), value) => json!({ "Some": value }),
+				(Some("MultiAddress"), Value::String(account)) if !is_variant(account) =>
+					json!({ "Id": account }),
+				_ => value.clone(),
+			};
+			let (name, fields) = match &value {
+				Value::String(name) => (name.as_str(), &Value::Null),
+				Value::Object(map) if map.len() == 1 => {
+					let (name, fields) = map.iter().next().expect("one entry; qed");
+					(name.as_str(), fields)
+				},
+				_ => return Err(format!("Expected a variant, not {}", value)),
+			};
+			let variant = variants.iter().find(|variant| variant.name() == name);
+			let variant = variant.ok_or_else(|| {
+				let names: Vec<_> =
+					variants.iter().map(|variant| variant.name().as_str()).collect();
+				format!("No variant `{}`, try one of {}", name, names.join(", "))
+			})?;
+			out.push(variant.index());
+			encode_fields(registry, variant.fields(), fields, out)
+		},
+		TypeDef::Sequence(sequence) => {
+			let item = sequence.type_param().id();
+			if let (Value::String(s), true) = (value, is_u8(registry, item)) {
+				let bytes = if s.starts_with("0x") {
+					bytes::from_hex(s).map_err(|e| format!("Invalid hex `{}`: {}", s, e))?
+				} else {
+					s.as_bytes().to_vec()
+				};
+				bytes.encode_to(out);
+				return Ok(())
+			}
+			let items =
+				value.as_array().ok_or_else(|| format!("Expected an array, not {}", value))?;
+			Compact(items.len() as u32).encode_to(out);
+			items.iter().try_for_each(|value| encode(registry, item, value, out))
+		},
+		TypeDef::Array(array) => {
+			let (item, len) = (array.type_param().id(), array.len() as usize);
+			if let (Value::String(s), true) = (value, is_u8(registry, item)) {
+				let bytes =
+					bytes::from_hex(s).map_err(|e| format!("Invalid hex `{}`: {}", s, e))?;
+				if bytes.len() != len {
+					return Err(format!("Expected {} bytes, not {}", len, bytes.len()))
+				}
+				out.extend(bytes);
+				return Ok(())
+			}
+			match value.as_array() {
+				Some(items) if items.len() == len =>
+					items.iter().try_for_each(|value| encode(registry, item, value, out)),
+				_ => Err(format!("Expected an array of {} items, not {}", len, value)),
+			}
+		},
+		TypeDef::Tuple(tuple) => match (tuple.fields(), value.as_array()) {
+			([], _) => Ok(()),
+			(items, Some(values)) if items.len() == values.len() => items
+				.iter()
+				.zip(values)
+				.try_for_each(|(item, value)| encode(registry, item.id(), value, out)),
+			(items, _) => Err(format!("Expected an array of {} items, not {}", items.len(), value)),
+		},
+		TypeDef::Primitive(primitive) => encode_primitive(primitive, value, out),
+		TypeDef::Compact(_) => {
+			Compact(unsigned::<u128>(value)?).encode_to(out);
+			Ok(())
+		},
+		TypeDef::BitSequence(_) => Err("Bit sequences are not supported".into()),
+	}
+}
+
+fn encode_fields(
+	registry: &PortableRegistry,
+	fields: &[Field<PortableForm>],
+	value: &Value,
+	out: &mut Vec<u8>,
+) -> EncodeResult {
+	match (fields, value) {
+		([], _) => Ok(()),
+		(fields, Value::Object(map)) if fields.iter().all(|field| field.name().is_some()) => {
+			if let Some(unknown) =
+				map.keys().find(|key| !fields.iter().any(|field| field.name() == Some(key)))
+			{
+				return Err(format!("Unknown field `{}`", unknown))
+			}
+			fields.iter().try_for_each(|field| {
+				let name = field.name().expect("all fields are named; qed");
+				let value = map.get(name).ok_or_else(|| format!("Missing field `{}`", name))?;
+				encode(registry, field.ty().id(), value, out)
+					.map_err(|e| format!("Field `{}`: {}", name, e))
+			})
+		},
+		([field], value) => encode(registry, field.ty().id(), value, out),
+		(fields, Value::Array(values)) if fields.len() == values.len() => fields
+			.iter()
+			.zip(values)
+			.try_for_each(|(field, value)| encode(registry, field.ty().id(), value, out)),
+		(fields, value) => Err(format!("Expected {} fields, not {}", fields.len(), value)),
+	}
+}
+
+fn unsigned<T: TryFrom<u128>>(value: &Value) -> EncodeResult<T> {
+	let n: u128 = match value {
+		Value::Number(n) => n.as_u64().map(Into::into),
+		Value::String(s) => s.parse().ok(),
+		_ => None,
+	}
+	.ok_or_else(|| format!("Expected an unsigned integer, not {}", value))?;
+	T::try_from(n).map_err(|_| format!("{} is out of range", n))
+}
+
+fn signed<T: TryFrom<i128>>(value: &Value) -> EncodeResult<T> {
+	let n: i128 = match value {
+		Value::Number(n) => n.as_i64().map(Into::into),
+		Value::String(s) => s.parse().ok(),
+		_ => None,
+	}
+	.ok_or_else(|| format!("Expected an integer, not {}", value))?;
+	T::try_from(n).map_err(|_| format!("{} is out of range", n))
+}
+
+fn encode_primitive(
+	primitive: &TypeDefPrimitive,
+	value: &Value,
+	out: &mut Vec<u8>,
+) -> EncodeResult {
+	match primitive {
+		TypeDefPrimitive::Bool => value
+			.as_bool()
+			.ok_or_else(|| format!("Expected a boolean, not {}", value))?
+			.encode_to(out),
+		TypeDefPrimitive::Str => value
+			.as_str()
+			.ok_or_else(|| format!("Expected a string, not {}", value))?
+			.encode_to(out),
+		TypeDefPrimitive::U8 => unsigned::<u8>(value)?.encode_to(out),
+		TypeDefPrimitive::U16 => unsigned::<u16>(value)?.encode_to(out),
+		TypeDefPrimitive::U32 => unsigned::<u32>(value)?.encode_to(out),
+		TypeDefPrimitive::U64 => unsigned::<u64>(value)?.encode_to(out),
+		TypeDefPrimitive::U128 => unsigned::<u128>(value)?.encode_to(out),
+		TypeDefPrimitive::U256 => match value {
+			Value::String(s) if s.starts_with("0x") => U256::from_str_radix(&s[2..], 16).ok(),
+			Value::String(s) => U256::from_dec_str(s).ok(),
+			value => value.as_u64().map(Into::into),
+		}
+		.ok_or_else(|| format!("Expected an unsigned integer, not {}", value))?
+		.encode_to(out),
+		TypeDefPrimitive::I8 => signed::<i8>(value)?.encode_to(out),
+		TypeDefPrimitive::I16 => signed::<i16>(value)?.encode_to(out),
+		TypeDefPrimitive::I32 => signed::<i32>(value)?.encode_to(out),
+		TypeDefPrimitive::I64 => signed::<i64>(value)?.encode_to(out),
+		TypeDefPrimitive::I128 => signed::<i128>(value)?.encode_to(out),
+		TypeDefPrimitive::Char | TypeDefPrimitive::I256 =>
+			return Err(format!("{:?} arguments are not supported", primitive)),
+	}
+	Ok(())
+}
+
+/// Checks that the node runs the spec and transaction versions the extrinsic is signed for.
+async fn check_runtime_version(
+	client: &WsClient,
+	expected_spec_version: u32,
+	expected_transaction_version: u32,
+) -> sc_cli::Result<()> {
+	let version: Value = client
+		.request("state_getRuntimeVersion", rpc_params![])
+		.await
+		.map_err(rpc_error)?;
+	let spec_version = version["specVersion"].as_u64();
+	let transaction_version = version["transactionVersion"].as_u64();
+	if spec_version != Some(expected_spec_version.into()) ||
+		transaction_version != Some(expected_transaction_version.into())
+	{
+		return Err(format!(
+			"The node runs spec version {:?} and transaction version {:?}, but the extrinsic is \
+			signed for {} and {}",
+			spec_version, transaction_version, expected_spec_version, expected_transaction_version,
+		)
+		.into())
+	}
+	Ok(())
+}
+
+/// Builds, signs and prints or submits the extrinsic described by `cmd`.
+pub async fn run(cmd: TxCmd) -> sc_cli::Result<()> {
+	// Arguments that are not JSON are strings, such as SS58 addresses.
+	let args: Vec<Value> = cmd
+		.args
+		.iter()
+		.map(|arg| serde_json::from_str(arg).unwrap_or_else(|_| Value::String(arg.clone())))
+		.collect();
+	let call = encode_call(&cmd.pallet, &cmd.call, &args)?;
+	let signer = sr25519::Pair::from_string(&cmd.suri, None)
+		.map_err(|e| format!("Invalid `--suri`: {:?}", e))?;
+
+	let spec_version = cmd.spec_version.unwrap_or(runtime::VERSION.spec_version);
+	let transaction_version =
+		cmd.transaction_version.unwrap_or(runtime::VERSION.transaction_version);
+
+	let client = if cmd.offline {
+		None
+	} else {
+		let client = WsClientBuilder::default().build(&cmd.url).await.map_err(rpc_error)?;
+		check_runtime_version(&client, spec_version, transaction_version).await?;
+		Some(client)
+	};
+	let offline = |flag| format!("Offline signing needs `{}`", flag);
+
+	let nonce = match (cmd.nonce, &client) {
+		(Some(nonce), _) => nonce,
+		(None, Some(client)) => {
+			let account = AccountId32::from(signer.public()).to_ss58check();
+			client
+				.request("system_accountNextIndex", rpc_params![account])
+				.await
+				.map_err(rpc_error)?
+		},
+		(None, None) => return Err(offline("--nonce").into()),
+	};
+	let genesis_hash = match (cmd.genesis_hash, &client) {
+		(Some(hash), _) => hash,
+		(None, Some(client)) => client
+			.request("chain_getBlockHash", rpc_params![0u32])
+			.await
+			.map_err(rpc_error)?,
+		(None, None) => return Err(offline("--genesis-hash").into()),
+	};
+	let (era, era_block_hash) = match (cmd.era.0, cmd.block_hash.zip(cmd.block_number), &client) {
+		(None, _, _) => (Era::Immortal, genesis_hash),
+		(Some(period), Some((hash, number)), _) => (Era::mortal(period, number.into()), hash),
+		(Some(period), None, Some(client)) => {
+			let hash: H256 = client
+				.request("chain_getFinalizedHead", rpc_params![])
+				.await
+				.map_err(rpc_error)?;
+			let header: opaque::Header =
+				client.request("chain_getHeader", rpc_params![hash]).await.map_err(rpc_error)?;
+			(Era::mortal(period, header.number.into()), hash)
+		},
+		(Some(_), None, None) =>
+			return Err(offline("--block-hash` and `--block-number`, or `--era immortal").into()),
+	};
+
+	let params = ExtrinsicParams {
+		nonce,
+		era,
+		tip: cmd.tip,
+		genesis_hash,
+		era_block_hash,
+		spec_version,
+		transaction_version,
+	};
+	let extrinsic = Bytes(create_extrinsic(signer, call, params).encode());
+
+	match client {
+		Some(client) if cmd.submit => {
+			let hash: H256 = client
+				.request("author_submitExtrinsic", rpc_params![extrinsic])
+				.await
+				.map_err(rpc_error)?;
+			println!("{:?}", hash);
+		},
+		_ => println!("{}", bytes::to_hex(&extrinsic, false)),
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use node_template_runtime::{
+		pallet_template, BalancesCall, Runtime, SudoCall, SystemCall, UNIT,
+	};
+	use sp_keyring::Sr25519Keyring::Bob;
+
+	fn do_something(something: u32) -> runtime::Call {
+		pallet_template::Call::<Runtime>::do_something { something }.into()
+	}
+
+	#[test]
+	fn calls_are_encoded_from_json_arguments() {
+		let call = encode_call("TemplateModule", "do_something", &[json!(42)]);
+		assert_eq!(call, Ok(do_something(42)));
+
+		let bob = Bob.to_account_id();
+		let args = [json!(bob.to_ss58check()), json!(UNIT.to_string())];
+		assert_eq!(
+			encode_call("Balances", "transfer", &args),
+			Ok(BalancesCall::transfer { dest: bob.clone().into(), value: UNIT }.into())
+		);
+		assert_eq!(
+			encode_call("Balances", "transfer", &[json!({ "Id": bob.to_ss58check() }), json!(1)]),
+			Ok(BalancesCall::transfer { dest: bob.into(), value: 1 }.into())
+		);
+
+		let remark = |remark: &[u8]| Ok(SystemCall::remark { remark: remark.to_vec() }.into());
+		assert_eq!(encode_call("System", "remark", &[json!("hello")]), remark(b"hello"));
+		assert_eq!(encode_call("System", "remark", &[json!("0x0102")]), remark(&[1, 2]));
+		assert_eq!(encode_call("System", "remark", &[json!([1, 2])]), remark(&[1, 2]));
+	}
+
+	#[test]
+	fn nested_calls_are_variants_of_variants() {
+		let sudo = json!({ "TemplateModule": { "do_something": { "something": 7 } } });
+		assert_eq!(
+			encode_call("Sudo", "sudo", &[sudo]),
+			Ok(SudoCall::sudo { call: Box::new(do_something(7)) }.into())
+		);
+	}
+
+	#[test]
+	fn invalid_calls_and_arguments_are_rejected() {
+		assert!(encode_call("Nope", "do_something", &[json!(1)]).is_err());
+		assert!(encode_call("TemplateModule", "nope", &[json!(1)]).is_err());
+		assert!(encode_call("TemplateModule", "do_something", &[]).is_err());
+		assert!(encode_call("TemplateModule", "do_something", &[json!("4294967296")]).is_err());
+		assert!(encode_call("TemplateModule", "do_something", &[json!(-1)]).is_err());
+		assert!(encode_call("Balances", "transfer", &[json!("not an address"), json!(1)]).is_err());
+	}
+}
@@ -0,0 +1,84 @@
+//! End-to-end tests of the node, through [`TestNode`].
+
+use jsonrpsee::{core::client::ClientT, rpc_params};
+use node_template::{service::Sealing, test_node::TestNode};
+use node_template_runtime::{BalancesCall, Call, EVM_CHAIN_ID, UNIT};
+use sc_client_api::BlockBackend;
+use sp_core::{blake2_256, crypto::Ss58Codec, Encode, H160, U256};
+use sp_keyring::Sr25519Keyring::{self, Alice, Bob};
+use sp_runtime::generic::BlockId;
+
+fn transfer_to_bob() -> Call {
+	BalancesCall::transfer { dest: Bob.to_account_id().into(), value: UNIT }.into()
+}
+
+async fn next_nonce(node: &TestNode, account: Sr25519Keyring) -> u32 {
+	node.rpc()
+		.request("system_accountNextIndex", rpc_params![account.to_account_id().to_ss58check()])
+		.await
+		.unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn instant_sealing_includes_transactions() {
+	let node = TestNode::builder().sealing(Sealing::Instant).start().await.unwrap();
+
+	let mut submitted = node.submit_signed(Alice, transfer_to_bob()).await.unwrap();
+	let block = submitted.in_block().await.unwrap();
+
+	let body = node.client().block_body(&BlockId::hash(block)).unwrap().unwrap();
+	let hashes: Vec<_> = body.iter().map(|extrinsic| blake2_256(&extrinsic.encode())).collect();
+	assert!(hashes.contains(&submitted.hash().0));
+	assert_eq!(next_nonce(&node, Alice).await, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn transactions_are_signed_with_the_nonces_after_pending_ones() {
+	let node = TestNode::builder().sealing(Sealing::Manual).start().await.unwrap();
+
+	let mut first = node.submit_signed(Alice, transfer_to_bob()).await.unwrap();
+	let mut second = node.submit_signed(Alice, transfer_to_bob()).await.unwrap();
+	let block = node.seal(false).await.unwrap();
+
+	assert_eq!(first.in_block().await.unwrap(), block);
+	assert_eq!(second.in_block().await.unwrap(), block);
+	assert_eq!(next_nonce(&node, Alice).await, 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn manual_sealing_finalizes_on_request() {
+	let node = TestNode::builder().sealing(Sealing::Manual).start().await.unwrap();
+
+	let mut submitted = node.submit_signed(Alice, transfer_to_bob()).await.unwrap();
+	let block = node.seal(true).await.unwrap();
+
+	assert_eq!(submitted.finalized().await.unwrap(), block);
+	assert_eq!(node.client().chain_info().finalized_hash, block);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn aura_and_grandpa_finalize_transactions() {
+	let node = TestNode::builder().start().await.unwrap();
+
+	let mut submitted = node.submit_signed(Alice, transfer_to_bob()).await.unwrap();
+	let included = submitted.in_block().await.unwrap();
+
+	assert_eq!(submitted.finalized().await.unwrap(), included);
+	assert!(node.seal(false).await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn the_ethereum_rpc_signs_for_dev_accounts() {
+	let node = TestNode::builder()
+		.sealing(Sealing::Instant)
+		.dev_accounts(2)
+		.start()
+		.await
+		.unwrap();
+
+	let chain_id: U256 = node.rpc().request("eth_chainId", rpc_params![]).await.unwrap();
+	let accounts: Vec<H160> = node.rpc().request("eth_accounts", rpc_params![]).await.unwrap();
+
+	assert_eq!(chain_id, EVM_CHAIN_ID.into());
+	assert_eq!(accounts.len(), 2);
+}
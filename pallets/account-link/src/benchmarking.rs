@@ -0,0 +1,43 @@
+//! Benchmarking setup for pallet-account-link
+
+use super::*;
+
+#[allow(unused)]
+use crate::Pallet as AccountLink;
+use frame_benchmarking::{benchmarks, whitelisted_caller};
+use frame_support::traits::Currency;
+use sp_core::crypto::KeyTypeId;
+
+const KEY_TYPE: KeyTypeId = KeyTypeId(*b"link");
+
+benchmarks! {
+	// The address held a balance and sent transactions before, so both are taken over.
+	link {
+		let caller: T::AccountId = whitelisted_caller();
+		let public = sp_io::crypto::ecdsa_generate(KEY_TYPE, None);
+		let message = Pallet::<T>::link_message(&caller);
+		let hash = Pallet::<T>::eip191_hash(&message);
+		let signature = sp_io::crypto::ecdsa_sign_prehashed(KEY_TYPE, &public, &hash).unwrap();
+		let address = Pallet::<T>::recover_signer(&signature, &message).unwrap();
+		let previous = T::FallbackMapping::into_account_id(address);
+		T::Currency::make_free_balance_be(&previous, T::Currency::minimum_balance() * 10u32.into());
+		<frame_system::Pallet<T>>::inc_account_nonce(&previous);
+	}: _(RawOrigin::Signed(caller.clone()), address, signature)
+	verify {
+		assert_eq!(Pallet::<T>::evm_address(&caller), Some(address));
+		assert_eq!(<frame_system::Pallet<T>>::account_nonce(&caller), 1u32.into());
+	}
+
+	unlink {
+		let caller: T::AccountId = whitelisted_caller();
+		let address = H160::repeat_byte(1);
+		<EvmAddresses<T>>::insert(&caller, address);
+		<Accounts<T>>::insert(address, &caller);
+		<frame_system::Pallet<T>>::inc_account_nonce(&caller);
+	}: _(RawOrigin::Signed(caller.clone()))
+	verify {
+		assert_eq!(Pallet::<T>::evm_address(&caller), None);
+	}
+
+	impl_benchmark_test_suite!(AccountLink, crate::mock::new_test_ext(), crate::mock::Test);
+}
@@ -0,0 +1,293 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// A pallet that links Substrate accounts to EVM addresses.
+///
+/// An account links an address by submitting an EIP-191 (`personal_sign`) signature of
+/// [`Pallet::link_message`] made with the address' key. The runtime uses
+/// [`LinkedAddressMapping`] as `pallet_evm::Config::AddressMapping`, so from then on the EVM
+/// address and the Substrate account share one balance and one nonce. Whatever the address held
+/// before is moved over when linking, and unlinking hands the address back to
+/// `Config::FallbackMapping` while the balance stays with the Substrate account. Linking fails
+/// while part of the address' balance is locked or reserved, since the lock or reserve belongs to
+/// the account it was made on.
+///
+/// Ethereum transactions are never mortal, so whichever account takes over the address' nonce,
+/// on linking or unlinking, has its nonce raised to at least the one the address had until then.
+/// Transactions the address signed before cannot be replayed.
+///
+/// [`EnsureAddressLinked`] lets accounts act for their addresses in `EVM::call`, `EVM::create`
+/// and `EVM::withdraw`.
+pub use pallet::*;
+
+use frame_system::RawOrigin;
+use pallet_evm::{AddressMapping, EnsureAddressOrigin};
+use sp_core::H160;
+use sp_std::marker::PhantomData;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+
+pub mod weights;
+pub use weights::WeightInfo;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use crate::WeightInfo;
+	use codec::Encode;
+	use frame_support::{
+		pallet_prelude::*,
+		traits::{Currency, ExistenceRequirement, ReservableCurrency, WithdrawReasons},
+	};
+	use frame_system::pallet_prelude::*;
+	use pallet_evm::AddressMapping;
+	use sp_core::{ecdsa, H160};
+	use sp_io::hashing::keccak_256;
+	use sp_runtime::traits::Zero;
+	use sp_std::prelude::*;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+		/// The currency whose balance is moved to the account when linking.
+		type Currency: ReservableCurrency<Self::AccountId>;
+		/// How addresses that are not linked are mapped to accounts.
+		type FallbackMapping: AddressMapping<Self::AccountId>;
+		/// Weight information for extrinsics in this pallet.
+		type WeightInfo: WeightInfo;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// The EVM address linked to an account.
+	#[pallet::storage]
+	#[pallet::getter(fn evm_address)]
+	pub type EvmAddresses<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, H160>;
+
+	/// The account linked to an EVM address.
+	#[pallet::storage]
+	#[pallet::getter(fn account)]
+	pub type Accounts<T: Config> = StorageMap<_, Blake2_128Concat, H160, T::AccountId>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// An account linked an EVM address. [account, address]
+		Linked(T::AccountId, H160),
+		/// An account unlinked its EVM address. [account, address]
+		Unlinked(T::AccountId, H160),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The account is already linked to an EVM address.
+		AccountAlreadyLinked,
+		/// The EVM address is already linked to an account.
+		AddressAlreadyLinked,
+		/// The signature was not made by the EVM address for this account.
+		InvalidSignature,
+		/// The account is not linked to an EVM address.
+		NotLinked,
+		/// Part of the address' balance is locked, so it cannot be moved to the account.
+		AddressBalanceLocked,
+		/// Part of the address' balance is reserved, so it cannot be moved to the account.
+		AddressBalanceReserved,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Link the sender to `address`, proven by an EIP-191 signature of
+		/// [`Pallet::link_message`] for the sender.
+		///
+		/// The balance `address` held until now is moved to the sender, whose nonce is raised to
+		/// at least the one of `address`. Fails if any of that balance is locked or reserved.
+		#[pallet::weight(T::WeightInfo::link())]
+		pub fn link(
+			origin: OriginFor<T>,
+			address: H160,
+			signature: ecdsa::Signature,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(!<EvmAddresses<T>>::contains_key(&who), Error::<T>::AccountAlreadyLinked);
+			ensure!(!<Accounts<T>>::contains_key(address), Error::<T>::AddressAlreadyLinked);
+			ensure!(
+				Self::recover_signer(&signature, &Self::link_message(&who)) == Some(address),
+				Error::<T>::InvalidSignature
+			);
+
+			let previous = T::FallbackMapping::into_account_id(address);
+			let nonce = <frame_system::Pallet<T>>::account_nonce(&previous);
+			if previous != who {
+				ensure!(
+					T::Currency::reserved_balance(&previous).is_zero(),
+					Error::<T>::AddressBalanceReserved
+				);
+				let balance = T::Currency::free_balance(&previous);
+				T::Currency::ensure_can_withdraw(
+					&previous,
+					balance,
+					WithdrawReasons::TRANSFER,
+					Zero::zero(),
+				)
+				.map_err(|_| Error::<T>::AddressBalanceLocked)?;
+				if !balance.is_zero() {
+					T::Currency::transfer(
+						&previous,
+						&who,
+						balance,
+						ExistenceRequirement::AllowDeath,
+					)?;
+				}
+			}
+			Self::raise_nonce(&who, nonce);
+
+			<EvmAddresses<T>>::insert(&who, address);
+			<Accounts<T>>::insert(address, &who);
+			Self::deposit_event(Event::Linked(who, address));
+			Ok(())
+		}
+
+		/// Unlink the sender from its EVM address.
+		///
+		/// The nonce of the account `Config::FallbackMapping` maps the address to is raised to at
+		/// least the one of the sender, creating the account if necessary.
+		#[pallet::weight(T::WeightInfo::unlink())]
+		pub fn unlink(origin: OriginFor<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let address = <EvmAddresses<T>>::take(&who).ok_or(Error::<T>::NotLinked)?;
+
+			let fallback = T::FallbackMapping::into_account_id(address);
+			Self::raise_nonce(&fallback, <frame_system::Pallet<T>>::account_nonce(&who));
+			<Accounts<T>>::remove(address);
+			Self::deposit_event(Event::Unlinked(who, address));
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// Raise the nonce of `who` to `nonce` if it is lower.
+		///
+		/// If `who` does not exist, it is created with a provider reference that is never removed:
+		/// the account keeps the nonce however often its balance is drained, so transactions the
+		/// address signed before cannot be replayed.
+		fn raise_nonce(who: &T::AccountId, nonce: T::Index) {
+			if <frame_system::Pallet<T>>::account_nonce(who) >= nonce {
+				return
+			}
+			if !<frame_system::Pallet<T>>::account_exists(who) {
+				let _ = <frame_system::Pallet<T>>::inc_providers(who);
+			}
+			frame_system::Account::<T>::mutate(who, |account| account.nonce = nonce);
+		}
+
+		/// The message an EVM address signs to link itself to `who`.
+		///
+		/// It names the account and the genesis hash, so a signature cannot be replayed for
+		/// another account or on another chain.
+		pub fn link_message(who: &T::AccountId) -> Vec<u8> {
+			let genesis = <frame_system::Pallet<T>>::block_hash(T::BlockNumber::zero());
+			let mut message = b"Link to account 0x".to_vec();
+			push_hex(&mut message, &who.encode());
+			message.extend_from_slice(b" on chain 0x");
+			push_hex(&mut message, genesis.as_ref());
+			message
+		}
+
+		/// The hash an EVM wallet signs for `personal_sign(message)`, as defined by EIP-191.
+		pub fn eip191_hash(message: &[u8]) -> [u8; 32] {
+			let mut prefixed = b"\x19Ethereum Signed Message:\n".to_vec();
+			push_decimal(&mut prefixed, message.len());
+			prefixed.extend_from_slice(message);
+			keccak_256(&prefixed)
+		}
+
+		/// Whether `who` may act for `address`: the account linked to `address`, or for
+		/// addresses nobody linked, the account whose first 20 bytes are `address`.
+		pub fn controls(who: &T::AccountId, address: &H160) -> bool
+		where
+			T::AccountId: AsRef<[u8]>,
+		{
+			match <Accounts<T>>::get(address) {
+				Some(owner) => owner == *who,
+				None => crate::truncated_address(who.as_ref()) == Some(*address),
+			}
+		}
+
+		/// The EVM address that signed `message` with `personal_sign`.
+		pub fn recover_signer(signature: &ecdsa::Signature, message: &[u8]) -> Option<H160> {
+			let public =
+				sp_io::crypto::secp256k1_ecdsa_recover(&signature.0, &Self::eip191_hash(message))
+					.ok()?;
+			Some(H160::from_slice(&keccak_256(&public)[12..]))
+		}
+	}
+
+	fn push_hex(out: &mut Vec<u8>, bytes: &[u8]) {
+		const DIGITS: &[u8; 16] = b"0123456789abcdef";
+		for byte in bytes {
+			out.push(DIGITS[(byte >> 4) as usize]);
+			out.push(DIGITS[(byte & 0xf) as usize]);
+		}
+	}
+
+	fn push_decimal(out: &mut Vec<u8>, mut n: usize) {
+		let start = out.len();
+		loop {
+			out.push(b'0' + (n % 10) as u8);
+			n /= 10;
+			if n == 0 {
+				break
+			}
+		}
+		out[start..].reverse();
+	}
+}
+
+/// The EVM address an account controls while nobody linked it: the account's first 20 bytes, or
+/// `None` if it is shorter than that.
+pub fn truncated_address(account: &[u8]) -> Option<H160> {
+	account.get(..20).map(H160::from_slice)
+}
+
+/// Maps linked EVM addresses to their account and all others through `Config::FallbackMapping`.
+pub struct LinkedAddressMapping<T>(PhantomData<T>);
+
+impl<T: Config> AddressMapping<T::AccountId> for LinkedAddressMapping<T> {
+	fn into_account_id(address: H160) -> T::AccountId {
+		<Accounts<T>>::get(address).unwrap_or_else(|| T::FallbackMapping::into_account_id(address))
+	}
+}
+
+/// Accepts signed origins of accounts that control the address, see [`Pallet::controls`].
+///
+/// Meant for `pallet_evm::Config::CallOrigin` and `WithdrawOrigin`: a linked account spends and
+/// withdraws its own balance, while an unlinked account keeps the `EnsureAddressTruncated`
+/// behaviour for the address it truncates to.
+pub struct EnsureAddressLinked<T>(PhantomData<T>);
+
+impl<T, OuterOrigin> EnsureAddressOrigin<OuterOrigin> for EnsureAddressLinked<T>
+where
+	T: Config,
+	T::AccountId: AsRef<[u8]>,
+	OuterOrigin: Into<Result<RawOrigin<T::AccountId>, OuterOrigin>> + From<RawOrigin<T::AccountId>>,
+{
+	type Success = T::AccountId;
+
+	fn try_address_origin(
+		address: &H160,
+		origin: OuterOrigin,
+	) -> Result<T::AccountId, OuterOrigin> {
+		origin.into().and_then(|o| match o {
+			RawOrigin::Signed(who) if Pallet::<T>::controls(&who, address) => Ok(who),
+			r => Err(OuterOrigin::from(r)),
+		})
+	}
+}
@@ -0,0 +1,226 @@
+use crate::{mock::*, EnsureAddressLinked, Error, Event as LinkEvent, LinkedAddressMapping};
+use frame_support::{
+	assert_noop, assert_ok,
+	traits::{Currency, LockableCurrency, ReservableCurrency, WithdrawReasons},
+};
+use hex_literal::hex;
+use pallet_evm::{AddressMapping, EnsureAddressOrigin, HashedAddressMapping};
+use sp_core::{ecdsa, Pair, H160};
+use sp_runtime::{traits::BlakeTwo256, AccountId32, DispatchError};
+
+/// A well-known development key and its EVM address.
+fn alith() -> (ecdsa::Pair, H160) {
+	let pair = ecdsa::Pair::from_seed(&hex!(
+		"5fb92d6e98884f76de468fa3f6278f8807c48bebc13595d45af5bdc4da702133"
+	));
+	(pair, H160(hex!("f24ff3a9cf04c71dbc94d0b566f7a27b94566cac")))
+}
+
+fn baltathar() -> (ecdsa::Pair, H160) {
+	let pair = ecdsa::Pair::from_seed(&hex!(
+		"8075991ce870b93a8870eca0c0f91913d12f47948ca0fd25b49c6fa7cdbeee8b"
+	));
+	(pair, H160(hex!("3cd0a705a2dc65e5b1e1205896baa2be8a07c6e0")))
+}
+
+/// What an EVM wallet returns for `personal_sign` of the link message for `who`.
+fn sign_link(pair: &ecdsa::Pair, who: &AccountId32) -> ecdsa::Signature {
+	pair.sign_prehashed(&AccountLink::eip191_hash(&AccountLink::link_message(who)))
+}
+
+fn last_event() -> Event {
+	System::events().pop().expect("an event was deposited").event
+}
+
+fn fallback(address: H160) -> AccountId32 {
+	HashedAddressMapping::<BlakeTwo256>::into_account_id(address)
+}
+
+#[test]
+fn eip191_hash_matches_personal_sign() {
+	assert_eq!(
+		AccountLink::eip191_hash(b"hello world"),
+		hex!("d9eba16ed0ecae432b71fe008c98cc872bb4cc214d3220a36f365326cf807d68")
+	);
+}
+
+#[test]
+fn linking_maps_the_address_both_ways() {
+	new_test_ext().execute_with(|| {
+		let (pair, address) = alith();
+		assert_eq!(LinkedAddressMapping::<Test>::into_account_id(address), fallback(address));
+
+		assert_ok!(AccountLink::link(Origin::signed(ALICE), address, sign_link(&pair, &ALICE)));
+		assert_eq!(last_event(), LinkEvent::<Test>::Linked(ALICE, address).into());
+		assert_eq!(AccountLink::evm_address(ALICE), Some(address));
+		assert_eq!(AccountLink::account(address), Some(ALICE));
+		assert_eq!(LinkedAddressMapping::<Test>::into_account_id(address), ALICE);
+	});
+}
+
+#[test]
+fn signatures_must_come_from_the_address_for_the_sender() {
+	new_test_ext().execute_with(|| {
+		let (alith, alith_address) = alith();
+		let (baltathar, _) = baltathar();
+
+		// Signed by another key.
+		assert_noop!(
+			AccountLink::link(Origin::signed(ALICE), alith_address, sign_link(&baltathar, &ALICE)),
+			Error::<Test>::InvalidSignature
+		);
+		// Signed for another account.
+		assert_noop!(
+			AccountLink::link(Origin::signed(ALICE), alith_address, sign_link(&alith, &BOB)),
+			Error::<Test>::InvalidSignature
+		);
+		// Not a valid signature at all.
+		assert_noop!(
+			AccountLink::link(
+				Origin::signed(ALICE),
+				alith_address,
+				ecdsa::Signature::from_raw([0; 65])
+			),
+			Error::<Test>::InvalidSignature
+		);
+		assert_noop!(
+			AccountLink::link(Origin::none(), alith_address, sign_link(&alith, &ALICE)),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn accounts_and_addresses_are_linked_at_most_once() {
+	new_test_ext().execute_with(|| {
+		let (alith, alith_address) = alith();
+		let (baltathar, baltathar_address) = baltathar();
+		assert_ok!(AccountLink::link(
+			Origin::signed(ALICE),
+			alith_address,
+			sign_link(&alith, &ALICE)
+		));
+
+		assert_noop!(
+			AccountLink::link(
+				Origin::signed(ALICE),
+				baltathar_address,
+				sign_link(&baltathar, &ALICE)
+			),
+			Error::<Test>::AccountAlreadyLinked
+		);
+		assert_noop!(
+			AccountLink::link(Origin::signed(BOB), alith_address, sign_link(&alith, &BOB)),
+			Error::<Test>::AddressAlreadyLinked
+		);
+	});
+}
+
+#[test]
+fn linking_moves_the_address_balance() {
+	new_test_ext().execute_with(|| {
+		let (pair, address) = alith();
+		let _ = Balances::deposit_creating(&fallback(address), 500);
+
+		assert_ok!(AccountLink::link(Origin::signed(ALICE), address, sign_link(&pair, &ALICE)));
+		assert_eq!(Balances::free_balance(&ALICE), 1_500);
+		assert_eq!(Balances::free_balance(&fallback(address)), 0);
+	});
+}
+
+#[test]
+fn locked_or_reserved_address_balances_are_not_moved() {
+	new_test_ext().execute_with(|| {
+		let (pair, address) = alith();
+		let link = || AccountLink::link(Origin::signed(ALICE), address, sign_link(&pair, &ALICE));
+		let _ = Balances::deposit_creating(&fallback(address), 500);
+
+		Balances::set_lock(*b"testlock", &fallback(address), 100, WithdrawReasons::all());
+		assert_noop!(link(), Error::<Test>::AddressBalanceLocked);
+		Balances::remove_lock(*b"testlock", &fallback(address));
+
+		assert_ok!(Balances::reserve(&fallback(address), 100));
+		assert_noop!(link(), Error::<Test>::AddressBalanceReserved);
+		Balances::unreserve(&fallback(address), 100);
+
+		assert_ok!(link());
+		assert_eq!(Balances::free_balance(&ALICE), 1_500);
+	});
+}
+
+#[test]
+fn unlinking_restores_the_fallback_mapping() {
+	new_test_ext().execute_with(|| {
+		let (pair, address) = alith();
+		assert_noop!(AccountLink::unlink(Origin::signed(ALICE)), Error::<Test>::NotLinked);
+
+		assert_ok!(AccountLink::link(Origin::signed(ALICE), address, sign_link(&pair, &ALICE)));
+		assert_ok!(AccountLink::unlink(Origin::signed(ALICE)));
+		assert_eq!(last_event(), LinkEvent::<Test>::Unlinked(ALICE, address).into());
+		assert_eq!(AccountLink::evm_address(ALICE), None);
+		assert_eq!(AccountLink::account(address), None);
+		assert_eq!(LinkedAddressMapping::<Test>::into_account_id(address), fallback(address));
+		assert_eq!(Balances::free_balance(&ALICE), 1_000);
+
+		// The address can be linked again, to another account.
+		assert_ok!(AccountLink::link(Origin::signed(BOB), address, sign_link(&pair, &BOB)));
+		assert_eq!(LinkedAddressMapping::<Test>::into_account_id(address), BOB);
+	});
+}
+
+#[test]
+fn the_nonce_is_carried_over_when_linking_and_unlinking() {
+	new_test_ext().execute_with(|| {
+		let (pair, address) = alith();
+		// The address sent three transactions before it was linked.
+		let _ = Balances::deposit_creating(&fallback(address), 500);
+		(0..3).for_each(|_| System::inc_account_nonce(&fallback(address)));
+
+		assert_ok!(AccountLink::link(Origin::signed(ALICE), address, sign_link(&pair, &ALICE)));
+		assert_eq!(System::account_nonce(&ALICE), 3);
+
+		// Then two more through Alice, which also reaped the account it was mapped to.
+		assert!(!System::account_exists(&fallback(address)));
+		(0..2).for_each(|_| System::inc_account_nonce(&ALICE));
+		assert_ok!(AccountLink::unlink(Origin::signed(ALICE)));
+		assert_eq!(System::account_nonce(&fallback(address)), 5);
+		assert_eq!(System::providers(&fallback(address)), 1);
+
+		// The account is kept, with its nonce, when its balance comes and goes.
+		let _ = Balances::deposit_creating(&fallback(address), 500);
+		let _ = Balances::slash(&fallback(address), 500);
+		assert!(System::account_exists(&fallback(address)));
+		assert_eq!(System::account_nonce(&fallback(address)), 5);
+
+		// Higher nonces are kept.
+		(0..7).for_each(|_| System::inc_account_nonce(&BOB));
+		assert_ok!(AccountLink::link(Origin::signed(BOB), address, sign_link(&pair, &BOB)));
+		assert_eq!(System::account_nonce(&BOB), 7);
+	});
+}
+
+#[test]
+fn linked_accounts_act_for_their_address() {
+	new_test_ext().execute_with(|| {
+		let (pair, address) = alith();
+		let ensure = |who: AccountId32, address| {
+			EnsureAddressLinked::<Test>::try_address_origin(&address, Origin::signed(who)).is_ok()
+		};
+		let truncated =
+			|who: &AccountId32| crate::truncated_address(AsRef::<[u8]>::as_ref(who)).unwrap();
+
+		// Unlinked addresses are controlled by the account they are a truncation of.
+		assert!(!ensure(ALICE, address));
+		assert!(ensure(ALICE, truncated(&ALICE)));
+		assert!(!ensure(BOB, truncated(&ALICE)));
+		assert!(EnsureAddressLinked::<Test>::try_address_origin(&address, Origin::root()).is_err());
+
+		assert_ok!(AccountLink::link(Origin::signed(ALICE), address, sign_link(&pair, &ALICE)));
+		assert!(ensure(ALICE, address));
+		assert!(!ensure(BOB, address));
+		assert_eq!(
+			EnsureAddressLinked::<Test>::try_address_origin(&address, Origin::signed(ALICE)).ok(),
+			Some(ALICE)
+		);
+	});
+}
@@ -0,0 +1,61 @@
+//! Weights for pallet_account_link.
+//!
+//! The values below account for the storage accesses and the secp256k1 recovery of `link`.
+//! Replace them with measured ones on the reference hardware with:
+//!
+//! ```sh
+//! ./target/release/node-template benchmark pallet --chain dev --execution wasm \
+//! 	--wasm-execution compiled --pallet pallet_account_link --extrinsic '*' --steps 50 --repeat 20 \
+//! 	--output pallets/account-link/src/weights.rs
+//! ```
+
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{
+	traits::Get,
+	weights::{constants::RocksDbWeight, Weight},
+};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for pallet_account_link.
+pub trait WeightInfo {
+	fn link() -> Weight;
+	fn unlink() -> Weight;
+}
+
+/// Weights for pallet_account_link using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	// Storage: AccountLink EvmAddresses (r:1 w:1)
+	// Storage: AccountLink Accounts (r:1 w:1)
+	// Storage: System BlockHash (r:1 w:0)
+	// Storage: System Account (r:2 w:2)
+	fn link() -> Weight {
+		(60_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(5 as Weight))
+			.saturating_add(T::DbWeight::get().writes(4 as Weight))
+	}
+	// Storage: AccountLink EvmAddresses (r:1 w:1)
+	// Storage: AccountLink Accounts (r:0 w:1)
+	// Storage: System Account (r:2 w:1)
+	fn unlink() -> Weight {
+		(15_000_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(3 as Weight))
+			.saturating_add(T::DbWeight::get().writes(3 as Weight))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn link() -> Weight {
+		(60_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(5 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(4 as Weight))
+	}
+	fn unlink() -> Weight {
+		(15_000_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(3 as Weight))
+	}
+}
@@ -0,0 +1,257 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// A pallet that restricts which accounts may deploy EVM contracts.
+///
+/// The allowlist is enforced by [`AllowlistRunner`], which the runtime uses as
+/// `pallet_evm::Config::Runner`. Every entry point that deploys a contract goes through the
+/// runner, so `EVM::create`, `EVM::create2` and Ethereum create transactions are all covered,
+/// while calls stay open to everyone. Ethereum create transactions bypass the signed extensions,
+/// so the runtime also rejects those of disallowed sources with [`Pallet::validate_transaction`]
+/// before they reach the pool, rather than letting them fail at dispatch without paying a fee.
+///
+/// Only top-level deployments are checked: a contract deployed by an allowed account may itself
+/// deploy contracts with `CREATE` and `CREATE2`, as factories do.
+pub use pallet::*;
+
+use pallet_ethereum::{Transaction, TransactionAction};
+use pallet_evm::{CallInfo, CreateInfo, EvmConfig};
+use sp_core::{H160, H256, U256};
+use sp_runtime::{
+	transaction_validity::{InvalidTransaction, TransactionValidityError},
+	DispatchError,
+};
+use sp_std::{marker::PhantomData, prelude::*};
+
+#[frame_support::pallet]
+pub mod pallet {
+	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
+	use sp_core::H160;
+	use sp_std::prelude::*;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config + pallet_evm::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+		/// The origin allowed to manage the allowlist.
+		type AllowlistOrigin: EnsureOrigin<Self::Origin>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// Whether deployments are restricted to the accounts in [`Deployers`].
+	#[pallet::storage]
+	#[pallet::getter(fn enforced)]
+	pub type Enforced<T> = StorageValue<_, bool, ValueQuery>;
+
+	/// The accounts allowed to deploy contracts while the allowlist is enforced.
+	#[pallet::storage]
+	pub type Deployers<T> = StorageMap<_, Blake2_128Concat, H160, (), OptionQuery>;
+
+	#[pallet::genesis_config]
+	#[derive(Default)]
+	pub struct GenesisConfig {
+		pub enforced: bool,
+		pub deployers: Vec<H160>,
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig {
+		fn build(&self) {
+			<Enforced<T>>::put(self.enforced);
+			for deployer in &self.deployers {
+				<Deployers<T>>::insert(deployer, ());
+			}
+		}
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// An account may now deploy contracts. [deployer]
+		DeployerAdded(H160),
+		/// An account may no longer deploy contracts. [deployer]
+		DeployerRemoved(H160),
+		/// The allowlist was switched on or off. [enforced]
+		EnforcementSet(bool),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The account is already allowed to deploy contracts.
+		AlreadyAllowed,
+		/// The account is not in the allowlist.
+		NotAllowed,
+		/// The source of the deployment is not allowed to deploy contracts.
+		DeployerNotAllowed,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Allow `deployer` to deploy contracts.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn add_deployer(origin: OriginFor<T>, deployer: H160) -> DispatchResult {
+			T::AllowlistOrigin::ensure_origin(origin)?;
+			ensure!(!<Deployers<T>>::contains_key(deployer), Error::<T>::AlreadyAllowed);
+
+			<Deployers<T>>::insert(deployer, ());
+			Self::deposit_event(Event::DeployerAdded(deployer));
+			Ok(())
+		}
+
+		/// Stop allowing `deployer` to deploy contracts. Contracts it deployed are unaffected.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn remove_deployer(origin: OriginFor<T>, deployer: H160) -> DispatchResult {
+			T::AllowlistOrigin::ensure_origin(origin)?;
+			ensure!(<Deployers<T>>::contains_key(deployer), Error::<T>::NotAllowed);
+
+			<Deployers<T>>::remove(deployer);
+			Self::deposit_event(Event::DeployerRemoved(deployer));
+			Ok(())
+		}
+
+		/// Restrict deployments to the allowlist, or open them to everyone.
+		#[pallet::weight(10_000 + T::DbWeight::get().writes(1))]
+		pub fn set_enforced(origin: OriginFor<T>, enforced: bool) -> DispatchResult {
+			T::AllowlistOrigin::ensure_origin(origin)?;
+
+			<Enforced<T>>::put(enforced);
+			Self::deposit_event(Event::EnforcementSet(enforced));
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// Whether `source` may deploy contracts right now.
+		pub fn can_deploy(source: &H160) -> bool {
+			!Self::enforced() || <Deployers<T>>::contains_key(source)
+		}
+
+		/// Fail with [`Error::DeployerNotAllowed`] if `source` may not deploy contracts.
+		pub fn ensure_can_deploy(source: &H160) -> Result<(), Error<T>> {
+			ensure!(Self::can_deploy(source), Error::<T>::DeployerNotAllowed);
+			Ok(())
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Reject `transaction` from `source` as an invalid call if it deploys a contract `source`
+	/// may not deploy.
+	pub fn validate_transaction(
+		source: &H160,
+		transaction: &Transaction,
+	) -> Result<(), TransactionValidityError> {
+		let action = match transaction {
+			Transaction::Legacy(t) => &t.action,
+			Transaction::EIP2930(t) => &t.action,
+			Transaction::EIP1559(t) => &t.action,
+		};
+		if matches!(action, TransactionAction::Create) && !Self::can_deploy(source) {
+			return Err(InvalidTransaction::Call.into())
+		}
+		Ok(())
+	}
+}
+
+/// A `pallet_evm::Runner` that checks the allowlist before handing deployments to `R`.
+///
+/// Calls are passed through unchecked.
+pub struct AllowlistRunner<T, R>(PhantomData<(T, R)>);
+
+impl<T, R> pallet_evm::Runner<T> for AllowlistRunner<T, R>
+where
+	T: Config,
+	R: pallet_evm::Runner<T>,
+{
+	type Error = DispatchError;
+
+	fn call(
+		source: H160,
+		target: H160,
+		input: Vec<u8>,
+		value: U256,
+		gas_limit: u64,
+		max_fee_per_gas: Option<U256>,
+		max_priority_fee_per_gas: Option<U256>,
+		nonce: Option<U256>,
+		access_list: Vec<(H160, Vec<H256>)>,
+		is_transactional: bool,
+		config: &EvmConfig,
+	) -> Result<CallInfo, Self::Error> {
+		R::call(
+			source,
+			target,
+			input,
+			value,
+			gas_limit,
+			max_fee_per_gas,
+			max_priority_fee_per_gas,
+			nonce,
+			access_list,
+			is_transactional,
+			config,
+		)
+		.map_err(Into::into)
+	}
+
+	fn create(
+		source: H160,
+		init: Vec<u8>,
+		value: U256,
+		gas_limit: u64,
+		max_fee_per_gas: Option<U256>,
+		max_priority_fee_per_gas: Option<U256>,
+		nonce: Option<U256>,
+		access_list: Vec<(H160, Vec<H256>)>,
+		is_transactional: bool,
+		config: &EvmConfig,
+	) -> Result<CreateInfo, Self::Error> {
+		Pallet::<T>::ensure_can_deploy(&source)?;
+		R::create(
+			source,
+			init,
+			value,
+			gas_limit,
+			max_fee_per_gas,
+			max_priority_fee_per_gas,
+			nonce,
+			access_list,
+			is_transactional,
+			config,
+		)
+		.map_err(Into::into)
+	}
+
+	fn create2(
+		source: H160,
+		init: Vec<u8>,
+		salt: H256,
+		value: U256,
+		gas_limit: u64,
+		max_fee_per_gas: Option<U256>,
+		max_priority_fee_per_gas: Option<U256>,
+		nonce: Option<U256>,
+		access_list: Vec<(H160, Vec<H256>)>,
+		is_transactional: bool,
+		config: &EvmConfig,
+	) -> Result<CreateInfo, Self::Error> {
+		Pallet::<T>::ensure_can_deploy(&source)?;
+		R::create2(
+			source,
+			init,
+			salt,
+			value,
+			gas_limit,
+			max_fee_per_gas,
+			max_priority_fee_per_gas,
+			nonce,
+			access_list,
+			is_transactional,
+			config,
+		)
+		.map_err(Into::into)
+	}
+}
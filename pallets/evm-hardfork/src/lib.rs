@@ -0,0 +1,112 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// A pallet that selects the hardfork rules (opcode set and gas schedule) the EVM runs with.
+///
+/// The runtime overrides `pallet_evm::Config::config` with [`Pallet::evm_config`], so the rules
+/// can be set at genesis and changed later through governance without a runtime upgrade.
+///
+/// Only the hardforks known to the `evm` crate pallet-evm is built against are available. That
+/// version stops at London: Shanghai (`PUSH0`) and Cancun (transient storage, `MCOPY`,
+/// `BLOBHASH`) need an `evm` upgrade, which comes with the next Frontier release. Contracts have
+/// to be compiled with `evmVersion` set to `london` or older until then.
+pub use evm::Config as EvmConfig;
+pub use pallet::*;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::EvmConfig;
+	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
+	#[cfg(feature = "std")]
+	use serde::{Deserialize, Serialize};
+
+	static ISTANBUL_CONFIG: EvmConfig = EvmConfig::istanbul();
+	static BERLIN_CONFIG: EvmConfig = EvmConfig::berlin();
+	static LONDON_CONFIG: EvmConfig = EvmConfig::london();
+
+	/// The hardforks the EVM can follow.
+	#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, MaxEncodedLen, RuntimeDebug, TypeInfo)]
+	#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+	pub enum Hardfork {
+		/// Adds `CHAINID` and `SELFBALANCE` and reprices storage access (EIP-1884, EIP-2200).
+		Istanbul,
+		/// Adds access lists and charges more for cold account and storage access (EIP-2929).
+		Berlin,
+		/// Adds `BASEFEE` and removes the `SELFDESTRUCT` refund (EIP-3198, EIP-3529).
+		London,
+	}
+
+	impl Default for Hardfork {
+		fn default() -> Self {
+			Hardfork::London
+		}
+	}
+
+	impl Hardfork {
+		/// The `evm` rules of this hardfork.
+		pub fn config(&self) -> &'static EvmConfig {
+			match self {
+				Hardfork::Istanbul => &ISTANBUL_CONFIG,
+				Hardfork::Berlin => &BERLIN_CONFIG,
+				Hardfork::London => &LONDON_CONFIG,
+			}
+		}
+	}
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+		/// The origin allowed to change the hardfork.
+		type HardforkOrigin: EnsureOrigin<Self::Origin>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// The hardfork whose rules the EVM currently follows.
+	#[pallet::storage]
+	#[pallet::getter(fn hardfork)]
+	pub type CurrentHardfork<T> = StorageValue<_, Hardfork, ValueQuery>;
+
+	#[pallet::genesis_config]
+	#[derive(Default)]
+	pub struct GenesisConfig {
+		pub hardfork: Hardfork,
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig {
+		fn build(&self) {
+			<CurrentHardfork<T>>::put(self.hardfork);
+		}
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// The EVM now follows the rules of the given hardfork. [hardfork]
+		HardforkSet(Hardfork),
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Switch the EVM to the rules of `hardfork`, starting with the next transaction.
+		#[pallet::weight(10_000 + T::DbWeight::get().writes(1))]
+		pub fn set_hardfork(origin: OriginFor<T>, hardfork: Hardfork) -> DispatchResult {
+			T::HardforkOrigin::ensure_origin(origin)?;
+
+			<CurrentHardfork<T>>::put(hardfork);
+			Self::deposit_event(Event::HardforkSet(hardfork));
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The rules to use as `pallet_evm::Config::config`.
+		pub fn evm_config() -> &'static EvmConfig {
+			Self::hardfork().config()
+		}
+	}
+}
@@ -0,0 +1,267 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// A pallet that removes the EVM code and storage of accounts that were reaped.
+///
+/// `pallet_evm` keys code and storage by address, outside of `frame_system`, so they outlive the
+/// account they belong to. Used as `frame_system::Config::OnKilledAccount`, this pallet queues
+/// killed accounts that still hold EVM state, and removes that state in `on_idle`, as far as the
+/// remaining weight of the block allows. Accounts that come back to life before their turn keep
+/// their state.
+///
+/// The address of an account is what `Config::AddressOf` tells, or else what [`RecordingRunner`]
+/// saw the EVM map to it. Accounts of contracts that other contracts deployed are only recorded
+/// once they are called at the top level.
+pub use pallet::*;
+
+use pallet_evm::{CallInfo, CreateInfo, EvmConfig, ExitReason};
+use sp_core::{H160, H256, U256};
+use sp_runtime::DispatchError;
+use sp_std::{marker::PhantomData, prelude::*};
+
+#[frame_support::pallet]
+pub mod pallet {
+	use frame_support::{pallet_prelude::*, sp_io::KillStorageResult, traits::OnKilledAccount};
+	use frame_system::pallet_prelude::*;
+	use pallet_evm::AddressMapping;
+	use sp_core::H160;
+	use sp_runtime::traits::Convert;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config + pallet_evm::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+		/// The EVM address of an account, if it is known.
+		type AddressOf: Convert<Self::AccountId, Option<H160>>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// The addresses of accounts that `Config::AddressOf` does not know, as seen by
+	/// [`crate::RecordingRunner`].
+	#[pallet::storage]
+	#[pallet::getter(fn address_of)]
+	pub type Addresses<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, H160>;
+
+	/// Addresses whose EVM state waits to be removed, with the account that was killed.
+	#[pallet::storage]
+	#[pallet::getter(fn pending)]
+	pub type Pending<T: Config> = StorageMap<_, Blake2_128Concat, H160, T::AccountId>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// The code and storage of a killed account were removed. [address]
+		Reaped(H160),
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_idle(_n: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+			Self::reap(remaining_weight)
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// Remove queued EVM state using at most `limit` weight, and return the weight used.
+		///
+		/// Storage of large contracts is removed over several calls, their code goes last.
+		pub fn reap(limit: Weight) -> Weight {
+			let db = T::DbWeight::get();
+			// Finding the next address and checking whether its account is back.
+			let lookup = db.reads(2);
+			// Removing the code and the queue entry.
+			let finish = db.writes(2);
+			let per_entry = db.writes(1).max(1);
+
+			let mut used = 0;
+			loop {
+				if used + lookup + finish > limit {
+					break
+				}
+				used += lookup;
+				let (address, who) = match <Pending<T>>::iter().next() {
+					Some(next) => next,
+					None => break,
+				};
+
+				if <frame_system::Pallet<T>>::account_exists(&who) {
+					used += db.reads_writes(1, 2);
+					<Pending<T>>::remove(address);
+					// Forgotten when the account was killed.
+					if T::AddressOf::convert(who.clone()).is_none() {
+						<Addresses<T>>::insert(&who, address);
+					}
+					continue
+				}
+
+				let budget = (limit - used - finish) / per_entry;
+				match <pallet_evm::AccountStorages<T>>::remove_prefix(
+					address,
+					Some(budget.try_into().unwrap_or(u32::MAX)),
+				) {
+					KillStorageResult::AllRemoved(removed) => {
+						used += db.writes(removed.into()) + finish;
+						<pallet_evm::AccountCodes<T>>::remove(address);
+						<Pending<T>>::remove(address);
+						Self::deposit_event(Event::Reaped(address));
+					},
+					KillStorageResult::SomeRemaining(removed) => {
+						used += db.writes(removed.into());
+						break
+					},
+				}
+			}
+			used
+		}
+
+		/// Remember the account `address` maps to if `address` holds EVM code or storage, unless
+		/// `Config::AddressOf` knows it already.
+		///
+		/// Other accounts leave nothing to reap, so every record belongs to a contract whose
+		/// deployment paid for its code.
+		pub fn note_address(address: H160) {
+			if !Self::has_evm_state(address) {
+				return
+			}
+			let who = T::AddressMapping::into_account_id(address);
+			if T::AddressOf::convert(who.clone()).is_none() && !<Addresses<T>>::contains_key(&who) {
+				<Addresses<T>>::insert(who, address);
+			}
+		}
+
+		fn has_evm_state(address: H160) -> bool {
+			<pallet_evm::AccountCodes<T>>::contains_key(address) ||
+				<pallet_evm::AccountStorages<T>>::iter_key_prefix(address).next().is_some()
+		}
+	}
+
+	impl<T: Config> OnKilledAccount<T::AccountId> for Pallet<T> {
+		fn on_killed_account(who: &T::AccountId) {
+			let recorded = <Addresses<T>>::take(who);
+			if let Some(address) = T::AddressOf::convert(who.clone()).or(recorded) {
+				// A recorded address may have been linked to another account since.
+				if T::AddressMapping::into_account_id(address) == *who &&
+					Self::has_evm_state(address)
+				{
+					<Pending<T>>::insert(address, who);
+				}
+			}
+		}
+	}
+}
+
+/// A `pallet_evm::Runner` that records the addresses `R` executes from and on, so that the EVM
+/// state of their accounts can be found when they are killed.
+///
+/// Only addresses with EVM code or storage are recorded, see [`Pallet::note_address`]. Checking
+/// costs up to four reads per address on top of the weight of the execution.
+pub struct RecordingRunner<T, R>(PhantomData<(T, R)>);
+
+impl<T, R> pallet_evm::Runner<T> for RecordingRunner<T, R>
+where
+	T: Config,
+	R: pallet_evm::Runner<T>,
+{
+	type Error = DispatchError;
+
+	fn call(
+		source: H160,
+		target: H160,
+		input: Vec<u8>,
+		value: U256,
+		gas_limit: u64,
+		max_fee_per_gas: Option<U256>,
+		max_priority_fee_per_gas: Option<U256>,
+		nonce: Option<U256>,
+		access_list: Vec<(H160, Vec<H256>)>,
+		is_transactional: bool,
+		config: &EvmConfig,
+	) -> Result<CallInfo, Self::Error> {
+		let info = R::call(
+			source,
+			target,
+			input,
+			value,
+			gas_limit,
+			max_fee_per_gas,
+			max_priority_fee_per_gas,
+			nonce,
+			access_list,
+			is_transactional,
+			config,
+		)
+		.map_err(Into::into)?;
+		Pallet::<T>::note_address(source);
+		Pallet::<T>::note_address(target);
+		Ok(info)
+	}
+
+	fn create(
+		source: H160,
+		init: Vec<u8>,
+		value: U256,
+		gas_limit: u64,
+		max_fee_per_gas: Option<U256>,
+		max_priority_fee_per_gas: Option<U256>,
+		nonce: Option<U256>,
+		access_list: Vec<(H160, Vec<H256>)>,
+		is_transactional: bool,
+		config: &EvmConfig,
+	) -> Result<CreateInfo, Self::Error> {
+		let info = R::create(
+			source,
+			init,
+			value,
+			gas_limit,
+			max_fee_per_gas,
+			max_priority_fee_per_gas,
+			nonce,
+			access_list,
+			is_transactional,
+			config,
+		)
+		.map_err(Into::into)?;
+		note_created::<T>(source, &info);
+		Ok(info)
+	}
+
+	fn create2(
+		source: H160,
+		init: Vec<u8>,
+		salt: H256,
+		value: U256,
+		gas_limit: u64,
+		max_fee_per_gas: Option<U256>,
+		max_priority_fee_per_gas: Option<U256>,
+		nonce: Option<U256>,
+		access_list: Vec<(H160, Vec<H256>)>,
+		is_transactional: bool,
+		config: &EvmConfig,
+	) -> Result<CreateInfo, Self::Error> {
+		let info = R::create2(
+			source,
+			init,
+			salt,
+			value,
+			gas_limit,
+			max_fee_per_gas,
+			max_priority_fee_per_gas,
+			nonce,
+			access_list,
+			is_transactional,
+			config,
+		)
+		.map_err(Into::into)?;
+		note_created::<T>(source, &info);
+		Ok(info)
+	}
+}
+
+fn note_created<T: Config>(source: H160, info: &CreateInfo) {
+	Pallet::<T>::note_address(source);
+	if matches!(info.exit_reason, ExitReason::Succeed(_)) {
+		Pallet::<T>::note_address(info.value);
+	}
+}
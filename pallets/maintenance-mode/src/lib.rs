@@ -0,0 +1,189 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// A pallet that lets a privileged origin halt user transactions during an incident, or pause
+/// individual pallets, without a runtime upgrade.
+///
+/// The pallet implements `Contains<Call>` and is meant to be used as (part of) the runtime's
+/// `frame_system::Config::BaseCallFilter`. Calls matched by `Config::WhitelistedCalls` are
+/// never filtered, so governance and the pallet itself stay reachable.
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use frame_support::{
+		pallet_prelude::*,
+		sp_std::vec::Vec,
+		traits::{CallMetadata, Contains, GetCallMetadata, PalletInfoAccess},
+	};
+	use frame_system::pallet_prelude::*;
+
+	/// Name of a pallet as it appears in `construct_runtime!`, e.g. `b"EVM"`.
+	pub type PalletNameOf<T> = BoundedVec<u8, <T as Config>::MaxPalletNameLen>;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+		/// The origin allowed to toggle maintenance mode and to pause pallets.
+		type MaintenanceOrigin: EnsureOrigin<Self::Origin>;
+		/// Calls that are never filtered, neither in maintenance mode nor by pausing their
+		/// pallet. This should at least cover governance, inherents and this pallet.
+		type WhitelistedCalls: Contains<Self::Call>;
+		/// The runtime's `Call`. Only the pallets it dispatches to can be paused.
+		type RuntimeCall: GetCallMetadata;
+		/// The maximum length of a pallet name that can be paused.
+		#[pallet::constant]
+		type MaxPalletNameLen: Get<u32>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// Whether the chain is in maintenance mode.
+	#[pallet::storage]
+	#[pallet::getter(fn maintenance_mode)]
+	pub type MaintenanceMode<T> = StorageValue<_, bool, ValueQuery>;
+
+	/// Pallets whose calls are currently filtered, keyed by name.
+	#[pallet::storage]
+	#[pallet::getter(fn paused_pallets)]
+	pub type PausedPallets<T> = StorageMap<_, Blake2_128Concat, PalletNameOf<T>, ()>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig {
+		/// Whether the chain starts in maintenance mode.
+		pub start_in_maintenance_mode: bool,
+	}
+
+	#[cfg(feature = "std")]
+	impl Default for GenesisConfig {
+		fn default() -> Self {
+			Self { start_in_maintenance_mode: false }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig {
+		fn build(&self) {
+			<MaintenanceMode<T>>::put(self.start_in_maintenance_mode);
+		}
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// All non-whitelisted calls are now filtered.
+		EnteredMaintenanceMode,
+		/// Maintenance mode was switched off.
+		NormalOperationResumed,
+		/// Calls to the given pallet are now filtered. [pallet]
+		PalletPaused(PalletNameOf<T>),
+		/// Calls to the given pallet are allowed again. [pallet]
+		PalletUnpaused(PalletNameOf<T>),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The chain is already in maintenance mode.
+		AlreadyInMaintenanceMode,
+		/// The chain is not in maintenance mode.
+		NotInMaintenanceMode,
+		/// The pallet is already paused.
+		AlreadyPaused,
+		/// The pallet is not paused.
+		NotPaused,
+		/// The pallet name is longer than `MaxPalletNameLen`.
+		PalletNameTooLong,
+		/// This pallet cannot pause itself.
+		CannotPauseSelf,
+		/// No pallet with calls has this name.
+		UnknownPallet,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Filter every call that is not whitelisted.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn enter_maintenance_mode(origin: OriginFor<T>) -> DispatchResult {
+			T::MaintenanceOrigin::ensure_origin(origin)?;
+			ensure!(!<MaintenanceMode<T>>::get(), Error::<T>::AlreadyInMaintenanceMode);
+
+			<MaintenanceMode<T>>::put(true);
+			Self::deposit_event(Event::EnteredMaintenanceMode);
+			Ok(())
+		}
+
+		/// Switch maintenance mode off. Individually paused pallets stay paused.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn resume_normal_operation(origin: OriginFor<T>) -> DispatchResult {
+			T::MaintenanceOrigin::ensure_origin(origin)?;
+			ensure!(<MaintenanceMode<T>>::get(), Error::<T>::NotInMaintenanceMode);
+
+			<MaintenanceMode<T>>::put(false);
+			Self::deposit_event(Event::NormalOperationResumed);
+			Ok(())
+		}
+
+		/// Filter all non-whitelisted calls to the pallet named `pallet`, which must be the name
+		/// of a pallet with calls as it appears in `construct_runtime!`.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn pause_pallet(origin: OriginFor<T>, pallet: Vec<u8>) -> DispatchResult {
+			T::MaintenanceOrigin::ensure_origin(origin)?;
+			ensure!(pallet != Self::name().as_bytes(), Error::<T>::CannotPauseSelf);
+			let pallet: PalletNameOf<T> =
+				pallet.try_into().map_err(|_| Error::<T>::PalletNameTooLong)?;
+			ensure!(
+				T::RuntimeCall::get_module_names()
+					.iter()
+					.any(|name| name.as_bytes() == &pallet[..]),
+				Error::<T>::UnknownPallet
+			);
+			ensure!(!<PausedPallets<T>>::contains_key(&pallet), Error::<T>::AlreadyPaused);
+
+			<PausedPallets<T>>::insert(&pallet, ());
+			Self::deposit_event(Event::PalletPaused(pallet));
+			Ok(())
+		}
+
+		/// Allow calls to the pallet named `pallet` again.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn unpause_pallet(origin: OriginFor<T>, pallet: Vec<u8>) -> DispatchResult {
+			T::MaintenanceOrigin::ensure_origin(origin)?;
+			let pallet: PalletNameOf<T> =
+				pallet.try_into().map_err(|_| Error::<T>::PalletNameTooLong)?;
+			ensure!(<PausedPallets<T>>::contains_key(&pallet), Error::<T>::NotPaused);
+
+			<PausedPallets<T>>::remove(&pallet);
+			Self::deposit_event(Event::PalletUnpaused(pallet));
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Contains<T::Call> for Pallet<T>
+	where
+		T::Call: GetCallMetadata,
+	{
+		fn contains(call: &T::Call) -> bool {
+			if T::WhitelistedCalls::contains(call) {
+				return true
+			}
+			if <MaintenanceMode<T>>::get() {
+				return false
+			}
+
+			let CallMetadata { pallet_name, .. } = call.get_call_metadata();
+			// Names that don't fit can't have been paused.
+			match PalletNameOf::<T>::try_from(pallet_name.as_bytes().to_vec()) {
+				Ok(name) => !<PausedPallets<T>>::contains_key(name),
+				Err(_) => true,
+			}
+		}
+	}
+}
@@ -0,0 +1,135 @@
+use crate::{mock::*, Error, Event as MaintenanceEvent};
+use frame_support::{assert_noop, assert_ok, dispatch::Dispatchable};
+use sp_runtime::DispatchError;
+
+fn remark() -> Call {
+	Call::System(frame_system::Call::remark { remark: vec![] })
+}
+
+fn last_event() -> Event {
+	System::events().pop().expect("an event was deposited").event
+}
+
+#[test]
+fn calls_pass_by_default() {
+	new_test_ext().execute_with(|| {
+		assert!(!MaintenanceMode::maintenance_mode());
+		assert_ok!(remark().dispatch(Origin::signed(1)));
+	});
+}
+
+#[test]
+fn maintenance_mode_filters_non_whitelisted_calls() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(MaintenanceMode::enter_maintenance_mode(Origin::root()));
+		assert_eq!(last_event(), MaintenanceEvent::<Test>::EnteredMaintenanceMode.into());
+
+		assert_noop!(
+			remark().dispatch(Origin::signed(1)),
+			frame_system::Error::<Test>::CallFiltered
+		);
+
+		// The whitelisted pallet stays reachable, so maintenance mode can be switched off.
+		assert_ok!(Call::MaintenanceMode(crate::Call::resume_normal_operation {})
+			.dispatch(Origin::root()));
+		assert_eq!(last_event(), MaintenanceEvent::<Test>::NormalOperationResumed.into());
+		assert_ok!(remark().dispatch(Origin::signed(1)));
+	});
+}
+
+#[test]
+fn root_bypasses_the_filter() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(MaintenanceMode::enter_maintenance_mode(Origin::root()));
+		assert_ok!(remark().dispatch(Origin::root()));
+	});
+}
+
+#[test]
+fn toggling_requires_maintenance_origin() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			MaintenanceMode::enter_maintenance_mode(Origin::signed(1)),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			MaintenanceMode::pause_pallet(Origin::signed(1), b"System".to_vec()),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn toggling_twice_fails() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			MaintenanceMode::resume_normal_operation(Origin::root()),
+			Error::<Test>::NotInMaintenanceMode
+		);
+		assert_ok!(MaintenanceMode::enter_maintenance_mode(Origin::root()));
+		assert_noop!(
+			MaintenanceMode::enter_maintenance_mode(Origin::root()),
+			Error::<Test>::AlreadyInMaintenanceMode
+		);
+	});
+}
+
+#[test]
+fn paused_pallet_is_filtered() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(MaintenanceMode::pause_pallet(Origin::root(), b"System".to_vec()));
+		assert_eq!(
+			last_event(),
+			MaintenanceEvent::<Test>::PalletPaused(b"System".to_vec().try_into().unwrap()).into()
+		);
+		assert_noop!(
+			remark().dispatch(Origin::signed(1)),
+			frame_system::Error::<Test>::CallFiltered
+		);
+
+		assert_ok!(MaintenanceMode::unpause_pallet(Origin::root(), b"System".to_vec()));
+		assert_eq!(
+			last_event(),
+			MaintenanceEvent::<Test>::PalletUnpaused(b"System".to_vec().try_into().unwrap()).into()
+		);
+		assert_ok!(remark().dispatch(Origin::signed(1)));
+	});
+}
+
+#[test]
+fn only_pallets_with_calls_can_be_paused() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			MaintenanceMode::pause_pallet(Origin::root(), b"EVM".to_vec()),
+			Error::<Test>::UnknownPallet
+		);
+		assert_noop!(
+			MaintenanceMode::pause_pallet(Origin::root(), b"system".to_vec()),
+			Error::<Test>::UnknownPallet
+		);
+		assert_ok!(remark().dispatch(Origin::signed(1)));
+	});
+}
+
+#[test]
+fn pause_bookkeeping_errors() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			MaintenanceMode::unpause_pallet(Origin::root(), b"System".to_vec()),
+			Error::<Test>::NotPaused
+		);
+		assert_ok!(MaintenanceMode::pause_pallet(Origin::root(), b"System".to_vec()));
+		assert_noop!(
+			MaintenanceMode::pause_pallet(Origin::root(), b"System".to_vec()),
+			Error::<Test>::AlreadyPaused
+		);
+		assert_noop!(
+			MaintenanceMode::pause_pallet(Origin::root(), b"MaintenanceMode".to_vec()),
+			Error::<Test>::CannotPauseSelf
+		);
+		assert_noop!(
+			MaintenanceMode::pause_pallet(Origin::root(), vec![b'x'; 17]),
+			Error::<Test>::PalletNameTooLong
+		);
+	});
+}
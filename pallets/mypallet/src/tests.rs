@@ -14,3 +14,20 @@ fn it_works_for_default_value() {
 		assert_eq!(1, 1);
 	});
 }
+
+#[test]
+fn migrate_to_v1_sets_storage_version_and_keeps_values() {
+	use crate::migrations::v1::MigrateToV1;
+	use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion};
+
+	new_test_ext().execute_with(|| {
+		assert_ok!(MyPallet::simple(Origin::signed(1), 123));
+		assert_eq!(MyPallet::on_chain_storage_version(), StorageVersion::new(0));
+
+		MigrateToV1::<Test>::on_runtime_upgrade();
+
+		assert_eq!(MyPallet::on_chain_storage_version(), StorageVersion::new(1));
+		assert_eq!(MyPallet::init_storage(), Some(8888));
+		assert_eq!(MyPallet::stored_value(), Some(123));
+	});
+}
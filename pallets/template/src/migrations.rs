@@ -0,0 +1,49 @@
+//! Storage migrations for pallet-template.
+
+use crate::{Config, Pallet, Something};
+use core::marker::PhantomData;
+use frame_support::{
+	traits::{Get, GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+	weights::Weight,
+};
+
+pub mod v1 {
+	use super::*;
+	#[cfg(feature = "try-runtime")]
+	use frame_support::{ensure, traits::OnRuntimeUpgradeHelpersExt};
+
+	/// Puts a storage version on chains that deployed the pallet before it was versioned.
+	///
+	/// The storage layout is unchanged, so this only has to make sure `Something` survives.
+	pub struct MigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() < 1 {
+				StorageVersion::new(1).put::<Pallet<T>>();
+				T::DbWeight::get().reads_writes(1, 1)
+			} else {
+				T::DbWeight::get().reads(1)
+			}
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<(), &'static str> {
+			ensure!(
+				Pallet::<T>::on_chain_storage_version() <= 1,
+				"on-chain storage version is newer than this migration"
+			);
+			Self::set_temp_storage(Something::<T>::get(), "something");
+			Ok(())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade() -> Result<(), &'static str> {
+			ensure!(Pallet::<T>::on_chain_storage_version() == 1, "storage version not set to 1");
+			let before: Option<u32> = Self::get_temp_storage("something")
+				.ok_or("`Something` was not recorded in pre_upgrade")?;
+			ensure!(before == Something::<T>::get(), "`Something` changed during the migration");
+			Ok(())
+		}
+	}
+}
@@ -18,3 +18,19 @@ fn correct_error_for_none_value() {
 		assert_noop!(TemplateModule::cause_error(Origin::signed(1)), Error::<Test>::NoneValue);
 	});
 }
+
+#[test]
+fn migrate_to_v1_sets_storage_version_and_keeps_value() {
+	use crate::migrations::v1::MigrateToV1;
+	use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion};
+
+	new_test_ext().execute_with(|| {
+		assert_ok!(TemplateModule::do_something(Origin::signed(1), 42));
+		assert_eq!(TemplateModule::on_chain_storage_version(), StorageVersion::new(0));
+
+		MigrateToV1::<Test>::on_runtime_upgrade();
+
+		assert_eq!(TemplateModule::on_chain_storage_version(), StorageVersion::new(1));
+		assert_eq!(TemplateModule::something(), Some(42));
+	});
+}
@@ -0,0 +1,204 @@
+//! Differential tests of the `Test` runtime's EVM against the `evm` crate on its own.
+//!
+//! Random bytecode is called with random calldata by dispatching `EVM::call`, and through a
+//! `StackExecutor` over a `MemoryBackend` with the same block environment. The caller pays a
+//! non-zero base fee, and its account is a hash of its address, as in the runtime. Any difference
+//! in the outcome comes from our `pallet_evm::Config` choices or from Frontier's stack state, not
+//! from the interpreter both share.
+
+use crate::mock::*;
+use evm::{
+	backend::{ApplyBackend, MemoryAccount, MemoryBackend, MemoryVicinity},
+	executor::stack::{MemoryStackState, StackExecutor, StackSubstateMetadata},
+	ExitReason,
+};
+use frame_support::{
+	sp_io::storage::{rollback_transaction, start_transaction},
+	traits::{Currency, Get},
+	weights::Weight,
+};
+use pallet_evm::{AddressMapping, Runner};
+use pallet_evm_hardfork::Hardfork;
+use proptest::{collection::vec, prelude::*, sample::select};
+use sp_core::{H160, H256, U256};
+use std::collections::BTreeMap;
+
+const CALLER: H160 = H160::repeat_byte(0x01);
+const CONTRACT: H160 = H160::repeat_byte(0xc0);
+const GAS_LIMIT: u64 = 1_000_000;
+const GAS_PRICE: u64 = 1_000;
+/// The balance of the caller before it pays for the call.
+const FUNDS: u128 = 1_000_000_000_000_000_000;
+
+/// What a call leaves behind, to compare.
+#[derive(Debug, PartialEq)]
+struct Outcome {
+	exit_reason: ExitReason,
+	return_data: Vec<u8>,
+	used_gas: U256,
+	/// The non-zero storage of all accounts.
+	storage: BTreeMap<(H160, H256), H256>,
+	/// What the call cost the caller, fee included.
+	spent: U256,
+	/// The weight of the call once it ran.
+	weight: Weight,
+}
+
+fn call_runtime(code: &[u8], input: &[u8]) -> Outcome {
+	new_test_ext().execute_with(|| {
+		HashAddresses::set(&true);
+		BaseFee::set(&GAS_PRICE.into());
+		let caller = TestAddressMapping::into_account_id(CALLER);
+		let _ = Balances::deposit_creating(&caller, FUNDS);
+		pallet_evm::AccountCodes::<Test>::insert(CONTRACT, code.to_vec());
+
+		// `EVM::call` does not return the call's outcome, so the runner it dispatches to runs the
+		// call first, in a storage transaction that is rolled back.
+		start_transaction();
+		let info = <Test as pallet_evm::Config>::Runner::call(
+			CALLER,
+			CONTRACT,
+			input.to_vec(),
+			U256::zero(),
+			GAS_LIMIT,
+			Some(GAS_PRICE.into()),
+			None,
+			None,
+			vec![],
+			true,
+			<Test as pallet_evm::Config>::config(),
+		)
+		.expect("call passes validation");
+		rollback_transaction();
+
+		let post_info = EVM::call(
+			Origin::root(),
+			CALLER,
+			CONTRACT,
+			input.to_vec(),
+			U256::zero(),
+			GAS_LIMIT,
+			GAS_PRICE.into(),
+			None,
+			None,
+			vec![],
+		)
+		.expect("call passes validation");
+
+		Outcome {
+			exit_reason: info.exit_reason,
+			return_data: info.value,
+			used_gas: info.used_gas,
+			storage: pallet_evm::AccountStorages::<Test>::iter()
+				.map(|(address, key, value)| ((address, key), value))
+				.collect(),
+			spent: (FUNDS - Balances::free_balance(&caller)).into(),
+			weight: post_info.actual_weight.expect("`EVM::call` returns its weight"),
+		}
+	})
+}
+
+fn call_evm(code: &[u8], input: &[u8]) -> Outcome {
+	// The environment `call_runtime` runs in. Only the previous block has a hash.
+	let vicinity = new_test_ext().execute_with(|| MemoryVicinity {
+		gas_price: GAS_PRICE.into(),
+		origin: CALLER,
+		chain_id: <Test as pallet_evm::Config>::ChainId::get().into(),
+		block_hashes: vec![System::block_hash(System::block_number() - 1)],
+		block_number: System::block_number().into(),
+		block_coinbase: Coinbase::get(),
+		block_timestamp: (Timestamp::get() / 1_000).into(),
+		block_difficulty: U256::zero(),
+		block_gas_limit: BlockGasLimit::get(),
+		block_base_fee_per_gas: GAS_PRICE.into(),
+	});
+	let contract = MemoryAccount {
+		nonce: U256::zero(),
+		balance: U256::zero(),
+		storage: BTreeMap::new(),
+		code: code.to_vec(),
+	};
+	// The fee for all the gas is paid up front, and what is left over refunded at the end.
+	let prepaid = U256::from(GAS_LIMIT) * GAS_PRICE;
+	let caller = MemoryAccount {
+		nonce: U256::zero(),
+		balance: U256::from(FUNDS) - prepaid,
+		storage: BTreeMap::new(),
+		code: vec![],
+	};
+	let mut backend =
+		MemoryBackend::new(&vicinity, BTreeMap::from([(CALLER, caller), (CONTRACT, contract)]));
+
+	let config = Hardfork::London.config();
+	let metadata = StackSubstateMetadata::new(GAS_LIMIT, config);
+	let mut executor =
+		StackExecutor::new_with_precompiles(MemoryStackState::new(metadata, &backend), config, &());
+	let (exit_reason, return_data) =
+		executor.transact_call(CALLER, CONTRACT, U256::zero(), input.to_vec(), GAS_LIMIT, vec![]);
+	let used_gas = U256::from(executor.used_gas());
+	let (changes, logs) = executor.into_state().deconstruct();
+	backend.apply(changes, logs, false);
+
+	let storage = backend
+		.state()
+		.iter()
+		.flat_map(|(address, account)| {
+			account.storage.iter().map(move |(key, value)| ((*address, *key), *value))
+		})
+		.filter(|(_, value)| !value.is_zero())
+		.collect();
+	let refund = (U256::from(GAS_LIMIT) - used_gas) * GAS_PRICE;
+	let left = backend.state().get(&CALLER).map_or(U256::zero(), |caller| caller.balance) + refund;
+	Outcome {
+		exit_reason,
+		return_data,
+		used_gas,
+		storage,
+		spent: U256::from(FUNDS) - left,
+		weight: used_gas.low_u64() * WEIGHT_PER_GAS,
+	}
+}
+
+/// The opcodes without immediates the generated code is made of, mostly.
+#[rustfmt::skip]
+const OPCODES: &[u8] = &[
+	// STOP … SIGNEXTEND
+	0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b,
+	// LT … SAR, SHA3
+	0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x20,
+	// ADDRESS … EXTCODEHASH
+	0x30, 0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x3b, 0x3c, 0x3d, 0x3e, 0x3f,
+	// BLOCKHASH … BASEFEE
+	0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48,
+	// POP … JUMPDEST
+	0x50, 0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x5b,
+	// DUP1 … DUP4, SWAP1 … SWAP4
+	0x80, 0x81, 0x82, 0x83, 0x90, 0x91, 0x92, 0x93,
+	// LOG0 … LOG2, RETURN, REVERT
+	0xa0, 0xa1, 0xa2, 0xf3, 0xfd,
+];
+
+/// Bytecode that mostly pushes small numbers, so memory, storage and jumps stay in range.
+fn bytecode() -> impl Strategy<Value = Vec<u8>> {
+	let instruction = prop_oneof![
+		4 => select(OPCODES).prop_map(|opcode| vec![opcode]),
+		4 => (0u8..64).prop_map(|n| vec![0x60, n]),
+		1 => (1usize..=32, any::<[u8; 32]>()).prop_map(|(n, bytes)| {
+			let mut push = vec![0x5f + n as u8];
+			push.extend_from_slice(&bytes[..n]);
+			push
+		}),
+		1 => any::<u8>().prop_map(|byte| vec![byte]),
+	];
+	vec(instruction, 0..64).prop_map(|instructions| instructions.concat())
+}
+
+proptest! {
+	#[test]
+	fn the_runtime_executes_like_the_evm_crate(
+		code in bytecode(),
+		input in vec(any::<u8>(), 0..68),
+	) {
+		prop_assert_eq!(call_runtime(&code, &input), call_evm(&code, &input));
+	}
+}
@@ -1,5 +1,11 @@
+#[cfg(test)]
+mod differential;
+
 #[cfg(test)]
 mod mock;
 
+#[cfg(test)]
+mod state_tests;
+
 #[cfg(test)]
 mod tests;
\ No newline at end of file
@@ -3,11 +3,16 @@ use frame_support::sp_runtime::{
 	app_crypto::sp_core::{H160, H256, U256},
 	testing::Header,
 	traits::BlakeTwo256,
+	traits::Convert,
 	traits::IdentityLookup,
 };
-use frame_support::traits::{ConstU16, ConstU64};
+use frame_support::traits::{ConstU16, ConstU64, FindAuthor};
+use frame_support::weights::{RuntimeDbWeight, Weight};
+use frame_support::{sp_io::hashing::blake2_256, ConsensusEngineId};
 use frame_system as system;
-use pallet_evm::{EnsureAddressNever, EnsureAddressRoot, IdentityAddressMapping};
+use pallet_evm::{
+	AddressMapping, EnsureAddressNever, EnsureAddressRoot, FeeCalculator, GasWeightMapping,
+};
 
 type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
 type Block = frame_system::mocking::MockBlock<Test>;
@@ -21,7 +26,11 @@ frame_support::construct_runtime!(
 		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
 		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
 		Timestamp: pallet_timestamp::{Pallet, Call, Storage},
-        EVM: pallet_evm::{Pallet, Call, Storage, Config, Event<T>}
+        EVM: pallet_evm::{Pallet, Call, Storage, Config, Event<T>},
+		EvmHardfork: pallet_evm_hardfork::{Pallet, Call, Storage, Config, Event<T>},
+		DeployerAllowlist: pallet_deployer_allowlist::{Pallet, Call, Storage, Config, Event<T>},
+		Ethereum: pallet_ethereum::{Pallet, Call, Storage, Event, Origin},
+		EvmReaper: pallet_evm_reaper::{Pallet, Storage, Event<T>},
 	}
 );
 
@@ -29,7 +38,7 @@ impl system::Config for Test {
 	type BaseCallFilter = frame_support::traits::Everything;
 	type BlockWeights = ();
 	type BlockLength = ();
-	type DbWeight = ();
+	type DbWeight = TestDbWeight;
 	type Origin = Origin;
 	type Call = Call;
 	type Index = u64;
@@ -43,9 +52,9 @@ impl system::Config for Test {
 	type BlockHashCount = ConstU64<250>;
 	type Version = ();
 	type PalletInfo = PalletInfo;
-	type AccountData = pallet_balances::AccountData<u64>;
+	type AccountData = pallet_balances::AccountData<u128>;
 	type OnNewAccount = ();
-	type OnKilledAccount = ();
+	type OnKilledAccount = EvmReaper;
 	type SystemWeightInfo = ();
 	type SS58Prefix = ConstU16<42>;
 	type OnSetCode = ();
@@ -53,11 +62,15 @@ impl system::Config for Test {
 }
 
 parameter_types! {
-	pub const ExistentialDeposit: u64 = 0;
+	pub const TestDbWeight: RuntimeDbWeight = RuntimeDbWeight { read: 1, write: 1 };
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u128 = 1;
 }
 impl pallet_balances::Config for Test {
 	type MaxLocks = ();
-	type Balance = u64;
+	type Balance = u128;
 	type DustRemoval = ();
 	type Event = Event;
 	type ExistentialDeposit = ExistentialDeposit;
@@ -77,24 +90,126 @@ impl pallet_timestamp::Config for Test {
 	type WeightInfo = ();
 }
 
+// The block environment, which the state tests set for each transaction.
 parameter_types! {
-	pub BlockGasLimit: U256 = U256::max_value();
+	pub storage BlockGasLimit: U256 = U256::max_value();
+	pub storage BaseFee: U256 = U256::zero();
+	pub storage Coinbase: H160 = H160::zero();
+}
+
+/// Off by default, so that tests can use addresses as accounts.
+parameter_types! {
+	pub storage HashAddresses: bool = false;
+}
+
+/// The account of an address is the address itself or, while [`HashAddresses`] is set, part of
+/// its hash, as for addresses the runtime does not know a linked account of.
+pub struct TestAddressMapping;
+impl AddressMapping<H160> for TestAddressMapping {
+	fn into_account_id(address: H160) -> H160 {
+		if HashAddresses::get() {
+			H160::from_slice(&blake2_256(address.as_bytes())[..20])
+		} else {
+			address
+		}
+	}
+}
+
+/// The address of an account while [`HashAddresses`] is off. Hashed accounts cannot be traced back,
+/// so the reaper relies on what `RecordingRunner` saw, as in the runtime.
+pub struct TestAddressOf;
+impl Convert<H160, Option<H160>> for TestAddressOf {
+	fn convert(who: H160) -> Option<H160> {
+		if HashAddresses::get() {
+			None
+		} else {
+			Some(who)
+		}
+	}
+}
+
+/// The weight of a unit of gas in the runtime.
+pub const WEIGHT_PER_GAS: Weight = 25_000;
+
+/// Charges [`WEIGHT_PER_GAS`] for every unit of gas, like the runtime.
+pub struct FixedGasWeightMapping;
+impl GasWeightMapping for FixedGasWeightMapping {
+	fn gas_to_weight(gas: u64) -> Weight {
+		gas.saturating_mul(WEIGHT_PER_GAS)
+	}
+
+	fn weight_to_gas(weight: Weight) -> u64 {
+		weight / WEIGHT_PER_GAS
+	}
+}
+
+/// Charges [`BaseFee`].
+pub struct FixedBaseFee;
+impl FeeCalculator for FixedBaseFee {
+	fn min_gas_price() -> U256 {
+		BaseFee::get()
+	}
+}
+
+/// Makes [`Coinbase`] the author of every block.
+pub struct FixedCoinbase;
+impl FindAuthor<H160> for FixedCoinbase {
+	fn find_author<'a, I>(_digests: I) -> Option<H160>
+	where
+		I: 'a + IntoIterator<Item = (ConsensusEngineId, &'a [u8])>,
+	{
+		Some(Coinbase::get())
+	}
 }
 
 impl pallet_evm::Config for Test {
-	type FeeCalculator = ();
-	type GasWeightMapping = ();
+	type FeeCalculator = FixedBaseFee;
+	type GasWeightMapping = FixedGasWeightMapping;
 	type CallOrigin = EnsureAddressRoot<Self::AccountId>;
 	type WithdrawOrigin = EnsureAddressNever<Self::AccountId>;
-	type AddressMapping = IdentityAddressMapping;
+	type AddressMapping = TestAddressMapping;
 	type Currency = Balances;
 	type Event = Event;
-	type Runner = pallet_evm::runner::stack::Runner<Self>;
+	type Runner = pallet_deployer_allowlist::AllowlistRunner<
+		Self,
+		pallet_evm_reaper::RecordingRunner<Self, pallet_evm::runner::stack::Runner<Self>>,
+	>;
 	type PrecompilesType = ();
 	type PrecompilesValue = ();
-	type ChainId = ();
+	type ChainId = ConstU64<1>;
 	type OnChargeTransaction = ();
 	type BlockGasLimit = BlockGasLimit;
 	type BlockHashMapping = pallet_evm::SubstrateBlockHashMapping<Self>;
-	type FindAuthor = ();
+	type FindAuthor = FixedCoinbase;
+
+	fn config() -> &'static pallet_evm_hardfork::EvmConfig {
+		EvmHardfork::evm_config()
+	}
+}
+
+impl pallet_evm_hardfork::Config for Test {
+	type Event = Event;
+	type HardforkOrigin = frame_system::EnsureRoot<Self::AccountId>;
+}
+
+impl pallet_deployer_allowlist::Config for Test {
+	type Event = Event;
+	type AllowlistOrigin = frame_system::EnsureRoot<Self::AccountId>;
+}
+
+impl pallet_ethereum::Config for Test {
+	type Event = Event;
+	type StateRoot = pallet_ethereum::IntermediateStateRoot<Self>;
+}
+
+impl pallet_evm_reaper::Config for Test {
+	type Event = Event;
+	type AddressOf = TestAddressOf;
+}
+
+pub fn new_test_ext() -> frame_support::sp_io::TestExternalities {
+	let t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	let mut ext = frame_support::sp_io::TestExternalities::new(t);
+	ext.execute_with(|| System::set_block_number(1));
+	ext
 }
@@ -0,0 +1,423 @@
+//! Runs the `GeneralStateTests` of [ethereum/tests](https://github.com/ethereum/tests) against
+//! `pallet_evm`, to check the EVM follows Ethereum semantics.
+//!
+//! The fixtures are not vendored. Point `GENERAL_STATE_TESTS` at a checkout of the
+//! `GeneralStateTests` directory, or any directory of filled state tests, and run
+//!
+//! ```text
+//! GENERAL_STATE_TESTS=../tests/GeneralStateTests \
+//! 	cargo test -p test_evm general_state_tests -- --ignored --nocapture
+//! ```
+//!
+//! Every post state of the forks `pallet_evm_hardfork` supports is checked: the transaction is set
+//! up in the `Test` runtime, executed through `pallet_evm::runner::stack::Runner`, and the
+//! resulting state root and logs hash compared to the expected ones. Block hashes and the
+//! difficulty are not part of the Substrate environment, so tests depending on them fail.
+
+use crate::mock::*;
+use frame_support::{assert_ok, traits::Currency};
+use pallet_evm::{runner::stack::Runner, Log, Runner as _};
+use pallet_evm_hardfork::Hardfork;
+use rlp::RlpStream;
+use serde::Deserialize;
+use sp_core::{hashing::keccak_256, H160, H256, U256};
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	fs,
+	path::{Path, PathBuf},
+};
+
+/// A state test, as filled in a fixture file under its name.
+#[derive(Deserialize)]
+struct StateTest {
+	env: Env,
+	pre: BTreeMap<String, PreAccount>,
+	transaction: Transaction,
+	/// The expected post states, by fork name.
+	post: BTreeMap<String, Vec<PostState>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Env {
+	current_coinbase: String,
+	current_gas_limit: String,
+	current_number: String,
+	current_timestamp: String,
+	current_base_fee: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PreAccount {
+	balance: String,
+	code: String,
+	nonce: String,
+	storage: BTreeMap<String, String>,
+}
+
+/// The transactions of a test: one for each combination of `data`, `gas_limit` and `value`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Transaction {
+	data: Vec<String>,
+	gas_limit: Vec<String>,
+	value: Vec<String>,
+	gas_price: Option<String>,
+	max_fee_per_gas: Option<String>,
+	max_priority_fee_per_gas: Option<String>,
+	nonce: String,
+	secret_key: String,
+	/// Empty for contract creations.
+	to: String,
+	/// The access list of each `data`, for typed transactions.
+	access_lists: Option<Vec<Option<Vec<AccessListItem>>>>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccessListItem {
+	address: String,
+	storage_keys: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PostState {
+	hash: String,
+	logs: String,
+	indexes: Indexes,
+	/// Set when the transaction is invalid, which leaves the state untouched.
+	expect_exception: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Indexes {
+	data: usize,
+	gas: usize,
+	value: usize,
+}
+
+/// The hardfork `pallet_evm_hardfork` implements a fork with, if any.
+fn hardfork(fork: &str) -> Option<Hardfork> {
+	match fork {
+		"Istanbul" => Some(Hardfork::Istanbul),
+		"Berlin" => Some(Hardfork::Berlin),
+		"London" => Some(Hardfork::London),
+		_ => None,
+	}
+}
+
+fn bytes(hex: &str) -> Result<Vec<u8>, String> {
+	// Fillers may keep the source of `data` as `:raw 0x…`.
+	let hex = hex.strip_prefix(":raw ").unwrap_or(hex);
+	let hex = hex.strip_prefix("0x").unwrap_or(hex);
+	if hex.len() % 2 != 0 {
+		return Err(format!("odd number of hex digits in {}", hex))
+	}
+	(0..hex.len())
+		.step_by(2)
+		.map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+		.collect::<Result<_, _>>()
+		.map_err(|_| format!("invalid hex {}", hex))
+}
+
+fn number(hex: &str) -> Result<U256, String> {
+	let digits = hex.strip_prefix("0x").unwrap_or(hex).trim_start_matches('0');
+	if digits.is_empty() {
+		return Ok(U256::zero())
+	}
+	U256::from_str_radix(digits, 16).map_err(|_| format!("invalid number {}", hex))
+}
+
+fn small_number<T: TryFrom<U256>>(hex: &str) -> Result<T, String> {
+	T::try_from(number(hex)?).map_err(|_| format!("{} is out of range", hex))
+}
+
+fn hash(hex: &str) -> Result<H256, String> {
+	// Storage keys and values are numbers, without leading zeros.
+	let bytes = bytes(hex)?;
+	if bytes.len() > 32 {
+		return Err(format!("invalid hash {}", hex))
+	}
+	let mut hash = H256::zero();
+	hash[32 - bytes.len()..].copy_from_slice(&bytes);
+	Ok(hash)
+}
+
+fn address(hex: &str) -> Result<H160, String> {
+	let bytes = bytes(hex)?;
+	(bytes.len() == 20)
+		.then(|| H160::from_slice(&bytes))
+		.ok_or_else(|| format!("invalid address {}", hex))
+}
+
+/// The address of the account `secret_key` signs for.
+fn sender(secret_key: &str) -> Result<H160, String> {
+	let secret = libsecp256k1::SecretKey::parse_slice(&bytes(secret_key)?)
+		.map_err(|e| format!("invalid secret key: {:?}", e))?;
+	let public = libsecp256k1::PublicKey::from_secret_key(&secret).serialize();
+	Ok(H160::from_slice(&keccak_256(&public[1..])[12..]))
+}
+
+/// `value` in big endian, without leading zeros, as RLP encodes integers.
+fn trimmed(value: U256) -> Vec<u8> {
+	let mut bytes = [0u8; 32];
+	value.to_big_endian(&mut bytes);
+	bytes[(value.leading_zeros() / 8) as usize..].to_vec()
+}
+
+/// The root of the Ethereum state trie of all non-empty accounts.
+fn state_root() -> H256 {
+	let mut addresses: BTreeSet<H160> = frame_system::Account::<Test>::iter_keys().collect();
+	addresses.extend(pallet_evm::AccountCodes::<Test>::iter_keys());
+	addresses.extend(pallet_evm::AccountStorages::<Test>::iter_keys().map(|(address, _)| address));
+
+	let accounts = addresses.into_iter().filter_map(|address| {
+		let basic = EVM::account_basic(&address);
+		let code = pallet_evm::AccountCodes::<Test>::get(address);
+		let storage: Vec<_> = pallet_evm::AccountStorages::<Test>::iter_prefix(address)
+			.filter(|(_, value)| !value.is_zero())
+			.map(|(key, value)| {
+				(key, rlp::encode(&trimmed(U256::from_big_endian(value.as_bytes()))))
+			})
+			.collect();
+		if basic.nonce.is_zero() && basic.balance.is_zero() && code.is_empty() && storage.is_empty()
+		{
+			return None
+		}
+
+		let storage_root = ethereum::util::sec_trie_root(storage);
+		let mut account = RlpStream::new_list(4);
+		account.append(&trimmed(basic.nonce));
+		account.append(&trimmed(basic.balance));
+		account.append(&storage_root.as_bytes());
+		account.append(&keccak_256(&code).as_slice());
+		Some((address, account.out()))
+	});
+	ethereum::util::sec_trie_root(accounts)
+}
+
+/// The hash of the RLP encoding of `logs`.
+fn logs_hash(logs: &[Log]) -> H256 {
+	let mut stream = RlpStream::new_list(logs.len());
+	for log in logs {
+		stream.begin_list(3);
+		stream.append(&log.address.as_bytes());
+		stream.begin_list(log.topics.len());
+		for topic in &log.topics {
+			stream.append(&topic.as_bytes());
+		}
+		stream.append(&log.data);
+	}
+	keccak_256(&stream.out()).into()
+}
+
+/// Runs the transaction `post` is the outcome of, with the rules of `hardfork`.
+fn run(test: &StateTest, hardfork: Hardfork, post: &PostState) -> Result<(), String> {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EvmHardfork::set_hardfork(Origin::root(), hardfork));
+
+		let env = &test.env;
+		System::set_block_number(small_number(&env.current_number)?);
+		pallet_timestamp::Now::<Test>::put(small_number::<u64>(&env.current_timestamp)? * 1_000);
+		BlockGasLimit::set(&number(&env.current_gas_limit)?);
+		BaseFee::set(&env.current_base_fee.as_deref().map(number).transpose()?.unwrap_or_default());
+		Coinbase::set(&address(&env.current_coinbase)?);
+
+		for (account, pre) in &test.pre {
+			let account = address(account)?;
+			let _ = Balances::make_free_balance_be(&account, small_number(&pre.balance)?);
+			frame_system::Account::<Test>::mutate(account, |info| -> Result<(), String> {
+				info.nonce = small_number(&pre.nonce)?;
+				Ok(())
+			})?;
+			pallet_evm::AccountCodes::<Test>::insert(account, bytes(&pre.code)?);
+			for (key, value) in &pre.storage {
+				pallet_evm::AccountStorages::<Test>::insert(account, hash(key)?, hash(value)?);
+			}
+		}
+
+		let tx = &test.transaction;
+		let indexes = &post.indexes;
+		let source = sender(&tx.secret_key)?;
+		let input = bytes(&tx.data[indexes.data])?;
+		let value = number(&tx.value[indexes.value])?;
+		let gas_limit = small_number(&tx.gas_limit[indexes.gas])?;
+		let (max_fee_per_gas, max_priority_fee_per_gas) = match &tx.gas_price {
+			Some(gas_price) => (number(gas_price)?, None),
+			None => (
+				number(tx.max_fee_per_gas.as_deref().unwrap_or_default())?,
+				Some(number(tx.max_priority_fee_per_gas.as_deref().unwrap_or_default())?),
+			),
+		};
+		let nonce = Some(number(&tx.nonce)?);
+		let access_list = tx
+			.access_lists
+			.as_ref()
+			.and_then(|lists| lists.get(indexes.data).cloned().flatten())
+			.unwrap_or_default()
+			.iter()
+			.map(|item| {
+				let keys =
+					item.storage_keys.iter().map(|key| hash(key)).collect::<Result<_, _>>()?;
+				Ok((address(&item.address)?, keys))
+			})
+			.collect::<Result<Vec<_>, String>>()?;
+		let config = <Test as pallet_evm::Config>::config();
+
+		let result = if tx.to.is_empty() {
+			Runner::<Test>::create(
+				source,
+				input,
+				value,
+				gas_limit,
+				Some(max_fee_per_gas),
+				max_priority_fee_per_gas,
+				nonce,
+				access_list,
+				true,
+				config,
+			)
+			.map(|info| info.logs)
+		} else {
+			Runner::<Test>::call(
+				source,
+				address(&tx.to)?,
+				input,
+				value,
+				gas_limit,
+				Some(max_fee_per_gas),
+				max_priority_fee_per_gas,
+				nonce,
+				access_list,
+				true,
+				config,
+			)
+			.map(|info| info.logs)
+		};
+
+		let logs = match (result, &post.expect_exception) {
+			(Ok(logs), None) => logs,
+			(Err(_), Some(_)) => vec![],
+			(Ok(_), Some(exception)) => return Err(format!("expected {}", exception)),
+			(Err(e), None) => return Err(format!("rejected with {:?}", e)),
+		};
+		if logs_hash(&logs) != hash(&post.logs)? {
+			return Err(format!("logs hash mismatch, got {:?}", logs))
+		}
+		let root = state_root();
+		if root != hash(&post.hash)? {
+			return Err(format!("state root mismatch, got {:?}", root))
+		}
+		Ok(())
+	})
+}
+
+/// All `.json` files under `dir`.
+fn fixtures(dir: &Path, found: &mut Vec<PathBuf>) {
+	for entry in fs::read_dir(dir).unwrap_or_else(|e| panic!("cannot read {:?}: {}", dir, e)) {
+		let path = entry.expect("directory entry").path();
+		if path.is_dir() {
+			fixtures(&path, found);
+		} else if path.extension().map_or(false, |extension| extension == "json") {
+			found.push(path);
+		}
+	}
+}
+
+#[derive(Default)]
+struct Counts {
+	passed: usize,
+	failed: usize,
+	/// Post states of forks the EVM cannot be configured for.
+	skipped: usize,
+}
+
+/// Runs every fixture under `GENERAL_STATE_TESTS` and reports the pass, fail and skip counts of
+/// each fork. Fails if any post state differs.
+#[test]
+#[ignore]
+fn general_state_tests() {
+	let dir = std::env::var("GENERAL_STATE_TESTS")
+		.expect("GENERAL_STATE_TESTS should be a directory of ethereum/tests state tests");
+	let mut paths = vec![];
+	fixtures(Path::new(&dir), &mut paths);
+	paths.sort();
+
+	let mut counts = BTreeMap::<String, Counts>::new();
+	let mut failures = vec![];
+	for path in paths {
+		let json = fs::read_to_string(&path).expect("fixture is readable");
+		let tests: BTreeMap<String, StateTest> = match serde_json::from_str(&json) {
+			Ok(tests) => tests,
+			Err(e) => {
+				failures.push(format!("{}: not a state test: {}", path.display(), e));
+				continue
+			},
+		};
+		for (name, test) in &tests {
+			for (fork, posts) in &test.post {
+				let counts = counts.entry(fork.clone()).or_default();
+				let hardfork = match hardfork(fork) {
+					Some(hardfork) => hardfork,
+					None => {
+						counts.skipped += posts.len();
+						continue
+					},
+				};
+				for (i, post) in posts.iter().enumerate() {
+					match run(test, hardfork, post) {
+						Ok(()) => counts.passed += 1,
+						Err(e) => {
+							counts.failed += 1;
+							failures.push(format!("{} {}[{}]: {}", name, fork, i, e));
+						},
+					}
+				}
+			}
+		}
+	}
+
+	for failure in &failures {
+		println!("FAIL {}", failure);
+	}
+	for (fork, Counts { passed, failed, skipped }) in &counts {
+		println!("{:>10}: {} passed, {} failed, {} skipped", fork, passed, failed, skipped);
+	}
+	assert!(failures.is_empty(), "{} state tests failed", failures.len());
+}
+
+#[test]
+fn an_empty_state_has_the_empty_trie_root() {
+	new_test_ext().execute_with(|| {
+		// keccak256(rlp(""))
+		assert_eq!(
+			state_root(),
+			H256(hex_literal::hex!(
+				"56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"
+			))
+		);
+		// keccak256(rlp([]))
+		assert_eq!(
+			logs_hash(&[]),
+			H256(hex_literal::hex!(
+				"1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347"
+			))
+		);
+	});
+}
+
+#[test]
+fn fixture_values_are_parsed() {
+	assert_eq!(number("0x00"), Ok(U256::zero()));
+	assert_eq!(number("0x0de0b6b3a7640000"), Ok(U256::exp10(18)));
+	assert_eq!(bytes(":raw 0x6001"), Ok(vec![0x60, 0x01]));
+	assert_eq!(hash("0x01"), Ok(H256::from_low_u64_be(1)));
+	assert_eq!(trimmed(U256::from(0x0100)), vec![0x01, 0x00]);
+	assert!(trimmed(U256::zero()).is_empty());
+	// The sender of most ethereum/tests transactions.
+	assert_eq!(
+		sender("0x45a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8"),
+		Ok(H160(hex_literal::hex!("a94f5374fce5edbc8e2a8697c15331677e6ebf0b")))
+	);
+}
@@ -0,0 +1,502 @@
+use crate::mock::*;
+use ethereum::TransactionAction;
+use frame_support::{
+	assert_noop, assert_ok,
+	dispatch::DispatchResultWithPostInfo,
+	traits::{Currency, Hooks},
+	weights::Weight,
+};
+use hex_literal::hex;
+use pallet_deployer_allowlist::{Error as AllowlistError, Event as AllowlistEvent};
+use pallet_evm::{AddressMapping, CallInfo, ExitReason, ExitSucceed, Runner};
+use pallet_evm_hardfork::{Event as HardforkEvent, Hardfork};
+use sp_core::{keccak_256, H160, H256, U256};
+use sp_runtime::{transaction_validity::InvalidTransaction, DispatchError};
+
+const CALLER: H160 = H160::repeat_byte(0x01);
+const CONTRACT: H160 = H160::repeat_byte(0xc0);
+
+const HARDFORKS: [Hardfork; 3] = [Hardfork::Istanbul, Hardfork::Berlin, Hardfork::London];
+
+/// Calls `code` deployed at `CONTRACT` with the rules selected in `pallet_evm_hardfork`.
+fn execute(code: &[u8]) -> CallInfo {
+	pallet_evm::AccountCodes::<Test>::insert(CONTRACT, code.to_vec());
+	<Test as pallet_evm::Config>::Runner::call(
+		CALLER,
+		CONTRACT,
+		vec![],
+		U256::zero(),
+		1_000_000,
+		None,
+		None,
+		None,
+		vec![],
+		false,
+		<Test as pallet_evm::Config>::config(),
+	)
+	.expect("call passes validation")
+}
+
+fn execute_with_hardfork(hardfork: Hardfork, code: &[u8]) -> CallInfo {
+	new_test_ext().execute_with(|| {
+		assert_ok!(EvmHardfork::set_hardfork(Origin::root(), hardfork));
+		execute(code)
+	})
+}
+
+/// Whether `code` runs without an error.
+fn succeeds(hardfork: Hardfork, code: &[u8]) -> bool {
+	match execute_with_hardfork(hardfork, code).exit_reason {
+		ExitReason::Succeed(_) => true,
+		ExitReason::Error(_) => false,
+		reason => panic!("unexpected exit reason {:?}", reason),
+	}
+}
+
+/// Whether `opcode` is defined, by running `opcode STOP` with an empty stack.
+fn is_available(hardfork: Hardfork, opcode: u8) -> bool {
+	succeeds(hardfork, &[opcode, 0x00])
+}
+
+#[test]
+fn london_is_the_default() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(EvmHardfork::hardfork(), Hardfork::London);
+		assert!(core::ptr::eq(<Test as pallet_evm::Config>::config(), Hardfork::London.config()));
+	});
+}
+
+#[test]
+fn only_the_hardfork_origin_can_switch() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			EvmHardfork::set_hardfork(Origin::signed(CALLER), Hardfork::Berlin),
+			DispatchError::BadOrigin
+		);
+
+		assert_ok!(EvmHardfork::set_hardfork(Origin::root(), Hardfork::Berlin));
+		assert_eq!(EvmHardfork::hardfork(), Hardfork::Berlin);
+		assert_eq!(
+			System::events().pop().expect("an event was deposited").event,
+			HardforkEvent::<Test>::HardforkSet(Hardfork::Berlin).into()
+		);
+	});
+}
+
+#[test]
+fn chainid_and_selfbalance_are_available_since_istanbul() {
+	for hardfork in HARDFORKS {
+		// CHAINID, SELFBALANCE
+		assert!(is_available(hardfork, 0x46), "{:?}", hardfork);
+		assert!(is_available(hardfork, 0x47), "{:?}", hardfork);
+	}
+}
+
+#[test]
+fn basefee_is_only_available_since_london() {
+	// BASEFEE
+	assert!(!is_available(Hardfork::Istanbul, 0x48));
+	assert!(!is_available(Hardfork::Berlin, 0x48));
+	assert!(is_available(Hardfork::London, 0x48));
+
+	// BASEFEE PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+	let info = execute_with_hardfork(Hardfork::London, &hex!("4860005260206000f3"));
+	assert_eq!(info.exit_reason, ExitReason::Succeed(ExitSucceed::Returned));
+	// `BaseFee` is zero unless a state test sets it.
+	assert_eq!(U256::from_big_endian(&info.value), U256::zero());
+}
+
+#[test]
+fn shanghai_and_cancun_opcodes_are_not_available() {
+	// The `evm` version pallet-evm is built against stops at London, see `pallet-evm-hardfork`.
+	// Flip these when the Frontier upgrade adds Shanghai and Cancun.
+	for hardfork in HARDFORKS {
+		// PUSH0
+		assert!(!is_available(hardfork, 0x5f), "{:?}", hardfork);
+		// PUSH1 0 PUSH1 0 TSTORE STOP
+		assert!(!succeeds(hardfork, &hex!("600060005d00")), "{:?}", hardfork);
+		// PUSH1 0 PUSH1 0 PUSH1 0 MCOPY STOP
+		assert!(!succeeds(hardfork, &hex!("6000600060005e00")), "{:?}", hardfork);
+	}
+}
+
+#[test]
+fn cold_storage_reads_are_repriced_in_berlin() {
+	// PUSH1 0 SLOAD STOP
+	let code = hex!("60005400");
+	let used_gas = |hardfork| execute_with_hardfork(hardfork, &code).used_gas;
+
+	// Intrinsic cost, PUSH1 and SLOAD (EIP-1884 in Istanbul, a cold slot under EIP-2929 after).
+	assert_eq!(used_gas(Hardfork::Istanbul), U256::from(21_000 + 3 + 800));
+	assert_eq!(used_gas(Hardfork::Berlin), U256::from(21_000 + 3 + 2_100));
+	assert_eq!(used_gas(Hardfork::London), U256::from(21_000 + 3 + 2_100));
+}
+
+#[test]
+fn the_hardfork_applies_to_dispatched_calls() {
+	new_test_ext().execute_with(|| {
+		pallet_evm::AccountCodes::<Test>::insert(CONTRACT, vec![0x48, 0x00]);
+		let call = |hardfork| {
+			assert_ok!(EvmHardfork::set_hardfork(Origin::root(), hardfork));
+			assert_ok!(EVM::call(
+				Origin::root(),
+				CALLER,
+				CONTRACT,
+				vec![],
+				U256::zero(),
+				1_000_000,
+				U256::zero(),
+				None,
+				None,
+				vec![],
+			));
+			System::events().pop().expect("an event was deposited").event
+		};
+
+		assert_eq!(
+			call(Hardfork::Berlin),
+			pallet_evm::Event::<Test>::ExecutedFailed(CONTRACT).into()
+		);
+		assert_eq!(call(Hardfork::London), pallet_evm::Event::<Test>::Executed(CONTRACT).into());
+	});
+}
+
+/// Init code deploying an empty contract: PUSH1 0 PUSH1 0 RETURN
+const INIT_CODE: [u8; 5] = hex!("60006000f3");
+
+fn create(source: H160) -> DispatchResultWithPostInfo {
+	EVM::create(
+		Origin::root(),
+		source,
+		INIT_CODE.to_vec(),
+		U256::zero(),
+		1_000_000,
+		U256::zero(),
+		None,
+		None,
+		vec![],
+	)
+}
+
+fn create2(source: H160) -> DispatchResultWithPostInfo {
+	EVM::create2(
+		Origin::root(),
+		source,
+		INIT_CODE.to_vec(),
+		H256::zero(),
+		U256::zero(),
+		1_000_000,
+		U256::zero(),
+		None,
+		None,
+		vec![],
+	)
+}
+
+fn last_event() -> Event {
+	System::events().pop().expect("an event was deposited").event
+}
+
+fn assert_created() {
+	let event = last_event();
+	assert!(matches!(event, Event::EVM(pallet_evm::Event::Created(_))), "{:?}", event);
+}
+
+fn assert_rejected(result: DispatchResultWithPostInfo) {
+	assert_eq!(result.map_err(|e| e.error), Err(AllowlistError::<Test>::DeployerNotAllowed.into()));
+}
+
+#[test]
+fn deployment_is_open_by_default() {
+	new_test_ext().execute_with(|| {
+		assert!(!DeployerAllowlist::enforced());
+		assert_ok!(create(CALLER));
+		assert_created();
+		assert_ok!(create2(CALLER));
+		assert_created();
+	});
+}
+
+#[test]
+fn only_the_allowlist_origin_manages_deployers() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			DeployerAllowlist::add_deployer(Origin::signed(CALLER), CALLER),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			DeployerAllowlist::set_enforced(Origin::signed(CALLER), true),
+			DispatchError::BadOrigin
+		);
+
+		assert_ok!(DeployerAllowlist::add_deployer(Origin::root(), CALLER));
+		assert_eq!(last_event(), AllowlistEvent::<Test>::DeployerAdded(CALLER).into());
+		assert_noop!(
+			DeployerAllowlist::add_deployer(Origin::root(), CALLER),
+			AllowlistError::<Test>::AlreadyAllowed
+		);
+
+		assert_noop!(
+			DeployerAllowlist::remove_deployer(Origin::signed(CALLER), CALLER),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(DeployerAllowlist::remove_deployer(Origin::root(), CALLER));
+		assert_eq!(last_event(), AllowlistEvent::<Test>::DeployerRemoved(CALLER).into());
+		assert_noop!(
+			DeployerAllowlist::remove_deployer(Origin::root(), CALLER),
+			AllowlistError::<Test>::NotAllowed
+		);
+	});
+}
+
+#[test]
+fn the_allowlist_restricts_create_and_create2() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(DeployerAllowlist::set_enforced(Origin::root(), true));
+		assert_eq!(last_event(), AllowlistEvent::<Test>::EnforcementSet(true).into());
+
+		assert_rejected(create(CALLER));
+		assert_rejected(create2(CALLER));
+
+		assert_ok!(DeployerAllowlist::add_deployer(Origin::root(), CALLER));
+		assert_ok!(create(CALLER));
+		assert_created();
+		assert_ok!(create2(CALLER));
+		assert_created();
+
+		assert_ok!(DeployerAllowlist::remove_deployer(Origin::root(), CALLER));
+		assert_rejected(create(CALLER));
+
+		assert_ok!(DeployerAllowlist::set_enforced(Origin::root(), false));
+		assert_ok!(create(CALLER));
+		assert_created();
+	});
+}
+
+#[test]
+fn the_allowlist_does_not_restrict_calls() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(DeployerAllowlist::set_enforced(Origin::root(), true));
+		pallet_evm::AccountCodes::<Test>::insert(CONTRACT, vec![0x00]);
+
+		assert_ok!(EVM::call(
+			Origin::root(),
+			CALLER,
+			CONTRACT,
+			vec![],
+			U256::zero(),
+			1_000_000,
+			U256::zero(),
+			None,
+			None,
+			vec![],
+		));
+		assert_eq!(last_event(), pallet_evm::Event::<Test>::Executed(CONTRACT).into());
+	});
+}
+
+/// A key and the address it signs Ethereum transactions for.
+fn ethereum_key() -> (libsecp256k1::SecretKey, H160) {
+	let secret = libsecp256k1::SecretKey::parse(&[0x42; 32]).unwrap();
+	let public = libsecp256k1::PublicKey::from_secret_key(&secret).serialize();
+	(secret, H160::from_slice(&keccak_256(&public[1..])[12..]))
+}
+
+/// An Ethereum transaction with nonce 0, signed with `secret`.
+fn ethereum_transaction(
+	secret: &libsecp256k1::SecretKey,
+	action: TransactionAction,
+	input: Vec<u8>,
+) -> pallet_ethereum::Transaction {
+	let message = ethereum::LegacyTransactionMessage {
+		nonce: U256::zero(),
+		gas_price: U256::zero(),
+		gas_limit: 1_000_000.into(),
+		action,
+		value: U256::zero(),
+		input,
+		chain_id: Some(1),
+	};
+	let hash = libsecp256k1::Message::parse(message.hash().as_fixed_bytes());
+	let (signature, recovery_id) = libsecp256k1::sign(&hash, secret);
+	let signature = signature.serialize();
+	let signature = ethereum::TransactionSignature::new(
+		37 + recovery_id.serialize() as u64,
+		H256::from_slice(&signature[..32]),
+		H256::from_slice(&signature[32..]),
+	)
+	.unwrap();
+	pallet_ethereum::Transaction::Legacy(ethereum::LegacyTransaction {
+		nonce: message.nonce,
+		gas_price: message.gas_price,
+		gas_limit: message.gas_limit,
+		action: message.action,
+		value: message.value,
+		input: message.input,
+		signature,
+	})
+}
+
+fn transact(source: H160, transaction: pallet_ethereum::Transaction) -> DispatchResultWithPostInfo {
+	Ethereum::transact(pallet_ethereum::RawOrigin::EthereumTransaction(source).into(), transaction)
+}
+
+#[test]
+fn the_allowlist_restricts_ethereum_creates() {
+	new_test_ext().execute_with(|| {
+		let (secret, source) = ethereum_key();
+		let create = ethereum_transaction(&secret, TransactionAction::Create, INIT_CODE.to_vec());
+		let call = ethereum_transaction(&secret, TransactionAction::Call(CONTRACT), vec![]);
+		assert_ok!(DeployerAllowlist::set_enforced(Origin::root(), true));
+
+		// Kept out of the pool, where they would not pay for failing.
+		assert_eq!(
+			DeployerAllowlist::validate_transaction(&source, &create),
+			Err(InvalidTransaction::Call.into())
+		);
+		assert_ok!(DeployerAllowlist::validate_transaction(&source, &call));
+		// And still turned away if they reach a block.
+		assert!(transact(source, create.clone()).is_err());
+		assert_eq!(last_event(), AllowlistEvent::<Test>::EnforcementSet(true).into());
+
+		assert_ok!(DeployerAllowlist::add_deployer(Origin::root(), source));
+		assert_ok!(DeployerAllowlist::validate_transaction(&source, &create));
+		assert_ok!(transact(source, create));
+		let event = last_event();
+		assert!(
+			matches!(
+				event,
+				Event::Ethereum(pallet_ethereum::Event::Executed(from, _, _, ExitReason::Succeed(_)))
+					if from == source
+			),
+			"{:?}",
+			event
+		);
+	});
+}
+
+fn account(address: H160) -> H160 {
+	TestAddressMapping::into_account_id(address)
+}
+
+/// Deploys a contract from `CALLER` through the runner, with `slots` storage entries and a
+/// balance, and commits it to the backend. Addresses are hashed, so the reaper only finds the
+/// contract through what the runner recorded.
+fn new_ext_with_contract(slots: u8) -> (frame_support::sp_io::TestExternalities, H160) {
+	let mut ext = new_test_ext();
+	let contract = ext.execute_with(|| {
+		HashAddresses::set(&true);
+		let _ = Balances::deposit_creating(&account(CALLER), 100);
+
+		let mut init = Vec::new();
+		for slot in 0..slots {
+			// PUSH1 1 PUSH1 slot SSTORE
+			init.extend_from_slice(&[0x60, 0x01, 0x60, slot, 0x55]);
+		}
+		// PUSH1 1 PUSH1 0 RETURN, deploying a single STOP
+		init.extend_from_slice(&hex!("60016000f3"));
+		let info = <Test as pallet_evm::Config>::Runner::create(
+			CALLER,
+			init,
+			U256::from(10),
+			1_000_000,
+			None,
+			None,
+			None,
+			vec![],
+			false,
+			<Test as pallet_evm::Config>::config(),
+		)
+		.expect("create passes validation");
+		assert_eq!(info.exit_reason, ExitReason::Succeed(ExitSucceed::Returned));
+		info.value
+	});
+	// Storage removal is only bounded for entries that made it to the backend.
+	ext.commit_all().unwrap();
+	(ext, contract)
+}
+
+fn kill(who: H160) {
+	let _ = Balances::make_free_balance_be(&who, 0);
+	assert!(!System::account_exists(&who));
+}
+
+fn has_evm_state(address: H160) -> bool {
+	pallet_evm::AccountCodes::<Test>::contains_key(address) ||
+		pallet_evm::AccountStorages::<Test>::iter_prefix(address).next().is_some()
+}
+
+#[test]
+fn only_addresses_with_evm_state_are_recorded() {
+	let (mut ext, contract) = new_ext_with_contract(1);
+	ext.execute_with(|| {
+		assert_eq!(EvmReaper::address_of(account(contract)), Some(contract));
+		assert_eq!(EvmReaper::address_of(account(CALLER)), None);
+	});
+}
+
+#[test]
+fn killed_accounts_lose_their_evm_state_when_idle() {
+	let (mut ext, contract) = new_ext_with_contract(3);
+	ext.execute_with(|| {
+		kill(account(contract));
+		// Nothing is removed until there is weight to spare.
+		assert!(has_evm_state(contract));
+		assert_eq!(EvmReaper::pending(contract), Some(account(contract)));
+		assert_eq!(EvmReaper::address_of(account(contract)), None);
+
+		EvmReaper::on_idle(1, Weight::MAX);
+		assert!(!has_evm_state(contract));
+		assert_eq!(EvmReaper::pending(contract), None);
+		assert_eq!(last_event(), pallet_evm_reaper::Event::<Test>::Reaped(contract).into());
+	});
+}
+
+#[test]
+fn reaping_is_bounded_by_the_remaining_weight() {
+	let (mut ext, contract) = new_ext_with_contract(5);
+	ext.execute_with(|| {
+		kill(account(contract));
+		let storage = || pallet_evm::AccountStorages::<Test>::iter_prefix(contract).count();
+
+		// Looking up the queue and its account takes 2 reads, removing the code and the queue
+		// entry 2 writes, leaving 3 writes for storage.
+		assert_eq!(EvmReaper::on_idle(1, 7), 2 + 3);
+		assert_eq!(storage(), 2);
+		assert!(pallet_evm::AccountCodes::<Test>::contains_key(contract));
+
+		// Not enough to look at the queue.
+		assert_eq!(EvmReaper::on_idle(1, 3), 0);
+		assert_eq!(storage(), 2);
+
+		// The rest of the storage and the code, then finding the queue empty.
+		assert_eq!(EvmReaper::on_idle(1, 1_000), 2 + 2 + 2 + 2);
+		assert!(!has_evm_state(contract));
+	});
+}
+
+#[test]
+fn revived_accounts_keep_their_evm_state() {
+	let (mut ext, contract) = new_ext_with_contract(3);
+	ext.execute_with(|| {
+		kill(account(contract));
+		let _ = Balances::deposit_creating(&account(contract), 10);
+
+		EvmReaper::on_idle(1, Weight::MAX);
+		assert!(has_evm_state(contract));
+		assert_eq!(pallet_evm::AccountStorages::<Test>::iter_prefix(contract).count(), 3);
+		assert_eq!(EvmReaper::pending(contract), None);
+		// Recorded again, for the next time it is killed.
+		assert_eq!(EvmReaper::address_of(account(contract)), Some(contract));
+	});
+}
+
+#[test]
+fn accounts_without_evm_state_are_not_queued() {
+	new_test_ext().execute_with(|| {
+		let _ = Balances::deposit_creating(&CALLER, 10);
+		kill(CALLER);
+		assert_eq!(EvmReaper::pending(CALLER), None);
+		assert_eq!(EvmReaper::on_idle(1, Weight::MAX), 2);
+	});
+}
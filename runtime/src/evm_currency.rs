@@ -0,0 +1,201 @@
+//! Bridging between native balances and EVM wei.
+//!
+//! The native token has [`TOKEN_DECIMALS`] decimals while EVM tooling assumes [`EVM_DECIMALS`],
+//! so one unit of [`Balance`] is worth [`WEI_PER_BALANCE`] wei. Everything the EVM sees goes
+//! through [`EvmCurrency`] and [`EvmFeeAdapter`]:
+//!
+//! - balances are reported in wei, so `BALANCE`, `eth_getBalance` and the runner's funds checks
+//!   agree with what wallets expect;
+//! - value transfers (`msg.value`, `withdraw`) must be a whole number of native units, amounts with
+//!   dust below [`WEI_PER_BALANCE`] are rejected rather than silently rounded;
+//! - fees are rounded up when withdrawn and refunds rounded down, so rounding never mints funds.
+//!
+//! Accounts created by the EVM are ordinary `pallet_balances` accounts: a transfer that would
+//! leave a fresh account below the existential deposit fails like any other transfer.
+
+use crate::{AccountId, Balance, Balances, Runtime, TOKEN_DECIMALS};
+use frame_support::traits::{Currency, ExistenceRequirement, SignedImbalance, WithdrawReasons};
+use pallet_evm::{EVMCurrencyAdapter, OnChargeEVMTransaction};
+use sp_core::{H160, U256};
+use sp_runtime::{DispatchError, DispatchResult};
+
+/// Number of decimals the EVM uses for the native token.
+pub const EVM_DECIMALS: u8 = 18;
+
+/// Number of wei one unit of [`Balance`] is worth.
+pub const WEI_PER_BALANCE: Balance = 10u128.pow((EVM_DECIMALS - TOKEN_DECIMALS) as u32);
+
+/// Error returned for value transfers that are not a whole number of native units.
+pub const DUST_ERROR: DispatchError =
+	DispatchError::Other("EVM value is not a multiple of WEI_PER_BALANCE");
+
+/// Convert a native balance to wei.
+pub fn balance_to_wei(balance: Balance) -> U256 {
+	U256::from(balance) * U256::from(WEI_PER_BALANCE)
+}
+
+/// Convert wei to a native balance, rounding down and saturating at `Balance::MAX`.
+pub fn wei_to_balance_floor(wei: U256) -> Balance {
+	saturated(wei / U256::from(WEI_PER_BALANCE))
+}
+
+/// Convert wei to a native balance, rounding up and saturating at `Balance::MAX`.
+pub fn wei_to_balance_ceil(wei: U256) -> Balance {
+	let (balance, dust) = wei.div_mod(U256::from(WEI_PER_BALANCE));
+	saturated(balance).saturating_add(if dust.is_zero() { 0 } else { 1 })
+}
+
+/// Convert wei to a native balance if it is a whole number of native units.
+pub fn wei_to_balance_exact(wei: U256) -> Option<Balance> {
+	let (balance, dust) = wei.div_mod(U256::from(WEI_PER_BALANCE));
+	if dust.is_zero() {
+		balance.try_into().ok()
+	} else {
+		None
+	}
+}
+
+fn saturated(value: U256) -> Balance {
+	value.try_into().unwrap_or(Balance::MAX)
+}
+
+/// Wei amounts as seen by `pallet_evm`, saturating at `u128::MAX` wei.
+fn to_wei(balance: Balance) -> Balance {
+	balance.saturating_mul(WEI_PER_BALANCE)
+}
+
+fn floor(wei: Balance) -> Balance {
+	wei / WEI_PER_BALANCE
+}
+
+fn ceil(wei: Balance) -> Balance {
+	wei_to_balance_ceil(wei.into())
+}
+
+fn exact(wei: Balance) -> Result<Balance, DispatchError> {
+	wei_to_balance_exact(wei.into()).ok_or(DUST_ERROR)
+}
+
+/// `pallet_balances` denominated in wei, used as `pallet_evm::Config::Currency`.
+///
+/// Amounts going in and out are in wei, but the imbalances are those of `pallet_balances` and
+/// hold native units. The EVM only drops them, other code should use `Balances` directly.
+pub struct EvmCurrency;
+
+impl Currency<AccountId> for EvmCurrency {
+	type Balance = Balance;
+	type PositiveImbalance = <Balances as Currency<AccountId>>::PositiveImbalance;
+	type NegativeImbalance = <Balances as Currency<AccountId>>::NegativeImbalance;
+
+	fn total_balance(who: &AccountId) -> Balance {
+		to_wei(Balances::total_balance(who))
+	}
+
+	fn can_slash(who: &AccountId, value: Balance) -> bool {
+		Balances::can_slash(who, ceil(value))
+	}
+
+	fn total_issuance() -> Balance {
+		to_wei(Balances::total_issuance())
+	}
+
+	fn minimum_balance() -> Balance {
+		to_wei(Balances::minimum_balance())
+	}
+
+	fn burn(amount: Balance) -> Self::PositiveImbalance {
+		Balances::burn(floor(amount))
+	}
+
+	fn issue(amount: Balance) -> Self::NegativeImbalance {
+		Balances::issue(floor(amount))
+	}
+
+	fn free_balance(who: &AccountId) -> Balance {
+		to_wei(Balances::free_balance(who))
+	}
+
+	fn ensure_can_withdraw(
+		who: &AccountId,
+		amount: Balance,
+		reasons: WithdrawReasons,
+		new_balance: Balance,
+	) -> DispatchResult {
+		Balances::ensure_can_withdraw(who, ceil(amount), reasons, floor(new_balance))
+	}
+
+	fn transfer(
+		source: &AccountId,
+		dest: &AccountId,
+		value: Balance,
+		existence_requirement: ExistenceRequirement,
+	) -> DispatchResult {
+		Balances::transfer(source, dest, exact(value)?, existence_requirement)
+	}
+
+	fn slash(who: &AccountId, value: Balance) -> (Self::NegativeImbalance, Balance) {
+		let (imbalance, remaining) = Balances::slash(who, ceil(value));
+		(imbalance, to_wei(remaining))
+	}
+
+	fn deposit_into_existing(
+		who: &AccountId,
+		value: Balance,
+	) -> Result<Self::PositiveImbalance, DispatchError> {
+		Balances::deposit_into_existing(who, floor(value))
+	}
+
+	fn deposit_creating(who: &AccountId, value: Balance) -> Self::PositiveImbalance {
+		Balances::deposit_creating(who, floor(value))
+	}
+
+	fn withdraw(
+		who: &AccountId,
+		value: Balance,
+		reasons: WithdrawReasons,
+		liveness: ExistenceRequirement,
+	) -> Result<Self::NegativeImbalance, DispatchError> {
+		Balances::withdraw(who, ceil(value), reasons, liveness)
+	}
+
+	fn make_free_balance_be(
+		who: &AccountId,
+		balance: Balance,
+	) -> SignedImbalance<Balance, Self::PositiveImbalance> {
+		Balances::make_free_balance_be(who, floor(balance))
+	}
+}
+
+/// Charges EVM fees in native units: the withdrawn fee is rounded up, the refund of unused gas
+/// rounded down, and the priority fee paid to the author rounded down.
+pub struct EvmFeeAdapter;
+
+type NativeAdapter = EVMCurrencyAdapter<Balances, ()>;
+
+impl OnChargeEVMTransaction<Runtime> for EvmFeeAdapter {
+	type LiquidityInfo = <NativeAdapter as OnChargeEVMTransaction<Runtime>>::LiquidityInfo;
+
+	fn withdraw_fee(
+		who: &H160,
+		fee: U256,
+	) -> Result<Self::LiquidityInfo, pallet_evm::Error<Runtime>> {
+		NativeAdapter::withdraw_fee(who, wei_to_balance_ceil(fee).into())
+	}
+
+	fn correct_and_deposit_fee(
+		who: &H160,
+		corrected_fee: U256,
+		already_withdrawn: Self::LiquidityInfo,
+	) {
+		// Charging the corrected fee rounded up refunds the difference rounded down.
+		NativeAdapter::correct_and_deposit_fee(
+			who,
+			wei_to_balance_ceil(corrected_fee).into(),
+			already_withdrawn,
+		)
+	}
+
+	fn pay_priority_fee(tip: U256) {
+		NativeAdapter::pay_priority_fee(wei_to_balance_floor(tip).into())
+	}
+}
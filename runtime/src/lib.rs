@@ -6,19 +6,28 @@
 #[cfg(feature = "std")]
 include!(concat!(env!("OUT_DIR"), "/wasm_binary.rs"));
 
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::traits::FindAuthor;
+use pallet_evm::{BlockHashMapping, HashedAddressMapping};
 use pallet_grandpa::{
 	fg_primitives, AuthorityId as GrandpaId, AuthorityList as GrandpaAuthorityList,
 };
+use smallvec::smallvec;
 use sp_api::impl_runtime_apis;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
-use sp_core::{crypto::KeyTypeId, OpaqueMetadata};
+use sp_core::{crypto::KeyTypeId, OpaqueMetadata, H160, H256, U256};
 use sp_runtime::{
 	create_runtime_str, generic, impl_opaque_keys,
-	traits::{AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount, NumberFor, Verify},
-	transaction_validity::{TransactionSource, TransactionValidity},
-	ApplyExtrinsicResult, MultiSignature,
+	traits::{
+		AccountIdLookup, BlakeTwo256, Block as BlockT, Convert, Dispatchable, IdentifyAccount,
+		NumberFor, PostDispatchInfoOf, UniqueSaturatedInto, Verify,
+	},
+	transaction_validity::{
+		InvalidTransaction, TransactionSource, TransactionValidity, TransactionValidityError,
+	},
+	ApplyExtrinsicResult, ConsensusEngineId, MultiSignature,
 };
-use sp_std::prelude::*;
+use sp_std::{marker::PhantomData, prelude::*};
 #[cfg(feature = "std")]
 use sp_version::NativeVersion;
 use sp_version::RuntimeVersion;
@@ -27,25 +36,35 @@ use sp_version::RuntimeVersion;
 pub use frame_support::{
 	construct_runtime, parameter_types,
 	traits::{
-		ConstU128, ConstU32, ConstU64, ConstU8, KeyOwnerProofSystem, Randomness, StorageInfo,
+		ConstBool, ConstU128, ConstU16, ConstU32, ConstU64, ConstU8, Contains, EnsureOneOf,
+		EqualPrivilegeOnly, InstanceFilter, KeyOwnerProofSystem, Randomness, StorageInfo,
 	},
 	weights::{
 		constants::{BlockExecutionWeight, ExtrinsicBaseWeight, RocksDbWeight, WEIGHT_PER_SECOND},
-		IdentityFee, Weight,
+		ConstantMultiplier, DispatchClass, IdentityFee, Weight, WeightToFeeCoefficient,
+		WeightToFeeCoefficients, WeightToFeePolynomial,
 	},
 	StorageValue,
 };
 pub use frame_system::Call as SystemCall;
+use frame_system::EnsureRoot;
 pub use pallet_balances::Call as BalancesCall;
+pub use pallet_ethereum::Call as EthereumCall;
+pub use pallet_evm::Call as EVMCall;
+pub use pallet_mypallet::Call as MyPalletCall;
+pub use pallet_sudo::Call as SudoCall;
 pub use pallet_timestamp::Call as TimestampCall;
-use pallet_transaction_payment::CurrencyAdapter;
+use pallet_transaction_payment::{CurrencyAdapter, Multiplier, TargetedFeeAdjustment};
 #[cfg(any(feature = "std", test))]
 pub use sp_runtime::BuildStorage;
-pub use sp_runtime::{Perbill, Permill};
+pub use sp_runtime::{FixedPointNumber, Perbill, Permill, Perquintill};
 
 /// Import the template pallet.
 pub use pallet_template;
 
+pub mod evm_currency;
+pub mod migrations;
+
 /// An index to a block.
 pub type BlockNumber = u32;
 
@@ -99,12 +118,13 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	// The version of the runtime specification. A full node will not attempt to use its native
 	//   runtime in substitute for the on-chain Wasm runtime unless all of `spec_name`,
 	//   `spec_version`, and `authoring_version` are the same between Wasm and native.
-	// This value is set to 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
-	//   the compatible custom types.
-	spec_version: 100,
+	// This value was set to 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
+	//   the compatible custom types. Bump it with every release, or `Migrations` won't run.
+	spec_version: 101,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
-	transaction_version: 1,
+	// Bumped whenever the encoding of calls changes, e.g. when pallets or calls are reindexed.
+	transaction_version: 2,
 	state_version: 1,
 };
 
@@ -125,6 +145,28 @@ pub const MINUTES: BlockNumber = 60_000 / (MILLISECS_PER_BLOCK as BlockNumber);
 pub const HOURS: BlockNumber = MINUTES * 60;
 pub const DAYS: BlockNumber = HOURS * 24;
 
+/// Symbol of the native token, as shown by wallets.
+pub const TOKEN_SYMBOL: &str = "UNIT";
+/// Number of decimals of the native token.
+pub const TOKEN_DECIMALS: u8 = 12;
+/// Address format of Substrate accounts, see <https://github.com/paritytech/ss58-registry>.
+pub const SS58_PREFIX: u16 = 7777;
+/// EIP-155 chain id used to sign Ethereum transactions.
+pub const EVM_CHAIN_ID: u64 = 42;
+
+// The native token has `TOKEN_DECIMALS` decimals.
+pub const UNIT: Balance = 1_000_000_000_000;
+pub const MILLIUNIT: Balance = 1_000_000_000;
+pub const MICROUNIT: Balance = 1_000_000;
+
+/// The minimum balance of an account, including accounts created by the EVM.
+pub const EXISTENTIAL_DEPOSIT: Balance = MICROUNIT;
+
+/// Deposit charged for keeping `items` storage entries of `bytes` total size on chain.
+pub const fn deposit(items: u32, bytes: u32) -> Balance {
+	items as Balance * 100 * MILLIUNIT + (bytes as Balance) * 100 * MICROUNIT
+}
+
 /// The version information used to identify this runtime when compiled natively.
 #[cfg(feature = "std")]
 pub fn native_version() -> NativeVersion {
@@ -141,14 +183,15 @@ parameter_types! {
 		::with_sensible_defaults(2 * WEIGHT_PER_SECOND, NORMAL_DISPATCH_RATIO);
 	pub BlockLength: frame_system::limits::BlockLength = frame_system::limits::BlockLength
 		::max_with_normal_ratio(5 * 1024 * 1024, NORMAL_DISPATCH_RATIO);
-	pub const SS58Prefix: u8 = 42;
+	pub const SS58Prefix: u16 = SS58_PREFIX;
 }
 
 // Configure FRAME pallets to include in runtime.
 
 impl frame_system::Config for Runtime {
-	/// The basic call filter to use in dispatchable.
-	type BaseCallFilter = frame_support::traits::Everything;
+	/// The basic call filter to use in dispatchable. Maintenance mode decides which calls can
+	/// currently be dispatched.
+	type BaseCallFilter = MaintenanceMode;
 	/// Block & extrinsics weights: base values and limits.
 	type BlockWeights = BlockWeights;
 	/// The maximum length of a block (in bytes).
@@ -185,13 +228,13 @@ impl frame_system::Config for Runtime {
 	type PalletInfo = PalletInfo;
 	/// What to do if a new account is created.
 	type OnNewAccount = ();
-	/// What to do if an account is fully reaped from the system.
-	type OnKilledAccount = ();
+	/// What to do if an account is fully reaped from the system. Its EVM state is removed later.
+	type OnKilledAccount = EvmReaper;
 	/// The data to be stored in an account.
 	type AccountData = pallet_balances::AccountData<Balance>;
 	/// Weight information for the extrinsics of this pallet.
 	type SystemWeightInfo = ();
-	/// This is used as an identifier of the chain. 42 is the generic substrate prefix.
+	/// The address format of this chain, see `SS58_PREFIX`.
 	type SS58Prefix = SS58Prefix;
 	/// The set code logic, just the default since we're not a parachain.
 	type OnSetCode = ();
@@ -243,17 +286,46 @@ impl pallet_balances::Config for Runtime {
 	/// The ubiquitous event type.
 	type Event = Event;
 	type DustRemoval = ();
-	type ExistentialDeposit = ConstU128<500>;
+	type ExistentialDeposit = ConstU128<EXISTENTIAL_DEPOSIT>;
 	type AccountStore = System;
 	type WeightInfo = pallet_balances::weights::SubstrateWeight<Runtime>;
 }
 
+/// Maps weight to fee such that an extrinsic of `ExtrinsicBaseWeight` costs exactly
+/// `MILLIUNIT`, scaling linearly from there.
+pub struct WeightToFee;
+impl WeightToFeePolynomial for WeightToFee {
+	type Balance = Balance;
+	fn polynomial() -> WeightToFeeCoefficients<Self::Balance> {
+		let p = MILLIUNIT;
+		let q = Balance::from(ExtrinsicBaseWeight::get());
+		smallvec![WeightToFeeCoefficient {
+			degree: 1,
+			negative: false,
+			coeff_frac: Perbill::from_rational(p % q, q),
+			coeff_integer: p / q,
+		}]
+	}
+}
+
+parameter_types! {
+	pub const TransactionByteFee: Balance = 10 * MICROUNIT;
+	/// The portion of the normal dispatch class that we aim to keep filled. Fees go up when
+	/// blocks are fuller than this and down when they are emptier.
+	pub const TargetBlockFullness: Perquintill = Perquintill::from_percent(25);
+	/// How fast the multiplier reacts to block fullness.
+	pub AdjustmentVariable: Multiplier = Multiplier::saturating_from_rational(3, 100_000);
+	/// The multiplier never drops below this value, so fees stay non-zero on an idle chain.
+	pub MinimumMultiplier: Multiplier = Multiplier::saturating_from_rational(1, 1_000_000u128);
+}
+
 impl pallet_transaction_payment::Config for Runtime {
 	type OnChargeTransaction = CurrencyAdapter<Balances, ()>;
 	type OperationalFeeMultiplier = ConstU8<5>;
-	type WeightToFee = IdentityFee<Balance>;
-	type LengthToFee = IdentityFee<Balance>;
-	type FeeMultiplierUpdate = ();
+	type WeightToFee = WeightToFee;
+	type LengthToFee = ConstantMultiplier<Balance, TransactionByteFee>;
+	type FeeMultiplierUpdate =
+		TargetedFeeAdjustment<Self, TargetBlockFullness, AdjustmentVariable, MinimumMultiplier>;
 }
 
 impl pallet_sudo::Config for Runtime {
@@ -261,11 +333,472 @@ impl pallet_sudo::Config for Runtime {
 	type Call = Call;
 }
 
+parameter_types! {
+	pub MaximumSchedulerWeight: Weight = Perbill::from_percent(80) * BlockWeights::get().max_block;
+	pub const NoPreimagePostponement: Option<BlockNumber> = Some(10);
+}
+
+impl pallet_scheduler::Config for Runtime {
+	type Event = Event;
+	type Origin = Origin;
+	type PalletsOrigin = OriginCaller;
+	type Call = Call;
+	type MaximumWeight = MaximumSchedulerWeight;
+	type ScheduleOrigin = EnsureRoot<AccountId>;
+	type MaxScheduledPerBlock = ConstU32<50>;
+	type WeightInfo = pallet_scheduler::weights::SubstrateWeight<Runtime>;
+	type OriginPrivilegeCmp = EqualPrivilegeOnly;
+	type PreimageProvider = Preimage;
+	type NoPreimagePostponement = NoPreimagePostponement;
+}
+
+parameter_types! {
+	pub const PreimageMaxSize: u32 = 4096 * 1024;
+	pub const PreimageBaseDeposit: Balance = UNIT;
+	pub const PreimageByteDeposit: Balance = 10 * MICROUNIT;
+}
+
+impl pallet_preimage::Config for Runtime {
+	type WeightInfo = pallet_preimage::weights::SubstrateWeight<Runtime>;
+	type Event = Event;
+	type Currency = Balances;
+	type ManagerOrigin = EnsureRoot<AccountId>;
+	type MaxSize = PreimageMaxSize;
+	type BaseDeposit = PreimageBaseDeposit;
+	type ByteDeposit = PreimageByteDeposit;
+}
+
+parameter_types! {
+	pub const CouncilMotionDuration: BlockNumber = 3 * DAYS;
+	pub const CouncilMaxProposals: u32 = 100;
+	pub const CouncilMaxMembers: u32 = 100;
+}
+
+type CouncilCollective = pallet_collective::Instance1;
+impl pallet_collective::Config<CouncilCollective> for Runtime {
+	type Origin = Origin;
+	type Proposal = Call;
+	type Event = Event;
+	type MotionDuration = CouncilMotionDuration;
+	type MaxProposals = CouncilMaxProposals;
+	type MaxMembers = CouncilMaxMembers;
+	type DefaultVote = pallet_collective::PrimeDefaultVote;
+	type WeightInfo = pallet_collective::weights::SubstrateWeight<Runtime>;
+}
+
+parameter_types! {
+	pub const TechnicalMotionDuration: BlockNumber = 3 * DAYS;
+	pub const TechnicalMaxProposals: u32 = 100;
+	pub const TechnicalMaxMembers: u32 = 100;
+}
+
+type TechnicalCollective = pallet_collective::Instance2;
+impl pallet_collective::Config<TechnicalCollective> for Runtime {
+	type Origin = Origin;
+	type Proposal = Call;
+	type Event = Event;
+	type MotionDuration = TechnicalMotionDuration;
+	type MaxProposals = TechnicalMaxProposals;
+	type MaxMembers = TechnicalMaxMembers;
+	type DefaultVote = pallet_collective::PrimeDefaultVote;
+	type WeightInfo = pallet_collective::weights::SubstrateWeight<Runtime>;
+}
+
+/// Root, or more than half of the council. Used for day-to-day privileged operations such as
+/// changing EVM parameters.
+pub type EnsureRootOrHalfCouncil = EnsureOneOf<
+	EnsureRoot<AccountId>,
+	pallet_collective::EnsureProportionMoreThan<AccountId, CouncilCollective, 1, 2>,
+>;
+
+/// Root, or the whole technical committee. Used for emergency operations.
+pub type EnsureRootOrAllTechnicalCommittee = EnsureOneOf<
+	EnsureRoot<AccountId>,
+	pallet_collective::EnsureProportionAtLeast<AccountId, TechnicalCollective, 1, 1>,
+>;
+
+parameter_types! {
+	pub const LaunchPeriod: BlockNumber = 7 * DAYS;
+	pub const VotingPeriod: BlockNumber = 7 * DAYS;
+	pub const FastTrackVotingPeriod: BlockNumber = 3 * HOURS;
+	pub const EnactmentPeriod: BlockNumber = DAYS;
+	pub const CooloffPeriod: BlockNumber = 7 * DAYS;
+	pub const MinimumDeposit: Balance = 100 * UNIT;
+	pub const MaxProposals: u32 = 100;
+}
+
+impl pallet_democracy::Config for Runtime {
+	type Proposal = Call;
+	type Event = Event;
+	type Currency = Balances;
+	type EnactmentPeriod = EnactmentPeriod;
+	type LaunchPeriod = LaunchPeriod;
+	type VotingPeriod = VotingPeriod;
+	type VoteLockingPeriod = EnactmentPeriod;
+	type MinimumDeposit = MinimumDeposit;
+	/// A straight majority of the council can decide what their next motion is.
+	type ExternalOrigin =
+		pallet_collective::EnsureProportionAtLeast<AccountId, CouncilCollective, 1, 2>;
+	/// A super-majority can have the next scheduled referendum be a straight majority-carries vote.
+	type ExternalMajorityOrigin =
+		pallet_collective::EnsureProportionAtLeast<AccountId, CouncilCollective, 3, 4>;
+	/// A unanimous council can have the next scheduled referendum be a straight default-carries
+	/// (NTB) vote.
+	type ExternalDefaultOrigin =
+		pallet_collective::EnsureProportionAtLeast<AccountId, CouncilCollective, 1, 1>;
+	/// Two thirds of the technical committee can have an ExternalMajority/ExternalDefault vote
+	/// be tabled immediately and with a shorter voting/enactment period.
+	type FastTrackOrigin =
+		pallet_collective::EnsureProportionAtLeast<AccountId, TechnicalCollective, 2, 3>;
+	type InstantOrigin =
+		pallet_collective::EnsureProportionAtLeast<AccountId, TechnicalCollective, 1, 1>;
+	type InstantAllowed = ConstBool<true>;
+	type FastTrackVotingPeriod = FastTrackVotingPeriod;
+	// To cancel a proposal which has been passed, 2/3 of the council must agree to it.
+	type CancellationOrigin =
+		pallet_collective::EnsureProportionAtLeast<AccountId, CouncilCollective, 2, 3>;
+	// To cancel a proposal before it has been passed, the technical committee must be unanimous or
+	// Root must agree.
+	type CancelProposalOrigin = EnsureRootOrAllTechnicalCommittee;
+	type BlacklistOrigin = EnsureRoot<AccountId>;
+	// Any single technical committee member may veto a coming council proposal, however they can
+	// only do it once and it lasts only for the cool-off period.
+	type VetoOrigin = pallet_collective::EnsureMember<AccountId, TechnicalCollective>;
+	type CooloffPeriod = CooloffPeriod;
+	type PreimageByteDeposit = PreimageByteDeposit;
+	type OperationalPreimageOrigin = pallet_collective::EnsureMember<AccountId, CouncilCollective>;
+	type Slash = ();
+	type Scheduler = Scheduler;
+	type PalletsOrigin = OriginCaller;
+	type MaxVotes = ConstU32<100>;
+	type WeightInfo = pallet_democracy::weights::SubstrateWeight<Runtime>;
+	type MaxProposals = MaxProposals;
+}
+
+impl pallet_utility::Config for Runtime {
+	type Event = Event;
+	type Call = Call;
+	type PalletsOrigin = OriginCaller;
+	type WeightInfo = pallet_utility::weights::SubstrateWeight<Runtime>;
+}
+
+parameter_types! {
+	// One storage item; key size is 32; value is size 4+4+16+32 bytes = 56 bytes.
+	pub const DepositBase: Balance = deposit(1, 88);
+	// Additional storage item size of 32 bytes.
+	pub const DepositFactor: Balance = deposit(0, 32);
+}
+
+impl pallet_multisig::Config for Runtime {
+	type Event = Event;
+	type Call = Call;
+	type Currency = Balances;
+	type DepositBase = DepositBase;
+	type DepositFactor = DepositFactor;
+	type MaxSignatories = ConstU16<100>;
+	type WeightInfo = pallet_multisig::weights::SubstrateWeight<Runtime>;
+}
+
+parameter_types! {
+	// One storage item; key size 32, value size 8.
+	pub const ProxyDepositBase: Balance = deposit(1, 8);
+	// Additional storage item size of 33 bytes.
+	pub const ProxyDepositFactor: Balance = deposit(0, 33);
+	pub const AnnouncementDepositBase: Balance = deposit(1, 8);
+	pub const AnnouncementDepositFactor: Balance = deposit(0, 66);
+}
+
+/// The kinds of delegation a proxy account can be given.
+#[derive(
+	Copy,
+	Clone,
+	Eq,
+	PartialEq,
+	Ord,
+	PartialOrd,
+	Encode,
+	Decode,
+	RuntimeDebug,
+	MaxEncodedLen,
+	scale_info::TypeInfo,
+)]
+pub enum ProxyType {
+	/// Anything the proxied account could do itself.
+	Any,
+	/// Everything except moving funds, e.g. for validator operations.
+	///
+	/// Calls that can move funds must be excluded from it as they are added to the runtime.
+	NonTransfer,
+	/// Only governance: democracy, council, technical committee and preimages.
+	Governance,
+	/// Only EVM calls, creates and withdrawals.
+	EvmOnly,
+}
+
+impl Default for ProxyType {
+	fn default() -> Self {
+		Self::Any
+	}
+}
+
+impl InstanceFilter<Call> for ProxyType {
+	fn filter(&self, c: &Call) -> bool {
+		match self {
+			ProxyType::Any => true,
+			// EVM calls can carry value and linking moves the linked address' balance, so they are
+			// excluded along with plain transfers.
+			ProxyType::NonTransfer => !matches!(
+				c,
+				Call::Balances(..) | Call::EVM(..) | Call::Ethereum(..) | Call::AccountLink(..)
+			),
+			ProxyType::Governance => matches!(
+				c,
+				Call::Democracy(..) |
+					Call::Council(..) | Call::TechnicalCommittee(..) |
+					Call::Preimage(..) | Call::Utility(..)
+			),
+			ProxyType::EvmOnly => matches!(c, Call::EVM(..) | Call::Utility(..)),
+		}
+	}
+
+	fn is_superset(&self, o: &Self) -> bool {
+		match (self, o) {
+			(x, y) if x == y => true,
+			(ProxyType::Any, _) => true,
+			(_, ProxyType::Any) => false,
+			(ProxyType::NonTransfer, ProxyType::Governance) => true,
+			_ => false,
+		}
+	}
+}
+
+impl pallet_proxy::Config for Runtime {
+	type Event = Event;
+	type Call = Call;
+	type Currency = Balances;
+	type ProxyType = ProxyType;
+	type ProxyDepositBase = ProxyDepositBase;
+	type ProxyDepositFactor = ProxyDepositFactor;
+	type MaxProxies = ConstU32<32>;
+	type WeightInfo = pallet_proxy::weights::SubstrateWeight<Runtime>;
+	type MaxPending = ConstU32<32>;
+	type CallHasher = BlakeTwo256;
+	type AnnouncementDepositBase = AnnouncementDepositBase;
+	type AnnouncementDepositFactor = AnnouncementDepositFactor;
+}
+
+/// EVM gas executed per second of weight.
+///
+/// Not yet calibrated for this runtime: 40M is the figure Moonbeam benchmarked for EVM execution
+/// in compiled Wasm on a 4.4 GHz CPU, which other Frontier chains reuse. It makes a unit of gas
+/// weigh 25_000. Calibrate it on the reference hardware with
+///
+/// ```text
+/// cargo build --release --features runtime-benchmarks
+/// ./target/release/node-template benchmark overhead --dev --workload evm-transfer
+/// ./target/release/node-template benchmark overhead --dev --workload erc20-transfer
+/// ```
+///
+/// For each workload, `GAS_PER_SECOND` must not exceed the gas a transfer uses times
+/// `WEIGHT_PER_SECOND`, divided by the measured per-extrinsic weight minus
+/// `ExtrinsicBaseWeight`. Take the lower of the two and record the hardware, both weights and the
+/// result here.
+///
+/// Measured: not yet.
+pub const GAS_PER_SECOND: u64 = 40_000_000;
+
+/// Weight charged per unit of EVM gas.
+pub const WEIGHT_PER_GAS: Weight = WEIGHT_PER_SECOND / GAS_PER_SECOND;
+
+/// Charges [`WEIGHT_PER_GAS`] for every unit of gas.
+pub struct FixedGasWeightMapping;
+impl pallet_evm::GasWeightMapping for FixedGasWeightMapping {
+	fn gas_to_weight(gas: u64) -> Weight {
+		gas.saturating_mul(WEIGHT_PER_GAS)
+	}
+
+	fn weight_to_gas(weight: Weight) -> u64 {
+		weight / WEIGHT_PER_GAS
+	}
+}
+
+/// The EVM base fee: a unit of gas costs what its [`WEIGHT_PER_GAS`] costs other extrinsics,
+/// including the current fee multiplier, so EVM transactions cannot fill blocks more cheaply than
+/// they could.
+///
+/// It is a whole number of native units, at least one, so EVM fees are charged without rounding
+/// and gas never becomes free however low the multiplier falls.
+pub struct MinGasPrice;
+impl pallet_evm::FeeCalculator for MinGasPrice {
+	fn min_gas_price() -> U256 {
+		let weight_fee =
+			<WeightToFee as frame_support::weights::WeightToFee>::weight_to_fee(&WEIGHT_PER_GAS);
+		evm_currency::balance_to_wei(
+			TransactionPayment::next_fee_multiplier().saturating_mul_int(weight_fee).max(1),
+		)
+	}
+}
+
+parameter_types! {
+	pub const ChainId: u64 = EVM_CHAIN_ID;
+	/// As much gas as fits in the weight a single normal extrinsic may take, which leaves room
+	/// for the base weight of the extrinsic and for block initialization.
+	pub BlockGasLimit: U256 = U256::from(
+		BlockWeights::get()
+			.get(DispatchClass::Normal)
+			.max_extrinsic
+			.expect("normal extrinsics are limited by `with_sensible_defaults`; qed") /
+			WEIGHT_PER_GAS
+	);
+	/// Whether `BLOCKHASH` returns the Ethereum block hashes of `pallet_ethereum` instead of the
+	/// Substrate block hashes. Can be switched with `system.setStorage`.
+	pub storage UseEthereumBlockHashes: bool = false;
+}
+
+/// Reports the current Aura author to the EVM as `COINBASE`: the address its account controls,
+/// see [`pallet_account_link::truncated_address`], so the fees it earns can be spent from it.
+pub struct FindAuthorTruncated<F>(PhantomData<F>);
+impl<F: FindAuthor<u32>> FindAuthor<H160> for FindAuthorTruncated<F> {
+	fn find_author<'a, I>(digests: I) -> Option<H160>
+	where
+		I: 'a + IntoIterator<Item = (ConsensusEngineId, &'a [u8])>,
+	{
+		let author_index = F::find_author(digests)?;
+		let authority = Aura::authorities().get(author_index as usize)?.clone();
+		pallet_account_link::truncated_address(AsRef::<[u8]>::as_ref(&authority))
+	}
+}
+
+/// Answers `BLOCKHASH` from the source selected by `UseEthereumBlockHashes`.
+pub struct ConfigurableBlockHashMapping;
+impl BlockHashMapping for ConfigurableBlockHashMapping {
+	fn block_hash(number: u32) -> H256 {
+		if UseEthereumBlockHashes::get() {
+			pallet_ethereum::EthereumBlockHashMapping::<Runtime>::block_hash(number)
+		} else {
+			pallet_evm::SubstrateBlockHashMapping::<Runtime>::block_hash(number)
+		}
+	}
+}
+
+impl pallet_evm::Config for Runtime {
+	type FeeCalculator = MinGasPrice;
+	type GasWeightMapping = FixedGasWeightMapping;
+	type BlockHashMapping = ConfigurableBlockHashMapping;
+	type CallOrigin = pallet_account_link::EnsureAddressLinked<Self>;
+	type WithdrawOrigin = pallet_account_link::EnsureAddressLinked<Self>;
+	type AddressMapping = pallet_account_link::LinkedAddressMapping<Self>;
+	type Currency = evm_currency::EvmCurrency;
+	type Event = Event;
+	type Runner = pallet_deployer_allowlist::AllowlistRunner<
+		Self,
+		pallet_evm_reaper::RecordingRunner<Self, pallet_evm::runner::stack::Runner<Self>>,
+	>;
+	type PrecompilesType = ();
+	type PrecompilesValue = ();
+	type ChainId = ChainId;
+	type BlockGasLimit = BlockGasLimit;
+	type OnChargeTransaction = evm_currency::EvmFeeAdapter;
+	type FindAuthor = FindAuthorTruncated<Aura>;
+
+	fn config() -> &'static pallet_evm_hardfork::EvmConfig {
+		EvmHardfork::evm_config()
+	}
+}
+
+impl pallet_evm_hardfork::Config for Runtime {
+	type Event = Event;
+	type HardforkOrigin = EnsureRootOrHalfCouncil;
+}
+
+impl pallet_account_link::Config for Runtime {
+	type Event = Event;
+	type Currency = Balances;
+	type FallbackMapping = HashedAddressMapping<BlakeTwo256>;
+	type WeightInfo = pallet_account_link::weights::SubstrateWeight<Runtime>;
+}
+
+/// The EVM address of accounts that linked one. The accounts of other addresses, contracts
+/// included, are `HashedAddressMapping` hashes, which cannot be traced back: `EvmReaper` records
+/// those holding EVM code or storage as the EVM uses them.
+pub struct LinkedEvmAddress;
+impl Convert<AccountId, Option<H160>> for LinkedEvmAddress {
+	fn convert(who: AccountId) -> Option<H160> {
+		AccountLink::evm_address(who)
+	}
+}
+
+impl pallet_evm_reaper::Config for Runtime {
+	type Event = Event;
+	type AddressOf = LinkedEvmAddress;
+}
+
+impl pallet_deployer_allowlist::Config for Runtime {
+	type Event = Event;
+	type AllowlistOrigin = EnsureRootOrHalfCouncil;
+}
+
+impl pallet_ethereum::Config for Runtime {
+	type Event = Event;
+	type StateRoot = pallet_ethereum::IntermediateStateRoot<Self>;
+}
+
+/// Wraps Ethereum transactions received over RPC into extrinsics.
+pub struct TransactionConverter;
+
+impl fp_rpc::ConvertTransaction<UncheckedExtrinsic> for TransactionConverter {
+	fn convert_transaction(&self, transaction: pallet_ethereum::Transaction) -> UncheckedExtrinsic {
+		UncheckedExtrinsic::new_unsigned(
+			pallet_ethereum::Call::<Runtime>::transact { transaction }.into(),
+		)
+	}
+}
+
+impl fp_rpc::ConvertTransaction<opaque::UncheckedExtrinsic> for TransactionConverter {
+	fn convert_transaction(
+		&self,
+		transaction: pallet_ethereum::Transaction,
+	) -> opaque::UncheckedExtrinsic {
+		let extrinsic = UncheckedExtrinsic::new_unsigned(
+			pallet_ethereum::Call::<Runtime>::transact { transaction }.into(),
+		);
+		let encoded = extrinsic.encode();
+		opaque::UncheckedExtrinsic::decode(&mut &encoded[..])
+			.expect("Encoded extrinsic is always valid")
+	}
+}
+
+/// Calls that stay available while in maintenance mode and that can never be paused: governance,
+/// sudo, the timestamp inherent and the maintenance pallet itself.
+pub struct MaintenanceWhitelist;
+impl Contains<Call> for MaintenanceWhitelist {
+	fn contains(call: &Call) -> bool {
+		matches!(
+			call,
+			Call::Timestamp(..) |
+				Call::Sudo(..) | Call::Scheduler(..) |
+				Call::Preimage(..) | Call::Democracy(..) |
+				Call::Council(..) | Call::TechnicalCommittee(..) |
+				Call::MaintenanceMode(..)
+		)
+	}
+}
+
+impl pallet_maintenance_mode::Config for Runtime {
+	type Event = Event;
+	type MaintenanceOrigin = EnsureRootOrAllTechnicalCommittee;
+	type WhitelistedCalls = MaintenanceWhitelist;
+	type RuntimeCall = Call;
+	type MaxPalletNameLen = ConstU32<32>;
+}
+
 /// Configure the pallet-template in pallets/template.
 impl pallet_template::Config for Runtime {
 	type Event = Event;
 }
 
+impl pallet_mypallet::Config for Runtime {}
+
 // Create the runtime by composing the FRAME pallets that were previously configured.
 construct_runtime!(
 	pub enum Runtime where
@@ -273,16 +806,34 @@ construct_runtime!(
 		NodeBlock = opaque::Block,
 		UncheckedExtrinsic = UncheckedExtrinsic
 	{
-		System: frame_system,
-		RandomnessCollectiveFlip: pallet_randomness_collective_flip,
-		Timestamp: pallet_timestamp,
-		Aura: pallet_aura,
-		Grandpa: pallet_grandpa,
-		Balances: pallet_balances,
-		TransactionPayment: pallet_transaction_payment,
-		Sudo: pallet_sudo,
+		// The indices are part of the encoding of calls and events, so they must never change.
+		// New pallets take the next free index.
+		System: frame_system = 0,
+		RandomnessCollectiveFlip: pallet_randomness_collective_flip = 1,
+		Timestamp: pallet_timestamp = 2,
+		Aura: pallet_aura = 3,
+		Grandpa: pallet_grandpa = 4,
+		Balances: pallet_balances = 5,
+		TransactionPayment: pallet_transaction_payment = 6,
+		Sudo: pallet_sudo = 7,
 		// Include the custom logic from the pallet-template in the runtime.
-		TemplateModule: pallet_template,
+		TemplateModule: pallet_template = 8,
+		Scheduler: pallet_scheduler = 9,
+		Preimage: pallet_preimage = 10,
+		Democracy: pallet_democracy = 11,
+		Council: pallet_collective::<Instance1> = 12,
+		TechnicalCommittee: pallet_collective::<Instance2> = 13,
+		Utility: pallet_utility = 14,
+		Multisig: pallet_multisig = 15,
+		Proxy: pallet_proxy = 16,
+		EVM: pallet_evm = 17,
+		Ethereum: pallet_ethereum = 18,
+		EvmHardfork: pallet_evm_hardfork = 19,
+		DeployerAllowlist: pallet_deployer_allowlist = 20,
+		AccountLink: pallet_account_link = 21,
+		EvmReaper: pallet_evm_reaper = 22,
+		MaintenanceMode: pallet_maintenance_mode = 23,
+		MyPallet: pallet_mypallet = 24,
 	}
 );
 
@@ -304,7 +855,10 @@ pub type SignedExtra = (
 	pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
 );
 /// Unchecked extrinsic type as expected by this runtime.
-pub type UncheckedExtrinsic = generic::UncheckedExtrinsic<Address, Call, Signature, SignedExtra>;
+pub type UncheckedExtrinsic =
+	fp_self_contained::UncheckedExtrinsic<Address, Call, Signature, SignedExtra>;
+/// Extrinsic type that has already been checked.
+pub type CheckedExtrinsic = fp_self_contained::CheckedExtrinsic<AccountId, Call, SignedExtra, H160>;
 /// The payload being signed in transactions.
 pub type SignedPayload = generic::SignedPayload<Call, SignedExtra>;
 /// Executive: handles dispatch to the various modules.
@@ -314,8 +868,79 @@ pub type Executive = frame_executive::Executive<
 	frame_system::ChainContext<Runtime>,
 	Runtime,
 	AllPalletsWithSystem,
+	migrations::Migrations,
 >;
 
+/// Ethereum transactions bypass the signed extensions and pay no fee when they fail before
+/// execution, so those that would be turned away at dispatch are rejected up front: while
+/// maintenance mode filters them, and when they deploy a contract from an address the deployer
+/// allowlist does not allow.
+fn check_ethereum_transaction(call: &Call, source: &H160) -> Result<(), TransactionValidityError> {
+	if !MaintenanceMode::contains(call) {
+		return Err(InvalidTransaction::Call.into())
+	}
+	if let Call::Ethereum(pallet_ethereum::Call::transact { transaction }) = call {
+		DeployerAllowlist::validate_transaction(source, transaction)?;
+	}
+	Ok(())
+}
+
+impl fp_self_contained::SelfContainedCall for Call {
+	type SignedInfo = H160;
+
+	fn is_self_contained(&self) -> bool {
+		match self {
+			Call::Ethereum(call) => call.is_self_contained(),
+			_ => false,
+		}
+	}
+
+	fn check_self_contained(&self) -> Option<Result<Self::SignedInfo, TransactionValidityError>> {
+		match self {
+			Call::Ethereum(call) => call.check_self_contained(),
+			_ => None,
+		}
+	}
+
+	fn validate_self_contained(&self, info: &Self::SignedInfo) -> Option<TransactionValidity> {
+		match self {
+			Call::Ethereum(call) => match check_ethereum_transaction(self, info) {
+				Ok(()) => call.validate_self_contained(info),
+				Err(e) => Some(Err(e)),
+			},
+			_ => None,
+		}
+	}
+
+	fn pre_dispatch_self_contained(
+		&self,
+		info: &Self::SignedInfo,
+	) -> Option<Result<(), TransactionValidityError>> {
+		match self {
+			Call::Ethereum(call) => match check_ethereum_transaction(self, info) {
+				Ok(()) => call.pre_dispatch_self_contained(info),
+				Err(e) => Some(Err(e)),
+			},
+			_ => None,
+		}
+	}
+
+	fn apply_self_contained(
+		self,
+		info: Self::SignedInfo,
+	) -> Option<sp_runtime::DispatchResultWithInfo<PostDispatchInfoOf<Self>>> {
+		match self {
+			call @ Call::Ethereum(pallet_ethereum::Call::transact { .. }) => Some(call.dispatch(
+				Origin::from(pallet_ethereum::RawOrigin::EthereumTransaction(info)),
+			)),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests;
+
 #[cfg(feature = "runtime-benchmarks")]
 #[macro_use]
 extern crate frame_benchmarking;
@@ -326,8 +951,16 @@ mod benches {
 		[frame_benchmarking, BaselineBench::<Runtime>]
 		[frame_system, SystemBench::<Runtime>]
 		[pallet_balances, Balances]
+		[pallet_collective, Council]
+		[pallet_democracy, Democracy]
+		[pallet_multisig, Multisig]
+		[pallet_preimage, Preimage]
+		[pallet_proxy, Proxy]
+		[pallet_scheduler, Scheduler]
 		[pallet_timestamp, Timestamp]
+		[pallet_utility, Utility]
 		[pallet_template, TemplateModule]
+		[pallet_account_link, AccountLink]
 	);
 }
 
@@ -462,6 +1095,145 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl fp_rpc::EthereumRuntimeRPCApi<Block> for Runtime {
+		fn chain_id() -> u64 {
+			<Runtime as pallet_evm::Config>::ChainId::get()
+		}
+
+		fn account_basic(address: H160) -> pallet_evm::Account {
+			EVM::account_basic(&address)
+		}
+
+		fn gas_price() -> U256 {
+			<Runtime as pallet_evm::Config>::FeeCalculator::min_gas_price()
+		}
+
+		fn account_code_at(address: H160) -> Vec<u8> {
+			EVM::account_codes(address)
+		}
+
+		fn author() -> H160 {
+			<pallet_evm::Pallet<Runtime>>::find_author()
+		}
+
+		fn storage_at(address: H160, index: U256) -> H256 {
+			let mut tmp = [0u8; 32];
+			index.to_big_endian(&mut tmp);
+			EVM::account_storages(address, H256::from_slice(&tmp[..]))
+		}
+
+		fn call(
+			from: H160,
+			to: H160,
+			data: Vec<u8>,
+			value: U256,
+			gas_limit: U256,
+			max_fee_per_gas: Option<U256>,
+			max_priority_fee_per_gas: Option<U256>,
+			nonce: Option<U256>,
+			estimate: bool,
+			access_list: Option<Vec<(H160, Vec<H256>)>>,
+		) -> Result<pallet_evm::CallInfo, sp_runtime::DispatchError> {
+			let mut config = <Runtime as pallet_evm::Config>::config().clone();
+			config.estimate = estimate;
+
+			<Runtime as pallet_evm::Config>::Runner::call(
+				from,
+				to,
+				data,
+				value,
+				gas_limit.unique_saturated_into(),
+				max_fee_per_gas,
+				max_priority_fee_per_gas,
+				nonce,
+				access_list.unwrap_or_default(),
+				false,
+				&config,
+			)
+			.map_err(Into::into)
+		}
+
+		fn create(
+			from: H160,
+			data: Vec<u8>,
+			value: U256,
+			gas_limit: U256,
+			max_fee_per_gas: Option<U256>,
+			max_priority_fee_per_gas: Option<U256>,
+			nonce: Option<U256>,
+			estimate: bool,
+			access_list: Option<Vec<(H160, Vec<H256>)>>,
+		) -> Result<pallet_evm::CreateInfo, sp_runtime::DispatchError> {
+			let mut config = <Runtime as pallet_evm::Config>::config().clone();
+			config.estimate = estimate;
+
+			<Runtime as pallet_evm::Config>::Runner::create(
+				from,
+				data,
+				value,
+				gas_limit.unique_saturated_into(),
+				max_fee_per_gas,
+				max_priority_fee_per_gas,
+				nonce,
+				access_list.unwrap_or_default(),
+				false,
+				&config,
+			)
+			.map_err(Into::into)
+		}
+
+		fn current_transaction_statuses() -> Option<Vec<fp_rpc::TransactionStatus>> {
+			Ethereum::current_transaction_statuses()
+		}
+
+		fn current_block() -> Option<pallet_ethereum::Block> {
+			Ethereum::current_block()
+		}
+
+		fn current_receipts() -> Option<Vec<pallet_ethereum::Receipt>> {
+			Ethereum::current_receipts()
+		}
+
+		fn current_all() -> (
+			Option<pallet_ethereum::Block>,
+			Option<Vec<pallet_ethereum::Receipt>>,
+			Option<Vec<fp_rpc::TransactionStatus>>,
+		) {
+			(
+				Ethereum::current_block(),
+				Ethereum::current_receipts(),
+				Ethereum::current_transaction_statuses(),
+			)
+		}
+
+		fn extrinsic_filter(
+			xts: Vec<<Block as BlockT>::Extrinsic>,
+		) -> Vec<pallet_ethereum::Transaction> {
+			xts.into_iter()
+				.filter_map(|xt| match xt.0.function {
+					Call::Ethereum(pallet_ethereum::Call::transact { transaction }) =>
+						Some(transaction),
+					_ => None,
+				})
+				.collect()
+		}
+
+		fn elasticity() -> Option<Permill> {
+			// Without a base fee pallet, fees do not adjust to the block fullness.
+			None
+		}
+	}
+
+	impl fp_rpc::ConvertTransactionRuntimeApi<Block> for Runtime {
+		fn convert_transaction(
+			transaction: pallet_ethereum::Transaction,
+		) -> <Block as BlockT>::Extrinsic {
+			UncheckedExtrinsic::new_unsigned(
+				pallet_ethereum::Call::<Runtime>::transact { transaction }.into(),
+			)
+		}
+	}
+
 	#[cfg(feature = "runtime-benchmarks")]
 	impl frame_benchmarking::Benchmark<Block> for Runtime {
 		fn benchmark_metadata(extra: bool) -> (
@@ -516,10 +1288,10 @@ impl_runtime_apis! {
 	#[cfg(feature = "try-runtime")]
 	impl frame_try_runtime::TryRuntime<Block> for Runtime {
 		fn on_runtime_upgrade() -> (Weight, Weight) {
-			// NOTE: intentional unwrap: we don't want to propagate the error backwards, and want to
-			// have a backtrace here. If any of the pre/post migration checks fail, we shall stop
-			// right here and right now.
-			let weight = Executive::try_runtime_upgrade().unwrap();
+			// The runtime API has no error type, so a failing `pre_upgrade` or `post_upgrade`
+			// check aborts the call with its message, which `try-runtime` reports as the error.
+			let weight = Executive::try_runtime_upgrade()
+				.unwrap_or_else(|e| panic!("migration checks failed: {}", e));
 			(weight, BlockWeights::get().max_block)
 		}
 
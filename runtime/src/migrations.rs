@@ -0,0 +1,51 @@
+//! Storage migrations run by `Executive` on runtime upgrades.
+//!
+//! Every migration is versioned through the `StorageVersion` of the pallet it touches, so running
+//! it twice is a no-op and it can stay in [`Migrations`] until the next release cleans it up.
+//!
+//! `Executive` only runs them when `spec_version` differs from the one recorded in
+//! `frame_system::LastRuntimeUpgrade`, so a release that adds a migration must bump it.
+//!
+//! See the README for how to check them against live state with `try-runtime`.
+
+use crate::{RocksDbWeight, Runtime, Weight};
+use frame_support::{storage::migration, traits::OnRuntimeUpgrade};
+
+/// Migrations applied on the next runtime upgrade, in order.
+pub type Migrations = (
+	pallet_template::migrations::v1::MigrateToV1<Runtime>,
+	pallet_mypallet::migrations::v1::MigrateToV1<Runtime>,
+);
+
+/// Clears the storage left behind by `pallet_sudo`.
+///
+/// Not part of [`Migrations`]: the runtime still includes `Sudo`.
+pub struct RemoveSudo;
+impl OnRuntimeUpgrade for RemoveSudo {
+	fn on_runtime_upgrade() -> Weight {
+		migration::remove_storage_prefix(b"Sudo", b"Key", &[]);
+		RocksDbWeight::get().writes(1)
+	}
+
+	#[cfg(feature = "try-runtime")]
+	fn pre_upgrade() -> Result<(), &'static str> {
+		frame_support::ensure!(
+			!crate::Council::members().is_empty(),
+			"the council must not be empty when sudo is removed"
+		);
+		frame_support::ensure!(
+			!crate::TechnicalCommittee::members().is_empty(),
+			"the technical committee must not be empty when sudo is removed"
+		);
+		Ok(())
+	}
+
+	#[cfg(feature = "try-runtime")]
+	fn post_upgrade() -> Result<(), &'static str> {
+		frame_support::ensure!(
+			!migration::have_storage_value(b"Sudo", b"Key", &[]),
+			"the sudo key is still present"
+		);
+		Ok(())
+	}
+}
@@ -0,0 +1,1048 @@
+use super::*;
+use frame_support::{
+	assert_ok,
+	traits::{Contains, InstanceFilter},
+	weights::DispatchClass,
+};
+use sp_runtime::traits::{Convert, One};
+
+type FeeMultiplierUpdate = <Runtime as pallet_transaction_payment::Config>::FeeMultiplierUpdate;
+
+fn new_test_ext() -> sp_io::TestExternalities {
+	frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap().into()
+}
+
+fn max_normal() -> Weight {
+	let weights = BlockWeights::get();
+	weights.get(DispatchClass::Normal).max_total.unwrap_or(weights.max_block)
+}
+
+fn target() -> Weight {
+	TargetBlockFullness::get() * max_normal()
+}
+
+fn run_with_system_weight<F: FnMut()>(w: Weight, mut assertions: F) {
+	new_test_ext().execute_with(|| {
+		System::set_block_consumed_resources(w, 0);
+		assertions()
+	});
+}
+
+/// Feeds `blocks` consecutive blocks of weight `w` through the fee multiplier update.
+fn simulate_blocks(w: Weight, start: Multiplier, blocks: u32) -> Multiplier {
+	let mut fm = start;
+	run_with_system_weight(w, || {
+		for _ in 0..blocks {
+			fm = FeeMultiplierUpdate::convert(fm);
+		}
+	});
+	fm
+}
+
+#[test]
+fn base_extrinsic_costs_one_milliunit() {
+	let fee = <WeightToFee as frame_support::weights::WeightToFee>::weight_to_fee(
+		&ExtrinsicBaseWeight::get(),
+	);
+	// The fractional coefficient is a `Perbill`, so allow for rounding in the last digit.
+	assert!(fee.max(MILLIUNIT) - fee.min(MILLIUNIT) <= 1, "base fee was {}", fee);
+}
+
+#[test]
+fn weight_to_fee_is_linear() {
+	let base = ExtrinsicBaseWeight::get();
+	let fee = |w| <WeightToFee as frame_support::weights::WeightToFee>::weight_to_fee(&w);
+	assert_eq!(fee(0), 0);
+	let ten = fee(10 * base);
+	assert!(ten.max(10 * MILLIUNIT) - ten.min(10 * MILLIUNIT) <= 10, "fee was {}", ten);
+}
+
+#[test]
+fn multiplier_is_stable_at_target_fullness() {
+	let fm = Multiplier::saturating_from_rational(3, 2);
+	assert_eq!(simulate_blocks(target(), fm, 10), fm);
+}
+
+#[test]
+fn multiplier_grows_on_full_blocks() {
+	let mut fm = Multiplier::one();
+	run_with_system_weight(max_normal(), || {
+		for _ in 0..100 {
+			let next = FeeMultiplierUpdate::convert(fm);
+			assert!(next > fm, "{:?} should be larger than {:?}", next, fm);
+			fm = next;
+		}
+	});
+}
+
+#[test]
+fn multiplier_shrinks_on_empty_blocks() {
+	let mut fm = Multiplier::one();
+	run_with_system_weight(0, || {
+		for _ in 0..100 {
+			let next = FeeMultiplierUpdate::convert(fm);
+			assert!(next < fm, "{:?} should be smaller than {:?}", next, fm);
+			fm = next;
+		}
+	});
+}
+
+#[test]
+fn a_day_of_full_blocks_raises_fees_by_roughly_a_third() {
+	// Each full block adds about `AdjustmentVariable * (1 - TargetBlockFullness)` to the
+	// multiplier, which compounds to ~1.38 over a day.
+	let fm = simulate_blocks(max_normal(), Multiplier::one(), DAYS);
+	assert!(fm > Multiplier::saturating_from_rational(13, 10), "{:?}", fm);
+	assert!(fm < Multiplier::saturating_from_rational(15, 10), "{:?}", fm);
+}
+
+#[test]
+fn a_day_of_empty_blocks_lowers_fees_by_roughly_a_tenth() {
+	let fm = simulate_blocks(0, Multiplier::one(), DAYS);
+	assert!(fm > Multiplier::saturating_from_rational(85, 100), "{:?}", fm);
+	assert!(fm < Multiplier::saturating_from_rational(95, 100), "{:?}", fm);
+}
+
+#[test]
+fn multiplier_never_drops_below_minimum() {
+	let fm = simulate_blocks(0, MinimumMultiplier::get(), 100);
+	assert_eq!(fm, MinimumMultiplier::get());
+
+	// Starting slightly above the floor converges onto it without undershooting.
+	let start = MinimumMultiplier::get().saturating_mul(Multiplier::saturating_from_rational(
+		1_000_001,
+		1_000_000,
+	));
+	assert_eq!(simulate_blocks(0, start, 1_000), MinimumMultiplier::get());
+}
+
+#[test]
+fn overfull_blocks_are_treated_as_full() {
+	// `CheckWeight` never lets a block go over the limit, but mandatory and operational
+	// extrinsics can push the normal class past it. That must not make fees explode.
+	assert_eq!(
+		simulate_blocks(max_normal() * 2, Multiplier::one(), 1),
+		simulate_blocks(max_normal(), Multiplier::one(), 1),
+	);
+}
+
+#[test]
+fn block_gas_limit_matches_the_largest_normal_extrinsic() {
+	use pallet_evm::GasWeightMapping;
+	type Mapping = <Runtime as pallet_evm::Config>::GasWeightMapping;
+
+	assert_eq!(WEIGHT_PER_GAS * GAS_PER_SECOND, WEIGHT_PER_SECOND);
+
+	let max_extrinsic = BlockWeights::get().get(DispatchClass::Normal).max_extrinsic.unwrap();
+	let block_gas_limit = BlockGasLimit::get().as_u64();
+	let weight = Mapping::gas_to_weight(block_gas_limit);
+	assert!(weight <= max_extrinsic);
+	assert!(max_extrinsic - weight < WEIGHT_PER_GAS);
+	assert_eq!(Mapping::weight_to_gas(weight), block_gas_limit);
+}
+
+#[test]
+fn evm_calls_up_to_the_block_gas_limit_fit_in_a_block() {
+	use frame_support::weights::GetDispatchInfo;
+
+	let call = |gas_limit| -> Call {
+		pallet_evm::Call::call {
+			source: Default::default(),
+			target: Default::default(),
+			input: vec![],
+			value: U256::zero(),
+			gas_limit,
+			max_fee_per_gas: U256::zero(),
+			max_priority_fee_per_gas: None,
+			nonce: None,
+			access_list: vec![],
+		}
+		.into()
+	};
+	let validate = |gas_limit| {
+		let info = call(gas_limit).get_dispatch_info();
+		frame_system::CheckWeight::<Runtime>::do_validate(&info, 0).map(|_| ())
+	};
+
+	new_test_ext().execute_with(|| {
+		let block_gas_limit = BlockGasLimit::get().as_u64();
+		assert_eq!(validate(block_gas_limit), Ok(()));
+		assert_eq!(
+			validate(block_gas_limit + 1),
+			Err(InvalidTransaction::ExhaustsResources.into())
+		);
+	});
+}
+
+fn remark() -> Call {
+	SystemCall::remark { remark: vec![] }.into()
+}
+
+fn transfer() -> Call {
+	BalancesCall::transfer { dest: AccountId::new([1; 32]).into(), value: UNIT }.into()
+}
+
+fn evm_withdraw() -> Call {
+	pallet_evm::Call::withdraw { address: Default::default(), value: UNIT }.into()
+}
+
+fn link() -> Call {
+	let signature = sp_core::ecdsa::Signature::from_raw([0; 65]);
+	pallet_account_link::Call::link { address: Default::default(), signature }.into()
+}
+
+fn vote() -> Call {
+	pallet_democracy::Call::second { proposal: 0, seconds_upper_bound: 1 }.into()
+}
+
+#[test]
+fn any_proxy_allows_everything() {
+	for call in [remark(), transfer(), evm_withdraw(), vote()] {
+		assert!(ProxyType::Any.filter(&call));
+	}
+}
+
+#[test]
+fn non_transfer_proxy_cannot_move_funds() {
+	assert!(ProxyType::NonTransfer.filter(&remark()));
+	assert!(ProxyType::NonTransfer.filter(&vote()));
+	assert!(!ProxyType::NonTransfer.filter(&transfer()));
+	assert!(!ProxyType::NonTransfer.filter(&evm_withdraw()));
+	assert!(!ProxyType::NonTransfer.filter(&link()));
+}
+
+#[test]
+fn governance_proxy_only_allows_governance() {
+	assert!(ProxyType::Governance.filter(&vote()));
+	assert!(!ProxyType::Governance.filter(&remark()));
+	assert!(!ProxyType::Governance.filter(&transfer()));
+	assert!(!ProxyType::Governance.filter(&evm_withdraw()));
+}
+
+#[test]
+fn evm_only_proxy_only_allows_evm() {
+	assert!(ProxyType::EvmOnly.filter(&evm_withdraw()));
+	assert!(!ProxyType::EvmOnly.filter(&remark()));
+	assert!(!ProxyType::EvmOnly.filter(&transfer()));
+	assert!(!ProxyType::EvmOnly.filter(&vote()));
+}
+
+#[test]
+fn proxy_type_supersets() {
+	use ProxyType::*;
+	for t in [Any, NonTransfer, Governance, EvmOnly] {
+		assert!(Any.is_superset(&t));
+		assert!(t.is_superset(&t));
+	}
+	assert!(NonTransfer.is_superset(&Governance));
+	assert!(!NonTransfer.is_superset(&EvmOnly));
+	assert!(!Governance.is_superset(&NonTransfer));
+	assert!(!EvmOnly.is_superset(&Any));
+}
+
+fn set_timestamp() -> Call {
+	TimestampCall::set { now: 0 }.into()
+}
+
+fn pause() -> Call {
+	pallet_maintenance_mode::Call::pause_pallet { pallet: b"Balances".to_vec() }.into()
+}
+
+#[test]
+fn maintenance_mode_only_lets_whitelisted_calls_through() {
+	new_test_ext().execute_with(|| {
+		assert!(MaintenanceMode::contains(&transfer()));
+		assert!(MaintenanceMode::contains(&evm_withdraw()));
+
+		assert_ok!(MaintenanceMode::enter_maintenance_mode(Origin::root()));
+
+		assert!(!MaintenanceMode::contains(&remark()));
+		assert!(!MaintenanceMode::contains(&transfer()));
+		assert!(!MaintenanceMode::contains(&evm_withdraw()));
+		assert!(MaintenanceMode::contains(&set_timestamp()));
+		assert!(MaintenanceMode::contains(&vote()));
+		assert!(MaintenanceMode::contains(&pause()));
+	});
+}
+
+#[test]
+fn evm_can_be_paused_on_its_own() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(MaintenanceMode::pause_pallet(Origin::root(), b"EVM".to_vec()));
+
+		assert!(!MaintenanceMode::contains(&evm_withdraw()));
+		assert!(MaintenanceMode::contains(&transfer()));
+
+		assert_ok!(MaintenanceMode::unpause_pallet(Origin::root(), b"EVM".to_vec()));
+		assert!(MaintenanceMode::contains(&evm_withdraw()));
+	});
+}
+
+#[test]
+fn pausing_a_whitelisted_pallet_has_no_effect() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(MaintenanceMode::pause_pallet(Origin::root(), b"Democracy".to_vec()));
+		assert!(MaintenanceMode::contains(&vote()));
+	});
+}
+
+#[test]
+fn unit_matches_token_decimals() {
+	assert_eq!(UNIT, 10u128.pow(TOKEN_DECIMALS.into()));
+}
+
+#[test]
+fn pallets_keep_their_indices() {
+	use frame_support::traits::PalletInfoAccess;
+
+	// Calls signed for the first release still decode to the same pallets.
+	assert_eq!(System::index(), 0);
+	assert_eq!(Balances::index(), 5);
+	assert_eq!(Sudo::index(), 7);
+	assert_eq!(TemplateModule::index(), 8);
+	assert_eq!(MyPallet::index(), 24);
+}
+
+#[test]
+fn migrations_run_on_a_spec_version_bump() {
+	use frame_support::traits::{GetStorageVersion, StorageVersion};
+	use sp_runtime::traits::Header as _;
+
+	new_test_ext().execute_with(|| {
+		let mut previous = VERSION;
+		previous.spec_version -= 1;
+		frame_system::LastRuntimeUpgrade::<Runtime>::put(
+			frame_system::LastRuntimeUpgradeInfo::from(previous),
+		);
+		StorageVersion::new(0).put::<TemplateModule>();
+		StorageVersion::new(0).put::<MyPallet>();
+
+		let header = Header::new(
+			1,
+			Default::default(),
+			Default::default(),
+			Default::default(),
+			Default::default(),
+		);
+		Executive::initialize_block(&header);
+
+		assert_eq!(TemplateModule::on_chain_storage_version(), 1);
+		assert_eq!(MyPallet::on_chain_storage_version(), 1);
+		assert_eq!(
+			frame_system::LastRuntimeUpgrade::<Runtime>::get().map(|info| info.spec_version),
+			Some(VERSION.spec_version.into()),
+		);
+	});
+}
+
+#[cfg(feature = "try-runtime")]
+#[test]
+fn migration_checks_catch_changed_storage() {
+	use frame_support::traits::{OnRuntimeUpgrade, StorageVersion};
+	use migrations::Migrations;
+
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<TemplateModule>();
+		StorageVersion::new(0).put::<MyPallet>();
+		pallet_template::Something::<Runtime>::put(7);
+
+		assert_ok!(Migrations::pre_upgrade());
+		Migrations::on_runtime_upgrade();
+		assert_ok!(Migrations::post_upgrade());
+
+		assert_ok!(Migrations::pre_upgrade());
+		pallet_template::Something::<Runtime>::put(8);
+		assert!(Migrations::post_upgrade().is_err());
+
+		StorageVersion::new(2).put::<TemplateModule>();
+		assert!(Migrations::pre_upgrade().is_err());
+	});
+}
+
+#[test]
+fn remove_sudo_clears_the_key() {
+	use frame_support::{storage::migration::put_storage_value, traits::OnRuntimeUpgrade};
+
+	new_test_ext().execute_with(|| {
+		put_storage_value(b"Sudo", b"Key", &[], AccountId::from([1u8; 32]));
+		assert!(Sudo::key().is_some());
+
+		migrations::RemoveSudo::on_runtime_upgrade();
+
+		assert_eq!(Sudo::key(), None);
+	});
+}
+
+#[test]
+fn gas_costs_what_its_weight_costs() {
+	use pallet_evm::FeeCalculator;
+
+	let price = new_test_ext().execute_with(MinGasPrice::min_gas_price);
+	let weight_fee =
+		<WeightToFee as frame_support::weights::WeightToFee>::weight_to_fee(&WEIGHT_PER_GAS);
+	assert!(!price.is_zero());
+	assert_eq!(price, evm_currency::balance_to_wei(weight_fee));
+	// A plain transfer costs about as much as the base fee of an extrinsic.
+	let transfer = evm_currency::wei_to_balance_exact(price * 21_000).unwrap();
+	assert!(transfer > MILLIUNIT && transfer < 10 * MILLIUNIT, "{}", transfer);
+}
+
+#[test]
+fn gas_price_follows_the_fee_multiplier() {
+	use pallet_evm::FeeCalculator;
+
+	new_test_ext().execute_with(|| {
+		let price = MinGasPrice::min_gas_price();
+
+		pallet_transaction_payment::NextFeeMultiplier::<Runtime>::put(Multiplier::saturating_from_integer(3));
+		assert_eq!(MinGasPrice::min_gas_price(), price * 3);
+
+		pallet_transaction_payment::NextFeeMultiplier::<Runtime>::put(MinimumMultiplier::get());
+		assert!(!MinGasPrice::min_gas_price().is_zero());
+	});
+}
+
+fn evm_call(from: H160, to: H160, input: Vec<u8>, value: U256) -> pallet_evm::CallInfo {
+	use pallet_evm::Runner;
+
+	<Runtime as pallet_evm::Config>::Runner::call(
+		from,
+		to,
+		input,
+		value,
+		1_000_000,
+		None,
+		None,
+		None,
+		vec![],
+		false,
+		<Runtime as pallet_evm::Config>::config(),
+	)
+	.expect("call passes validation")
+}
+
+mod evm_balances {
+	use super::*;
+	use crate::evm_currency::*;
+	use frame_support::traits::{Currency, ExistenceRequirement, Imbalance, WithdrawReasons};
+	use pallet_evm::{AddressMapping, ExitError, ExitReason, ExitSucceed, OnChargeEVMTransaction};
+
+	const ALICE: H160 = H160::repeat_byte(0xaa);
+	const BOB: H160 = H160::repeat_byte(0xbb);
+
+	fn account(address: H160) -> AccountId {
+		<Runtime as pallet_evm::Config>::AddressMapping::into_account_id(address)
+	}
+
+	fn native_balance(address: H160) -> Balance {
+		Balances::free_balance(account(address))
+	}
+
+	fn new_test_ext() -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+		pallet_balances::GenesisConfig::<Runtime> { balances: vec![(account(ALICE), 10 * UNIT)] }
+			.assimilate_storage(&mut t)
+			.unwrap();
+		t.into()
+	}
+
+	fn transfer(from: H160, to: H160, value: U256) -> ExitReason {
+		evm_call(from, to, vec![], value).exit_reason
+	}
+
+	#[test]
+	fn conversions_round_as_documented() {
+		assert_eq!(WEI_PER_BALANCE, 1_000_000);
+		assert_eq!(balance_to_wei(UNIT), U256::exp10(18));
+		assert_eq!(
+			balance_to_wei(Balance::MAX),
+			U256::from(Balance::MAX) * U256::from(WEI_PER_BALANCE)
+		);
+
+		let wei = U256::from(3 * WEI_PER_BALANCE + 1);
+		assert_eq!(wei_to_balance_floor(wei), 3);
+		assert_eq!(wei_to_balance_ceil(wei), 4);
+		assert_eq!(wei_to_balance_exact(wei), None);
+		assert_eq!(wei_to_balance_exact(U256::from(3 * WEI_PER_BALANCE)), Some(3));
+
+		assert_eq!(wei_to_balance_floor(U256::one()), 0);
+		assert_eq!(wei_to_balance_ceil(U256::one()), 1);
+		assert_eq!(wei_to_balance_ceil(U256::zero()), 0);
+		assert_eq!(wei_to_balance_floor(U256::MAX), Balance::MAX);
+		assert_eq!(wei_to_balance_ceil(U256::MAX), Balance::MAX);
+		assert_eq!(wei_to_balance_exact(balance_to_wei(Balance::MAX) * U256::from(2)), None);
+	}
+
+	#[test]
+	fn evm_sees_balances_in_wei() {
+		new_test_ext().execute_with(|| {
+			assert_eq!(EVM::account_basic(&ALICE).balance, U256::exp10(19));
+			assert_eq!(EvmCurrency::free_balance(&account(ALICE)), 10 * UNIT * WEI_PER_BALANCE);
+			assert_eq!(EvmCurrency::minimum_balance(), EXISTENTIAL_DEPOSIT * WEI_PER_BALANCE);
+		});
+	}
+
+	#[test]
+	fn whole_unit_transfers_move_native_balance() {
+		new_test_ext().execute_with(|| {
+			let value = balance_to_wei(UNIT);
+			assert_eq!(transfer(ALICE, BOB, value), ExitReason::Succeed(ExitSucceed::Stopped));
+			assert_eq!(native_balance(ALICE), 9 * UNIT);
+			assert_eq!(native_balance(BOB), UNIT);
+			assert_eq!(EVM::account_basic(&BOB).balance, value);
+		});
+	}
+
+	#[test]
+	fn dust_transfers_are_rejected() {
+		new_test_ext().execute_with(|| {
+			let value = balance_to_wei(UNIT) + 1;
+			assert_eq!(transfer(ALICE, BOB, value), ExitReason::Error(ExitError::OutOfFund));
+			assert_eq!(native_balance(ALICE), 10 * UNIT);
+			assert_eq!(native_balance(BOB), 0);
+
+			assert_eq!(
+				EvmCurrency::transfer(
+					&account(ALICE),
+					&account(BOB),
+					WEI_PER_BALANCE - 1,
+					ExistenceRequirement::AllowDeath
+				),
+				Err(DUST_ERROR)
+			);
+		});
+	}
+
+	#[test]
+	fn transfers_cannot_create_accounts_below_the_existential_deposit() {
+		new_test_ext().execute_with(|| {
+			let value = balance_to_wei(EXISTENTIAL_DEPOSIT - 1);
+			assert_eq!(transfer(ALICE, BOB, value), ExitReason::Error(ExitError::OutOfFund));
+			assert!(!System::account_exists(&account(BOB)));
+
+			let value = balance_to_wei(EXISTENTIAL_DEPOSIT);
+			assert_eq!(transfer(ALICE, BOB, value), ExitReason::Succeed(ExitSucceed::Stopped));
+			assert!(System::account_exists(&account(BOB)));
+		});
+	}
+
+	#[test]
+	fn fees_round_in_favour_of_the_chain() {
+		new_test_ext().execute_with(|| {
+			let issuance = Balances::total_issuance();
+
+			// 5 units and 1 wei are charged as 6 units.
+			let paid =
+				EvmFeeAdapter::withdraw_fee(&ALICE, U256::from(5 * WEI_PER_BALANCE + 1)).unwrap();
+			assert_eq!(native_balance(ALICE), 10 * UNIT - 6);
+
+			// Only 2 units and 1 wei were used: 3 units are kept and 3 refunded.
+			EvmFeeAdapter::correct_and_deposit_fee(
+				&ALICE,
+				U256::from(2 * WEI_PER_BALANCE + 1),
+				paid,
+			);
+			assert_eq!(native_balance(ALICE), 10 * UNIT - 3);
+			assert_eq!(Balances::total_issuance(), issuance - 3);
+		});
+	}
+
+	#[test]
+	fn fees_below_one_unit_are_not_free() {
+		new_test_ext().execute_with(|| {
+			let paid = EvmFeeAdapter::withdraw_fee(&ALICE, U256::one()).unwrap();
+			EvmFeeAdapter::correct_and_deposit_fee(&ALICE, U256::one(), paid);
+			assert_eq!(native_balance(ALICE), 10 * UNIT - 1);
+		});
+	}
+
+	#[test]
+	fn withdrawals_round_up() {
+		new_test_ext().execute_with(|| {
+			// Imbalances are in native units.
+			assert_eq!(
+				EvmCurrency::withdraw(
+					&account(ALICE),
+					WEI_PER_BALANCE + 1,
+					WithdrawReasons::FEE,
+					ExistenceRequirement::AllowDeath,
+				)
+				.map(|imbalance| imbalance.peek()),
+				Ok(2)
+			);
+		});
+	}
+}
+
+mod evm_environment {
+	use super::*;
+	use frame_support::{storage::migration::put_storage_value, Hashable};
+	use hex_literal::hex;
+	use sp_consensus_aura::{Slot, AURA_ENGINE_ID};
+	use sp_core::{sr25519, H256};
+	use sp_runtime::DigestItem;
+
+	const CALLER: H160 = H160::repeat_byte(0xaa);
+	const CONTRACT: H160 = H160::repeat_byte(0xcc);
+
+	// COINBASE PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+	const COINBASE: &[u8] = &hex!("4160005260206000f3");
+	// PUSH1 0 CALLDATALOAD BLOCKHASH PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+	const BLOCKHASH: &[u8] = &hex!("6000354060005260206000f3");
+
+	/// The key of the `n`th authority, with a distinct first byte so truncating it at the wrong
+	/// offset gives a different address.
+	fn authority_key(n: u8) -> [u8; 32] {
+		let mut key = [n; 32];
+		key[0] = 0xff;
+		key
+	}
+
+	fn authorities() -> Vec<AuraId> {
+		vec![
+			sr25519::Public::from_raw(authority_key(1)).into(),
+			sr25519::Public::from_raw(authority_key(2)).into(),
+		]
+	}
+
+	fn new_test_ext() -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+		pallet_aura::GenesisConfig::<Runtime> { authorities: authorities() }
+			.assimilate_storage(&mut t)
+			.unwrap();
+		t.into()
+	}
+
+	fn run(code: &[u8], input: Vec<u8>) -> H256 {
+		pallet_evm::AccountCodes::<Runtime>::insert(CONTRACT, code.to_vec());
+		let info = evm_call(CALLER, CONTRACT, input, U256::zero());
+		assert_eq!(info.exit_reason, ExitReason::Succeed(ExitSucceed::Returned));
+		H256::from_slice(&info.value)
+	}
+
+	fn insert_block_hashes(number: u32) {
+		frame_system::BlockHash::<Runtime>::insert(number, H256::repeat_byte(5));
+		// `pallet_ethereum::BlockHash` is private to the pallet.
+		let key = U256::from(number).twox_64_concat();
+		put_storage_value(b"Ethereum", b"BlockHash", &key, H256::repeat_byte(0xe5));
+	}
+
+	fn blockhash(number: u32) -> H256 {
+		run(BLOCKHASH, H256::from_low_u64_be(number.into()).as_bytes().to_vec())
+	}
+
+	#[test]
+	fn coinbase_is_the_aura_author() {
+		new_test_ext().execute_with(|| {
+			// Without a pre-runtime digest there is no author.
+			assert_eq!(run(COINBASE, vec![]), H256::zero());
+
+			System::deposit_log(DigestItem::PreRuntime(AURA_ENGINE_ID, Slot::from(3).encode()));
+
+			// Slot 3 of 2 authorities is authored by the second one.
+			let author = H160::from_slice(&authority_key(2)[..20]);
+			assert_eq!(run(COINBASE, vec![]), H256::from(author));
+			assert_eq!(EVM::find_author(), author);
+		});
+	}
+
+	#[test]
+	fn the_author_can_spend_its_tips() {
+		use frame_support::traits::Currency;
+		use pallet_evm::{AddressMapping, OnChargeEVMTransaction};
+
+		new_test_ext().execute_with(|| {
+			System::deposit_log(DigestItem::PreRuntime(AURA_ENGINE_ID, Slot::from(3).encode()));
+			let author = AccountId::from(authority_key(2));
+			let coinbase = EVM::find_author();
+			// Tips are only paid into existing accounts.
+			let tips = <Runtime as pallet_evm::Config>::AddressMapping::into_account_id(coinbase);
+			let _ = Balances::deposit_creating(&tips, EXISTENTIAL_DEPOSIT);
+
+			evm_currency::EvmFeeAdapter::pay_priority_fee(evm_currency::balance_to_wei(UNIT));
+
+			let tip = UNIT * evm_currency::WEI_PER_BALANCE;
+			assert_ok!(EVM::withdraw(Origin::signed(author.clone()), coinbase, tip));
+			assert_eq!(Balances::free_balance(&author), UNIT);
+		});
+	}
+
+	#[test]
+	fn blockhash_uses_substrate_hashes_by_default() {
+		new_test_ext().execute_with(|| {
+			System::set_block_number(10);
+			insert_block_hashes(5);
+
+			assert!(!UseEthereumBlockHashes::get());
+			assert_eq!(blockhash(5), H256::repeat_byte(5));
+			assert_eq!(blockhash(6), H256::zero());
+		});
+	}
+
+	#[test]
+	fn blockhash_can_use_ethereum_hashes() {
+		new_test_ext().execute_with(|| {
+			System::set_block_number(10);
+			insert_block_hashes(5);
+
+			UseEthereumBlockHashes::set(&true);
+			assert_eq!(blockhash(5), H256::repeat_byte(0xe5));
+		});
+	}
+}
+
+mod evm_origins {
+	use super::*;
+	use crate::evm_currency::{balance_to_wei, WEI_PER_BALANCE};
+	use frame_support::{assert_noop, dispatch::DispatchResultWithPostInfo, traits::Currency};
+	use hex_literal::hex;
+	use pallet_evm::{AddressMapping, FeeCalculator};
+	use sp_core::{ecdsa, Pair};
+	use sp_runtime::DispatchError;
+
+	pub(super) const ALICE: AccountId = AccountId::new([1; 32]);
+	const BOB: AccountId = AccountId::new([2; 32]);
+	const CONTRACT: H160 = H160::repeat_byte(0xcc);
+
+	/// A well-known development key and its EVM address.
+	pub(super) fn alith() -> (ecdsa::Pair, H160) {
+		let pair = ecdsa::Pair::from_seed(&hex!(
+			"5fb92d6e98884f76de468fa3f6278f8807c48bebc13595d45af5bdc4da702133"
+		));
+		(pair, H160(hex!("f24ff3a9cf04c71dbc94d0b566f7a27b94566cac")))
+	}
+
+	fn truncated(who: &AccountId) -> H160 {
+		pallet_account_link::truncated_address(AsRef::<[u8]>::as_ref(who)).unwrap()
+	}
+
+	fn new_test_ext() -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+		pallet_balances::GenesisConfig::<Runtime> {
+			balances: vec![(ALICE, 10 * UNIT), (BOB, 10 * UNIT)],
+		}
+		.assimilate_storage(&mut t)
+		.unwrap();
+		let mut ext = sp_io::TestExternalities::new(t);
+		ext.execute_with(|| System::set_block_number(1));
+		ext
+	}
+
+	/// Links `ALICE` to Alith's address and returns it.
+	pub(super) fn link_alice() -> H160 {
+		let (pair, address) = alith();
+		let message = AccountLink::link_message(&ALICE);
+		let signature = pair.sign_prehashed(&AccountLink::eip191_hash(&message));
+		assert_ok!(AccountLink::link(Origin::signed(ALICE), address, signature));
+		address
+	}
+
+	fn call(origin: Origin, source: H160, value: U256) -> DispatchResultWithPostInfo {
+		EVM::call(
+			origin,
+			source,
+			CONTRACT,
+			vec![],
+			value,
+			1_000_000,
+			MinGasPrice::min_gas_price(),
+			None,
+			None,
+			vec![],
+		)
+	}
+
+	/// The fee of a call that only costs the 21000 gas of a transaction.
+	fn plain_call_fee() -> Balance {
+		evm_currency::wei_to_balance_exact(MinGasPrice::min_gas_price() * 21_000).unwrap()
+	}
+
+	fn create(origin: Origin, source: H160) -> DispatchResultWithPostInfo {
+		// PUSH1 0 PUSH1 0 RETURN
+		EVM::create(
+			origin,
+			source,
+			hex!("60006000f3").to_vec(),
+			U256::zero(),
+			1_000_000,
+			MinGasPrice::min_gas_price(),
+			None,
+			None,
+			vec![],
+		)
+	}
+
+	fn last_event() -> Event {
+		System::events().pop().expect("an event was deposited").event
+	}
+
+	#[test]
+	fn linked_accounts_call_from_their_address() {
+		new_test_ext().execute_with(|| {
+			let address = link_alice();
+			pallet_evm::AccountCodes::<Runtime>::insert(CONTRACT, vec![0x00]);
+
+			assert_ok!(call(Origin::signed(ALICE), address, balance_to_wei(UNIT)));
+			assert_eq!(last_event(), pallet_evm::Event::<Runtime>::Executed(CONTRACT).into());
+			// The value and the fee came out of the Substrate account.
+			assert_eq!(Balances::free_balance(&ALICE), 9 * UNIT - plain_call_fee());
+
+			assert_noop!(
+				call(Origin::signed(BOB), address, U256::zero()),
+				DispatchError::BadOrigin
+			);
+			assert_noop!(call(Origin::root(), address, U256::zero()), DispatchError::BadOrigin);
+		});
+	}
+
+	#[test]
+	fn calls_must_pay_the_gas_price() {
+		new_test_ext().execute_with(|| {
+			let address = link_alice();
+			pallet_evm::AccountCodes::<Runtime>::insert(CONTRACT, vec![0x00]);
+
+			let result = EVM::call(
+				Origin::signed(ALICE),
+				address,
+				CONTRACT,
+				vec![],
+				U256::zero(),
+				1_000_000,
+				U256::zero(),
+				None,
+				None,
+				vec![],
+			);
+			assert_eq!(
+				result.map_err(|e| e.error),
+				Err(pallet_evm::Error::<Runtime>::GasPriceTooLow.into())
+			);
+			assert_eq!(Balances::free_balance(&ALICE), 10 * UNIT);
+		});
+	}
+
+	#[test]
+	fn linked_accounts_create_from_their_address() {
+		new_test_ext().execute_with(|| {
+			let address = link_alice();
+
+			assert_ok!(create(Origin::signed(ALICE), address));
+			let event = last_event();
+			assert!(matches!(event, Event::EVM(pallet_evm::Event::Created(_))), "{:?}", event);
+
+			assert_noop!(create(Origin::signed(BOB), address), DispatchError::BadOrigin);
+		});
+	}
+
+	#[test]
+	fn accounts_withdraw_from_their_truncated_address() {
+		new_test_ext().execute_with(|| {
+			let address = truncated(&ALICE);
+			let mapped = <Runtime as pallet_evm::Config>::AddressMapping::into_account_id(address);
+			let _ = Balances::deposit_creating(&mapped, UNIT);
+
+			assert_noop!(
+				EVM::withdraw(Origin::signed(BOB), address, UNIT * WEI_PER_BALANCE),
+				DispatchError::BadOrigin
+			);
+			assert_ok!(EVM::withdraw(Origin::signed(ALICE), address, UNIT * WEI_PER_BALANCE));
+			assert_eq!(Balances::free_balance(&ALICE), 11 * UNIT);
+			assert_eq!(Balances::free_balance(&mapped), 0);
+		});
+	}
+
+	#[test]
+	fn linking_keeps_the_truncated_address() {
+		new_test_ext().execute_with(|| {
+			let address = link_alice();
+			let truncated = truncated(&ALICE);
+			let mapped =
+				<Runtime as pallet_evm::Config>::AddressMapping::into_account_id(truncated);
+			let _ = Balances::deposit_creating(&mapped, UNIT);
+
+			// Alice still controls the address she truncates to, which nobody linked.
+			assert_ok!(create(Origin::signed(ALICE), truncated));
+			assert_ok!(create(Origin::signed(ALICE), address));
+			assert_noop!(create(Origin::signed(BOB), truncated), DispatchError::BadOrigin);
+		});
+	}
+}
+
+mod ethereum_transactions {
+	use super::*;
+	use ethereum::{LegacyTransaction, LegacyTransactionMessage, TransactionAction};
+	use fp_self_contained::SelfContainedCall;
+	use frame_support::traits::Currency;
+	use pallet_evm::{AddressMapping, FeeCalculator};
+	use sp_runtime::transaction_validity::InvalidTransaction;
+
+	/// A transaction from Alith, offering `gas_price`.
+	fn transaction(action: TransactionAction, input: Vec<u8>, gas_price: U256) -> Call {
+		let (pair, _) = evm_origins::alith();
+		let message = LegacyTransactionMessage {
+			nonce: U256::zero(),
+			gas_price,
+			gas_limit: 100_000.into(),
+			action,
+			value: U256::zero(),
+			input,
+			chain_id: Some(EVM_CHAIN_ID),
+		};
+		let signature = pair.sign_prehashed(message.hash().as_fixed_bytes());
+		let bytes: &[u8; 65] = signature.as_ref();
+		let signature = ethereum::TransactionSignature::new(
+			EVM_CHAIN_ID * 2 + 35 + bytes[64] as u64,
+			H256::from_slice(&bytes[..32]),
+			H256::from_slice(&bytes[32..64]),
+		)
+		.unwrap();
+		let transaction = pallet_ethereum::Transaction::Legacy(LegacyTransaction {
+			nonce: message.nonce,
+			gas_price,
+			gas_limit: message.gas_limit,
+			action: message.action,
+			value: message.value,
+			input: message.input,
+			signature,
+		});
+		pallet_ethereum::Call::transact { transaction }.into()
+	}
+
+	/// A transfer of nothing, offering `gas_price`.
+	fn transfer(gas_price: U256) -> Call {
+		transaction(TransactionAction::Call(H160::repeat_byte(0xbb)), vec![], gas_price)
+	}
+
+	/// The deployment of an empty contract.
+	fn create() -> Call {
+		// PUSH1 0 PUSH1 0 RETURN
+		let init = hex_literal::hex!("60006000f3").to_vec();
+		transaction(TransactionAction::Create, init, MinGasPrice::min_gas_price())
+	}
+
+	fn validate(call: &Call) -> Option<TransactionValidity> {
+		let signer = call.check_self_contained()?.expect("the signature is valid");
+		call.validate_self_contained(&signer)
+	}
+
+	fn pre_dispatch(call: &Call) -> Option<Result<(), TransactionValidityError>> {
+		let signer = call.check_self_contained()?.expect("the signature is valid");
+		call.pre_dispatch_self_contained(&signer)
+	}
+
+	/// Gives Alith's address something to pay fees with.
+	fn fund_alith() -> H160 {
+		let (_, address) = evm_origins::alith();
+		let account = <Runtime as pallet_evm::Config>::AddressMapping::into_account_id(address);
+		let _ = Balances::deposit_creating(&account, UNIT);
+		address
+	}
+
+	#[test]
+	fn transactions_must_pay_the_gas_price() {
+		new_test_ext().execute_with(|| {
+			fund_alith();
+			let price = MinGasPrice::min_gas_price();
+
+			let payment = Some(Err(InvalidTransaction::Payment.into()));
+			assert_eq!(validate(&transfer(U256::zero())), payment);
+			assert_eq!(validate(&transfer(price - 1)), payment);
+			assert!(matches!(validate(&transfer(price)), Some(Ok(_))));
+		});
+	}
+
+	#[test]
+	fn transactions_from_before_a_link_cannot_be_replayed() {
+		new_test_ext().execute_with(|| {
+			fund_alith();
+			let transfer = transfer(MinGasPrice::min_gas_price());
+			let signer = transfer.check_self_contained().unwrap().unwrap();
+			assert_ok!(transfer.clone().apply_self_contained(signer).unwrap());
+			let stale = Some(Err(InvalidTransaction::Stale.into()));
+			assert_eq!(validate(&transfer), stale);
+
+			// Alice's nonce takes over from the one of the account Alith was mapped to.
+			evm_origins::link_alice();
+			assert_eq!(validate(&transfer), stale);
+
+			// And the other way round.
+			assert_ok!(AccountLink::unlink(Origin::signed(evm_origins::ALICE)));
+			fund_alith();
+			assert_eq!(validate(&transfer), stale);
+		});
+	}
+
+	#[test]
+	fn creates_from_disallowed_deployers_never_reach_the_pool() {
+		new_test_ext().execute_with(|| {
+			let address = fund_alith();
+			assert!(matches!(validate(&create()), Some(Ok(_))));
+
+			assert_ok!(DeployerAllowlist::set_enforced(Origin::root(), true));
+			let rejected = Some(Err(InvalidTransaction::Call.into()));
+			assert_eq!(validate(&create()), rejected);
+			assert_eq!(pre_dispatch(&create()), rejected);
+			// Calls stay open to everyone.
+			assert!(matches!(validate(&transfer(MinGasPrice::min_gas_price())), Some(Ok(_))));
+
+			assert_ok!(DeployerAllowlist::add_deployer(Origin::root(), address));
+			assert!(matches!(validate(&create()), Some(Ok(_))));
+		});
+	}
+}
+
+mod evm_reaping {
+	use super::*;
+	use crate::evm_currency::balance_to_wei;
+	use frame_support::traits::{Currency, Hooks};
+	use pallet_evm::{AddressMapping, FeeCalculator};
+
+	/// Deploys a contract that keeps a storage slot, with a balance, from Alith's address.
+	fn deploy() -> H160 {
+		let _ = Balances::deposit_creating(&evm_origins::ALICE, 10 * UNIT);
+		let source = evm_origins::link_alice();
+		// PUSH1 1 PUSH1 0 SSTORE PUSH1 1 PUSH1 0 RETURN
+		let init = hex_literal::hex!("600160005560016000f3").to_vec();
+		assert_ok!(EVM::create(
+			Origin::signed(evm_origins::ALICE),
+			source,
+			init,
+			balance_to_wei(UNIT),
+			1_000_000,
+			MinGasPrice::min_gas_price(),
+			None,
+			None,
+			vec![],
+		));
+		System::events()
+			.into_iter()
+			.find_map(|record| match record.event {
+				Event::EVM(pallet_evm::Event::Created(address)) => Some(address),
+				_ => None,
+			})
+			.expect("the contract was created")
+	}
+
+	#[test]
+	fn killed_contract_accounts_lose_their_evm_state() {
+		new_test_ext().execute_with(|| {
+			System::set_block_number(1);
+			let contract = deploy();
+			let account =
+				<Runtime as pallet_evm::Config>::AddressMapping::into_account_id(contract);
+			assert_eq!(LinkedEvmAddress::convert(account.clone()), None);
+			assert_eq!(EvmReaper::address_of(&account), Some(contract));
+			assert!(pallet_evm::AccountCodes::<Runtime>::contains_key(contract));
+
+			let _ = Balances::make_free_balance_be(&account, 0);
+			assert!(!System::account_exists(&account));
+			assert_eq!(EvmReaper::pending(contract), Some(account.clone()));
+			assert_eq!(EvmReaper::address_of(&account), None);
+
+			EvmReaper::on_idle(1, Weight::MAX);
+			assert!(!pallet_evm::AccountCodes::<Runtime>::contains_key(contract));
+			assert_eq!(pallet_evm::AccountStorages::<Runtime>::iter_prefix(contract).count(), 0);
+			assert_eq!(EvmReaper::pending(contract), None);
+		});
+	}
+}
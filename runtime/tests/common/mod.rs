@@ -0,0 +1,122 @@
+//! Genesis and extrinsic helpers for tests against the real `Runtime`.
+
+use codec::Encode;
+use node_template_runtime::{
+	AccountId, Balance, BalancesConfig, Call, Executive, GenesisConfig, Header, Index, Runtime,
+	Signature, SignedExtra, SignedPayload, SudoConfig, System, UncheckedExtrinsic, UNIT,
+};
+use sp_keyring::Sr25519Keyring::{self, Alice, Bob, Charlie};
+use sp_runtime::{
+	generic::Era,
+	traits::{Header as _, SignedExtension},
+	BuildStorage,
+};
+
+/// What each account [`ExtBuilder::default`] endows starts with.
+pub const INITIAL_BALANCE: Balance = 1_000 * UNIT;
+
+/// Builds the genesis state of the real `GenesisConfig`, and runs tests in the first block.
+pub struct ExtBuilder {
+	balances: Vec<(AccountId, Balance)>,
+	sudo: Option<AccountId>,
+}
+
+impl Default for ExtBuilder {
+	/// Alice, Bob and Charlie with [`INITIAL_BALANCE`] each, and Alice as the sudo key.
+	fn default() -> Self {
+		Self {
+			balances: [Alice, Bob, Charlie]
+				.into_iter()
+				.map(|account| (account.to_account_id(), INITIAL_BALANCE))
+				.collect(),
+			sudo: Some(Alice.to_account_id()),
+		}
+	}
+}
+
+impl ExtBuilder {
+	/// Endow `account` with `balance`, instead of what it would have.
+	pub fn balance(mut self, account: Sr25519Keyring, balance: Balance) -> Self {
+		let account = account.to_account_id();
+		self.balances.retain(|(who, _)| *who != account);
+		self.balances.push((account, balance));
+		self
+	}
+
+	/// Make `account` the sudo key, or remove the key.
+	pub fn sudo(mut self, account: Option<Sr25519Keyring>) -> Self {
+		self.sudo = account.map(|account| account.to_account_id());
+		self
+	}
+
+	/// The genesis state, before any block.
+	pub fn build(self) -> sp_io::TestExternalities {
+		GenesisConfig {
+			balances: BalancesConfig { balances: self.balances },
+			sudo: SudoConfig { key: self.sudo },
+			..Default::default()
+		}
+		.build_storage()
+		.unwrap()
+		.into()
+	}
+
+	/// Run `test` in block 1, as initialized by `Executive`.
+	pub fn execute<R>(self, test: impl FnOnce() -> R) -> R {
+		self.build().execute_with(|| {
+			let header = Header::new(
+				1,
+				Default::default(),
+				Default::default(),
+				System::block_hash(0),
+				Default::default(),
+			);
+			Executive::initialize_block(&header);
+			test()
+		})
+	}
+}
+
+/// The signed extensions, for `nonce` and `tip`, of an immortal transaction.
+pub fn extra(nonce: Index, tip: Balance) -> SignedExtra {
+	(
+		frame_system::CheckNonZeroSender::<Runtime>::new(),
+		frame_system::CheckSpecVersion::<Runtime>::new(),
+		frame_system::CheckTxVersion::<Runtime>::new(),
+		frame_system::CheckGenesis::<Runtime>::new(),
+		frame_system::CheckEra::<Runtime>::from(Era::Immortal),
+		frame_system::CheckNonce::<Runtime>::from(nonce),
+		frame_system::CheckWeight::<Runtime>::new(),
+		pallet_transaction_payment::ChargeTransactionPayment::<Runtime>::from(tip),
+	)
+}
+
+/// Sign `call` for `signer`, over `extra` and the given implicit data.
+pub fn sign_with(
+	signer: Sr25519Keyring,
+	call: impl Into<Call>,
+	extra: SignedExtra,
+	additional: <SignedExtra as SignedExtension>::AdditionalSigned,
+) -> UncheckedExtrinsic {
+	let call = call.into();
+	let payload = SignedPayload::from_raw(call.clone(), extra.clone(), additional);
+	let signature = payload.using_encoded(|payload| signer.sign(payload));
+	UncheckedExtrinsic::new_signed(
+		call,
+		signer.to_account_id().into(),
+		Signature::Sr25519(signature),
+		extra,
+	)
+}
+
+/// Sign `call` for `signer` with `nonce` and no tip, as a wallet would for the current chain.
+pub fn sign(signer: Sr25519Keyring, nonce: Index, call: impl Into<Call>) -> UncheckedExtrinsic {
+	let extra = extra(nonce, 0);
+	let additional = extra.additional_signed().unwrap();
+	sign_with(signer, call, extra, additional)
+}
+
+/// The length `Executive` charges `extrinsic` for.
+pub fn length(extrinsic: &UncheckedExtrinsic) -> u32 {
+	extrinsic.encode().len() as u32
+}
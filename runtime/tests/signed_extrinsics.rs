@@ -0,0 +1,225 @@
+//! Signed extrinsics applied through `Executive`, on the production configuration.
+
+mod common;
+
+use common::*;
+use frame_support::weights::{DispatchClass, GetDispatchInfo};
+use node_template_runtime::{
+	AccountId, Balance, Balances, BalancesCall, BlockLength, BlockWeights, Event, Executive,
+	ExtrinsicBaseWeight, Runtime, Sudo, SudoCall, System, SystemCall, TransactionByteFee,
+	TransactionPayment, UncheckedExtrinsic, WeightToFee, MILLIUNIT, UNIT,
+};
+use pallet_transaction_payment::InclusionFee;
+use sp_core::H256;
+use sp_keyring::Sr25519Keyring::{self, Alice, Bob, Charlie, Dave};
+use sp_runtime::{
+	traits::SignedExtension,
+	transaction_validity::{InvalidTransaction, TransactionValidityError},
+	ApplyExtrinsicResult,
+};
+
+fn transfer(to: Sr25519Keyring, value: Balance) -> BalancesCall<Runtime> {
+	BalancesCall::transfer { dest: to.to_account_id().into(), value }
+}
+
+fn free_balance(account: Sr25519Keyring) -> Balance {
+	Balances::free_balance(account.to_account_id())
+}
+
+fn invalid(invalid: InvalidTransaction) -> ApplyExtrinsicResult {
+	Err(TransactionValidityError::Invalid(invalid))
+}
+
+/// The fee `ChargeTransactionPayment` withdraws for `extrinsic`, before any refund.
+fn fee(extrinsic: &UncheckedExtrinsic, tip: Balance) -> Balance {
+	TransactionPayment::compute_fee(length(extrinsic), &extrinsic.get_dispatch_info(), tip)
+}
+
+fn withdrawn(who: AccountId) -> Vec<Balance> {
+	System::events()
+		.into_iter()
+		.filter_map(|record| match record.event {
+			Event::Balances(pallet_balances::Event::Withdraw { who: w, amount }) if w == who =>
+				Some(amount),
+			_ => None,
+		})
+		.collect()
+}
+
+#[test]
+fn fees_are_the_base_length_and_weight_fees() {
+	ExtBuilder::default().execute(|| {
+		let extrinsic = sign(Alice, 0, transfer(Bob, UNIT));
+		let info = extrinsic.get_dispatch_info();
+		let weight_to_fee = <WeightToFee as frame_support::weights::WeightToFee>::weight_to_fee;
+
+		let details =
+			TransactionPayment::compute_fee_details(length(&extrinsic), &info, 0).inclusion_fee;
+		assert_eq!(
+			details,
+			Some(InclusionFee {
+				base_fee: weight_to_fee(&ExtrinsicBaseWeight::get()),
+				len_fee: length(&extrinsic) as Balance * TransactionByteFee::get(),
+				// The multiplier starts at one.
+				adjusted_weight_fee: weight_to_fee(&info.weight),
+			})
+		);
+		// A transfer costs a few milliunits.
+		let fee = fee(&extrinsic, 0);
+		assert!((MILLIUNIT..10 * MILLIUNIT).contains(&fee), "fee was {}", fee);
+	});
+}
+
+#[test]
+fn a_signed_transfer_pays_its_fee_which_is_burned() {
+	ExtBuilder::default().execute(|| {
+		let extrinsic = sign(Alice, 0, transfer(Bob, 10 * UNIT));
+		let fee = fee(&extrinsic, 0);
+		let issuance = Balances::total_issuance();
+
+		assert_eq!(Executive::apply_extrinsic(extrinsic), Ok(Ok(())));
+
+		assert_eq!(free_balance(Alice), INITIAL_BALANCE - 10 * UNIT - fee);
+		assert_eq!(free_balance(Bob), INITIAL_BALANCE + 10 * UNIT);
+		assert_eq!(Balances::total_issuance(), issuance - fee);
+		assert_eq!(System::account_nonce(Alice.to_account_id()), 1);
+		assert_eq!(withdrawn(Alice.to_account_id()), vec![fee]);
+		assert!(System::events().iter().any(|record| record.event ==
+			Event::Balances(pallet_balances::Event::Transfer {
+				from: Alice.to_account_id(),
+				to: Bob.to_account_id(),
+				amount: 10 * UNIT,
+			})));
+	});
+}
+
+#[test]
+fn tips_are_charged_on_top_of_the_fee() {
+	ExtBuilder::default().execute(|| {
+		let extra = extra(0, UNIT);
+		let additional = extra.additional_signed().unwrap();
+		let extrinsic = sign_with(Alice, transfer(Bob, UNIT), extra, additional);
+		let untipped = fee(&sign(Alice, 0, transfer(Bob, UNIT)), 0);
+		assert_eq!(fee(&extrinsic, UNIT), untipped + UNIT);
+
+		assert_eq!(Executive::apply_extrinsic(extrinsic), Ok(Ok(())));
+		assert_eq!(free_balance(Alice), INITIAL_BALANCE - UNIT - untipped - UNIT);
+	});
+}
+
+#[test]
+fn failed_calls_pay_their_fee_and_use_their_nonce() {
+	ExtBuilder::default().execute(|| {
+		let extrinsic = sign(Alice, 0, transfer(Bob, 2 * INITIAL_BALANCE));
+		let fee = fee(&extrinsic, 0);
+
+		assert!(matches!(Executive::apply_extrinsic(extrinsic), Ok(Err(_))));
+
+		assert_eq!(free_balance(Alice), INITIAL_BALANCE - fee);
+		assert_eq!(free_balance(Bob), INITIAL_BALANCE);
+		assert_eq!(System::account_nonce(Alice.to_account_id()), 1);
+	});
+}
+
+#[test]
+fn nonces_are_checked() {
+	ExtBuilder::default().execute(|| {
+		let future = sign(Alice, 1, transfer(Bob, UNIT));
+		assert_eq!(Executive::apply_extrinsic(future), invalid(InvalidTransaction::Future));
+
+		let current = sign(Alice, 0, transfer(Bob, UNIT));
+		assert_eq!(Executive::apply_extrinsic(current.clone()), Ok(Ok(())));
+		assert_eq!(Executive::apply_extrinsic(current), invalid(InvalidTransaction::Stale));
+	});
+}
+
+#[test]
+fn transactions_signed_for_another_runtime_or_chain_are_rejected() {
+	ExtBuilder::default().execute(|| {
+		let extra = extra(0, 0);
+		let (a, spec_version, transaction_version, genesis, era, b, c, d) =
+			extra.additional_signed().unwrap();
+
+		let other_runtime = (a, spec_version + 1, transaction_version, genesis, era, b, c, d);
+		let extrinsic = sign_with(Alice, transfer(Bob, UNIT), extra.clone(), other_runtime);
+		assert_eq!(Executive::apply_extrinsic(extrinsic), invalid(InvalidTransaction::BadProof));
+
+		let other_genesis = H256::repeat_byte(1);
+		let other_chain =
+			(a, spec_version, transaction_version, other_genesis, other_genesis, b, c, d);
+		let extrinsic = sign_with(Alice, transfer(Bob, UNIT), extra, other_chain);
+		assert_eq!(Executive::apply_extrinsic(extrinsic), invalid(InvalidTransaction::BadProof));
+	});
+}
+
+#[test]
+fn accounts_that_cannot_pay_the_fee_are_rejected() {
+	ExtBuilder::default().balance(Charlie, MILLIUNIT).execute(|| {
+		let unendowed = sign(Dave, 0, transfer(Bob, 0));
+		assert_eq!(Executive::apply_extrinsic(unendowed), invalid(InvalidTransaction::Payment));
+
+		let poor = sign(Charlie, 0, transfer(Bob, 0));
+		assert!(fee(&poor, 0) > MILLIUNIT);
+		assert_eq!(Executive::apply_extrinsic(poor), invalid(InvalidTransaction::Payment));
+	});
+}
+
+#[test]
+fn check_weight_counts_and_limits_normal_extrinsics() {
+	ExtBuilder::default().execute(|| {
+		let extrinsic = sign(Alice, 0, transfer(Bob, UNIT));
+		let weight = extrinsic.get_dispatch_info().weight;
+		let normal = || *System::block_weight().get(DispatchClass::Normal);
+		let before = normal();
+
+		assert_eq!(Executive::apply_extrinsic(extrinsic), Ok(Ok(())));
+		assert_eq!(normal(), before + weight + ExtrinsicBaseWeight::get());
+
+		let max_normal = BlockWeights::get().get(DispatchClass::Normal).max_total.unwrap();
+		System::set_block_consumed_resources(max_normal, 0);
+		let extrinsic = sign(Alice, 1, transfer(Bob, UNIT));
+		assert_eq!(
+			Executive::apply_extrinsic(extrinsic),
+			invalid(InvalidTransaction::ExhaustsResources)
+		);
+	});
+}
+
+#[test]
+fn check_weight_limits_the_length_of_normal_extrinsics() {
+	ExtBuilder::default().execute(|| {
+		let max_normal = *BlockLength::get().max.get(DispatchClass::Normal) as usize;
+		let extrinsic = sign(Alice, 0, SystemCall::remark { remark: vec![0; max_normal] });
+		assert_eq!(
+			Executive::apply_extrinsic(extrinsic),
+			invalid(InvalidTransaction::ExhaustsResources)
+		);
+	});
+}
+
+#[test]
+fn only_the_sudo_key_dispatches_as_root_for_free() {
+	ExtBuilder::default().sudo(Some(Bob)).execute(|| {
+		assert_eq!(Sudo::key(), Some(Bob.to_account_id()));
+		let set_balance = || SudoCall::sudo {
+			call: Box::new(
+				BalancesCall::set_balance {
+					who: Charlie.to_account_id().into(),
+					new_free: UNIT,
+					new_reserved: 0,
+				}
+				.into(),
+			),
+		};
+
+		let extrinsic = sign(Alice, 0, set_balance());
+		let fee = fee(&extrinsic, 0);
+		assert!(matches!(Executive::apply_extrinsic(extrinsic), Ok(Err(_))));
+		assert_eq!(free_balance(Alice), INITIAL_BALANCE - fee);
+		assert_eq!(free_balance(Charlie), INITIAL_BALANCE);
+
+		assert_eq!(Executive::apply_extrinsic(sign(Bob, 0, set_balance())), Ok(Ok(())));
+		assert_eq!(free_balance(Charlie), UNIT);
+		assert_eq!(free_balance(Bob), INITIAL_BALANCE);
+	});
+}